@@ -27,8 +27,14 @@ pub struct AuthConfig {
     // -- Crypt
     pub PWD_KEY: Vec<u8>,
 
+    // NOTE: Access (web) token. Short-lived, not re-issued on each request.
     pub TOKEN_KEY: Vec<u8>,
     pub TOKEN_DURATION_SEC: f64,
+
+    // NOTE: U: Refresh token. Long-lived and single-use (see /api/refresh),
+    // signed with its own key so it can never be used as an access token.
+    pub REFRESH_TOKEN_KEY: Vec<u8>,
+    pub REFRESH_TOKEN_DURATION_SEC: f64,
 }
 
 impl AuthConfig {
@@ -39,6 +45,9 @@ impl AuthConfig {
 
             TOKEN_KEY: get_env_base64url_as_u8s("SERVICE_TOKEN_KEY")?,
             TOKEN_DURATION_SEC: get_env_parse("SERVICE_TOKEN_DURATION_SEC")?,
+
+            REFRESH_TOKEN_KEY: get_env_base64url_as_u8s("SERVICE_REFRESH_TOKEN_KEY")?,
+            REFRESH_TOKEN_DURATION_SEC: get_env_parse("SERVICE_REFRESH_TOKEN_DURATION_SEC")?,
         })
     }
 }
@@ -150,6 +150,7 @@ impl FromStr for PwdParts {
 
 // region:       -- Tests
 
+#[cfg(test)]
 mod tests {
     pub type Result<T> = core::result::Result<T, Error>;
     pub type Error = Box<dyn std::error::Error>; // For tests
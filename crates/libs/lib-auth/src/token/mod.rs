@@ -24,7 +24,7 @@ use uuid::Uuid;
 /// String format: `identifier_b64u.expiration_b64u.signature_b64u`
 // NOTE: Signature is already b64u because we just want to match it
 // REF: https://youtu.be/3cA_mk4vdWY?t=9346
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(test, derive(PartialEq))]
 pub struct Token {
     pub ident: String,     // Identifier (e.g., username).
@@ -82,6 +82,29 @@ pub fn validate_web_token(origin_token: &Token, salt: Uuid) -> Result<()> {
 
 // endregion:    -- Web Token Gen & Validation
 
+// region:       -- Refresh Token Gen & Validation
+// NOTE: Same Token format, but the ident is the refresh token id (jti) persisted
+// in the refresh_token table, and it is signed with the REFRESH_TOKEN_KEY.
+
+pub fn generate_refresh_token(jti: &str, salt: Uuid) -> Result<Token> {
+    let config = &auth_config();
+    _generate_token(
+        jti,
+        config.REFRESH_TOKEN_DURATION_SEC,
+        salt,
+        &config.REFRESH_TOKEN_KEY,
+    )
+}
+
+pub fn validate_refresh_token(origin_token: &Token, salt: Uuid) -> Result<()> {
+    let config = &auth_config();
+    _validate_token_sign_and_exp(origin_token, salt, &config.REFRESH_TOKEN_KEY)?;
+
+    Ok(())
+}
+
+// endregion:    -- Refresh Token Gen & Validation
+
 // region:       -- (private) Token Gen & Validation
 // NOTE: Here we don't know the specifics of the web token
 
@@ -154,7 +177,7 @@ mod tests {
 
     use super::*;
     use crate::token;
-    use std::{fmt::format, thread, time::Duration};
+    use std::{thread, time::Duration};
 
     // NOTE: TIP: For testing `impl Display for Token`, go ahead
     // and create a test at the same time and print what
@@ -248,5 +271,27 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_validate_refresh_token_err_wrong_key() -> Result<()> {
+        // -- Setup & Fixtures
+        let fx_jti = "f8a0c0a4-4f0e-4f57-a5b1-8d39d3b4a8f0";
+        let fx_salt = Uuid::parse_str("f05e8961-d6ad-4086-9e78-a6de065e5453").unwrap();
+        let fx_refresh_token = generate_refresh_token(fx_jti, fx_salt)?;
+
+        // -- Exec
+        let refresh_res = validate_refresh_token(&fx_refresh_token, fx_salt);
+        // A refresh token can never be used as an access (web) token
+        let web_res = validate_web_token(&fx_refresh_token, fx_salt);
+
+        // -- Check
+        refresh_res?;
+        assert!(
+            matches!(web_res, Err(token::Error::SignatureNotMatching)),
+            "Should have matched `Err(Error::SignatureNotMatching)` but was `{web_res:?}`"
+        );
+
+        Ok(())
+    }
 }
 // endregion:    -- Tests
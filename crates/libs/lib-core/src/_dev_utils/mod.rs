@@ -1,9 +1,13 @@
 mod dev_db;
 
+pub use dev_db::base_dir;
+
 // NOTE: OnceLock is not for async. We need OnceCell that
 // supports async closure with its get_or_init()
-use simple_fs::{ensure_dir, read_to_string, SFile, SPath};
-use std::{collections::HashMap, fs, path::Path};
+use modql::filter::ListOptions;
+use serde_json::json;
+use simple_fs::{ensure_dir, read_to_string};
+use std::path::Path;
 use tokio::sync::OnceCell;
 use tracing::info;
 
@@ -11,8 +15,9 @@ use crate::{
     ctx::Ctx,
     model::{
         self,
+        base::LIST_LIMIT_MAX,
         task::{Task, TaskBmc, TaskForCreate},
-        token::{BirdeyeRootResponse, BirdeyeTokenResponse, Token, TokenBmc, TokenForCreate},
+        token::{BirdeyeRootResponse, Token, TokenBmc, TokenFilter},
         ModelManager,
     },
 };
@@ -22,10 +27,6 @@ use crate::{
 // into struct. Then I could seed_tokens() and continue adding/updating
 // tests.
 
-const JAN_01_UNIX: i64 = 1704080350;
-const JAN_01_HUMAN: &str = "Jan 01 2024";
-const MAR_10_UNIX: i64 = 1710041950;
-const MAR_10_HUMAN: &str = "Mar 10 2024";
 // WARN: Troubleshooting dir locations:
 // "_mock_data" ->> lib-core/_mock_data/
 // "src/_dev_utils/_mock_data/" ->> lib-core/src/_dev_utils/_mock_data/
@@ -63,6 +64,12 @@ pub async fn init_test() -> ModelManager {
     mm.clone()
 }
 
+/// Initialize a test ModelManager without the dev db (lazy db pool),
+/// for the tests that never run a query (e.g., the lib-rpc router tests).
+pub fn init_test_no_db() -> ModelManager {
+    ModelManager::new_lazy().unwrap()
+}
+
 /// Seed tasks table for testing
 pub async fn seed_tasks(ctx: &Ctx, mm: &ModelManager, titles: &[&str]) -> model::Result<Vec<Task>> {
     // It's okay for our dev_utils to have a dependency on our model layer,
@@ -103,7 +110,6 @@ pub async fn seed_tokens(ctx: &Ctx, mm: &ModelManager) -> model::Result<Vec<Toke
     // generic serde_json::Value? Dunno. This would go back to how to deser from Value.
     // My guess is to use serde_json::from_value() and then specify Vec<BirdeyeTokenResponse>
     // A: Not worth it. Keep it clear with the
-    let tokens: Vec<BirdeyeTokenResponse> = root.data.tokens; // Works
 
     // Q: Any way to quickly seed some token details?
     // U: I used https://docs.birdeye.so/reference/get_defi-tokenlist API to fetch
@@ -112,33 +118,24 @@ pub async fn seed_tokens(ctx: &Ctx, mm: &ModelManager) -> model::Result<Vec<Toke
     // Q: After adding #[serde(flatten)] timestamp: TimeStamp, how can I add shared
     // timestamp data to EACH single BirdeyeTokenResponse? If I do nothing, it errors
     // because of missing fields 'updateUnixTime' not found.
-    // U: Have to pull from BirdeyeDataResponse for now. Also, need to unwrap the
-    // v24h_change_percent Option<f64>, since you can't store an Option type inside PG database.
-    let mut result = Vec::new();
-    for token in tokens {
-        let id = TokenBmc::create(
-            ctx,
-            mm,
-            TokenForCreate {
-                update_unix_time: root.data.update_unix_time,
-                update_time: root.data.update_time.to_string(),
-                address: token.address,
-                decimals: token.decimals,
-                symbol: token.symbol,
-                name: token.name,
-                mc: token.mc,
-                v24h_change_percent: token.v24h_change_percent.unwrap_or_default(),
-                v24h_usd: token.v24h_usd,
-                liquidity: token.liquidity,
-                logo_uri: token.logo_uri,
-                last_trade_unix_time: token.last_trade_unix_time,
-            },
-        )
-        .await?;
-
-        let token_c = TokenBmc::get(ctx, mm, id).await?;
-
-        result.push(token_c)
+    // U: Have to pull from BirdeyeDataResponse for now (see BirdeyeDataResponse::into_token_cs,
+    // also used by the token-ingest service).
+    // U: Seeding with one multi-rows upsert (base::upsert_many), so seeding
+    // again (e.g., each test) updates the existing tokens (unique address).
+    let token_cs = root.data.into_token_cs();
+    let ids = TokenBmc::upsert_many(ctx, mm, token_cs).await?;
+
+    // -- Get the seeded tokens back (ordered by id, not by the data order)
+    let mut result = Vec::with_capacity(ids.len());
+    for ids in ids.chunks(LIST_LIMIT_MAX as usize) {
+        let filters: Vec<TokenFilter> = serde_json::from_value(json!([{ "id": { "$in": ids } }]))
+            .map_err(model::Error::SerdeJson)?;
+        let list_options = ListOptions {
+            limit: Some(LIST_LIMIT_MAX),
+            order_bys: Some("id".into()),
+            ..Default::default()
+        };
+        result.extend(TokenBmc::list(ctx, mm, Some(filters), Some(list_options)).await?);
     }
 
     Ok(result)
@@ -1,4 +1,4 @@
-use lib_utils::envs::get_env;
+use lib_utils::envs::{get_env, get_env_parse_or};
 use std::sync::OnceLock;
 
 // NOTE: We don't want to reload the CoreConfig ENV again and again.
@@ -26,6 +26,8 @@ pub fn core_config() -> &'static CoreConfig {
 pub struct CoreConfig {
     // -- Db
     pub DB_URL: String,
+    pub DB_MIGRATE: bool,
+    pub DB_MIGRATIONS_DIR: String,
 
     // -- Web
     pub WEB_FOLDER: String,
@@ -36,6 +38,13 @@ impl CoreConfig {
         Ok(CoreConfig {
             // -- Db
             DB_URL: get_env("SERVICE_DB_URL")?,
+            // NOTE: Migrations are opt-in (e.g., SERVICE_DB_MIGRATE=true for deployed
+            // services). Dev/tests get their schema from _dev_utils::init_dev_db.
+            DB_MIGRATE: get_env_parse_or("SERVICE_DB_MIGRATE", false)?,
+            DB_MIGRATIONS_DIR: get_env_parse_or(
+                "SERVICE_DB_MIGRATIONS_DIR",
+                "sql/migrations".to_string(),
+            )?,
 
             // -- Web
             // Ideally don't use unwrap().
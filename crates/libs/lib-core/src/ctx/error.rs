@@ -12,6 +12,8 @@ pub type Result<T> = core::result::Result<T, Error>;
 #[derive(Debug, Serialize)]
 pub enum Error {
     CtxCannotNewRootCtx,
+    CtxNotAdmin { user_id: i64 },
+    RoleUnknown(String),
 }
 
 // region:    --- Error Boilerplate
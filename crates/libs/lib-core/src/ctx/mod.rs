@@ -1,6 +1,8 @@
 pub mod error;
 
 pub use self::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 // NOTE: Extractors at a high level is something that implements
 // FromRequest or FromRequestParts. This allows the extractor to
 // take parts (or whole) of the request, and turn into something
@@ -20,27 +22,110 @@ pub use self::error::{Error, Result};
 #[derive(Clone, Debug)]
 pub struct Ctx {
     user_id: i64,
+    role: Role,
+    all_owners: bool,
 }
 
 impl Ctx {
     pub fn root_ctx() -> Self {
-        Ctx { user_id: 0 }
+        Ctx {
+            user_id: 0,
+            role: Role::Admin,
+            all_owners: true,
+        }
     }
     // NOTE: user_id is immutable, but we could add
     // mutable props (e.g., access level) later on
     // Constructor:
     pub fn new(user_id: i64) -> Result<Self> {
+        Self::new_with_role(user_id, Role::User)
+    }
+
+    // NOTE: U: The role comes from the "user".role column (see mw_ctx_resolve)
+    pub fn new_with_role(user_id: i64, role: Role) -> Result<Self> {
         if user_id == 0 {
             Err(Error::CtxCannotNewRootCtx)
         } else {
-            Ok(Self { user_id })
+            Ok(Self {
+                user_id,
+                role,
+                all_owners: false,
+            })
         }
     }
 
+    /// The same admin Ctx, but not scoped to the admin's own rows
+    // NOTE: Only for the admin rpc methods that need cross-user access
+    // (e.g., delete_token). The user_id stays the actor (mid, audit log).
+    pub fn for_all_owners(&self) -> Result<Self> {
+        if !self.has_role(Role::Admin) {
+            return Err(Error::CtxNotAdmin {
+                user_id: self.user_id,
+            });
+        }
+
+        Ok(Self {
+            all_owners: true,
+            ..self.clone()
+        })
+    }
+
     // Property Accessors:
     pub fn user_id(&self) -> i64 {
         // This way nobody can change user_id of a Ctx that
         // is not within this module
         self.user_id
     }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// True when the Ctx role is at least the given role (e.g., Admin has all User rights)
+    pub fn has_role(&self, role: Role) -> bool {
+        self.role >= role
+    }
+
+    /// True for the root Ctx (system/admin access, not scoped to a user)
+    pub fn is_root(&self) -> bool {
+        self.user_id == 0
+    }
+
+    /// True when the Ctx is not scoped to its own rows (root, or see for_all_owners)
+    pub fn is_all_owners(&self) -> bool {
+        self.all_owners
+    }
+}
+
+// region:    -- Role
+/// Access level of a Ctx, stored in the "user".role column.
+// NOTE: Variants are ordered from the least to the most privileged,
+// so Ctx::has_role() can simply compare them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "user" => Ok(Role::User),
+            "admin" => Ok(Role::Admin),
+            _ => Err(Error::RoleUnknown(s.to_string())),
+        }
+    }
 }
+// endregion: -- Role
@@ -0,0 +1,525 @@
+// NOTE: Token price alerts. A user's AlertRule (a threshold on a token metric)
+// is evaluated after each token write (TokenBmc update/upsert, so each
+// ingestion batch too), and each fired rule is recorded as an AlertEvent
+// (see sql/migrations/0007-alert.sql).
+use crate::ctx::Ctx;
+use crate::model::base::{self, CommonIden, DbBmc, LIST_LIMIT_MAX};
+use crate::model::token::{TokenBmc, TokenRef};
+use crate::model::ModelManager;
+use crate::model::{ListPage, Result};
+use lib_utils::time::{now_utc, Rfc3339};
+use modql::field::Fields;
+use modql::filter::{FilterNodes, ListOptions, OpValsInt64, OpValsString};
+use sea_query::{Expr, Iden, IntoIden, LockType, PostgresQueryBuilder, Query};
+use sea_query_binder::SqlxBinder;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use sqlx::FromRow;
+use time::{Duration, OffsetDateTime};
+
+// region: -- Alert Types
+/// The token metric of an alert rule (the token column name)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertMetric {
+    Mc,
+    Liquidity,
+    V24hChangePercent,
+    V24hUsd,
+}
+
+impl AlertMetric {
+    const ALL: [AlertMetric; 4] = [
+        AlertMetric::Mc,
+        AlertMetric::Liquidity,
+        AlertMetric::V24hChangePercent,
+        AlertMetric::V24hUsd,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertMetric::Mc => "mc",
+            AlertMetric::Liquidity => "liquidity",
+            AlertMetric::V24hChangePercent => "v24h_change_percent",
+            AlertMetric::V24hUsd => "v24h_usd",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|metric| metric.as_str() == name)
+    }
+}
+
+/// How the metric value compares to the rule threshold to fire
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertComparator {
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl AlertComparator {
+    const ALL: [AlertComparator; 4] = [
+        AlertComparator::Gt,
+        AlertComparator::Gte,
+        AlertComparator::Lt,
+        AlertComparator::Lte,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertComparator::Gt => "gt",
+            AlertComparator::Gte => "gte",
+            AlertComparator::Lt => "lt",
+            AlertComparator::Lte => "lte",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|cmp| cmp.as_str() == name)
+    }
+
+    fn matches(&self, value: f64, threshold: f64) -> bool {
+        match self {
+            AlertComparator::Gt => value > threshold,
+            AlertComparator::Gte => value >= threshold,
+            AlertComparator::Lt => value < threshold,
+            AlertComparator::Lte => value <= threshold,
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Fields, FromRow, Serialize)]
+pub struct AlertRule {
+    pub id: i64,
+    pub token_id: i64,
+    pub metric: String,
+    pub comparator: String,
+    pub threshold: f64,
+    pub cooldown_sec: i64,
+    #[serde_as(as = "Option<Rfc3339>")]
+    pub last_triggered_at: Option<OffsetDateTime>,
+
+    // -- Ownership (filled from the Ctx in base::create)
+    pub owner_id: i64,
+
+    // -- Timestamps (stamped in base::create/update)
+    pub cid: i64,
+    #[serde_as(as = "Rfc3339")]
+    pub ctime: OffsetDateTime,
+    pub mid: i64,
+    #[serde_as(as = "Rfc3339")]
+    pub mtime: OffsetDateTime,
+}
+
+/// The rule token by id or address, e.g.,
+/// `{"token_id": 1000, "metric": "mc", "comparator": "gt", "threshold": 1e9}`
+#[derive(Deserialize)]
+pub struct AlertRuleForCreate {
+    #[serde(flatten)]
+    pub token: TokenRef,
+    pub metric: AlertMetric,
+    pub comparator: AlertComparator,
+    pub threshold: f64,
+    /// Default ALERT_COOLDOWN_SEC_DEFAULT
+    pub cooldown_sec: Option<u32>,
+}
+
+#[derive(Fields)]
+struct AlertRuleForInsert {
+    token_id: i64,
+    metric: String,
+    comparator: String,
+    threshold: f64,
+    cooldown_sec: u32,
+}
+
+#[derive(Fields, Default, Deserialize)]
+pub struct AlertRuleForUpdate {
+    pub threshold: Option<f64>,
+    pub cooldown_sec: Option<u32>,
+}
+
+#[derive(FilterNodes, Deserialize, Default, Debug)]
+pub struct AlertRuleFilter {
+    id: Option<OpValsInt64>,
+
+    token_id: Option<OpValsInt64>,
+    metric: Option<OpValsString>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Fields, FromRow, Serialize)]
+pub struct AlertEvent {
+    pub id: i64,
+    pub rule_id: i64,
+    pub token_id: i64,
+    pub owner_id: i64,
+
+    pub metric: String,
+    pub comparator: String,
+    pub threshold: f64,
+    /// The metric value that fired the rule
+    pub value: f64,
+
+    #[serde_as(as = "Rfc3339")]
+    pub ctime: OffsetDateTime,
+}
+
+#[derive(FilterNodes, Deserialize, Default, Debug)]
+pub struct AlertEventFilter {
+    id: Option<OpValsInt64>,
+
+    rule_id: Option<OpValsInt64>,
+    token_id: Option<OpValsInt64>,
+}
+
+/// A rule to evaluate, with the current metrics of its token
+#[derive(FromRow)]
+struct AlertCandidate {
+    id: i64,
+    token_id: i64,
+    owner_id: i64,
+    metric: String,
+    comparator: String,
+    threshold: f64,
+    cooldown_sec: i64,
+    last_triggered_at: Option<OffsetDateTime>,
+
+    liquidity: f64,
+    mc: f64,
+    v24h_change_percent: f64,
+    v24h_usd: f64,
+}
+
+impl AlertCandidate {
+    /// The metric value when the rule fires (threshold crossed & not in cooldown)
+    fn fired_value(&self, now: OffsetDateTime) -> Option<f64> {
+        let in_cooldown = matches!(
+            self.last_triggered_at,
+            Some(last) if now - last < Duration::seconds(self.cooldown_sec)
+        );
+        if in_cooldown {
+            return None;
+        }
+
+        let value = match AlertMetric::from_name(&self.metric)? {
+            AlertMetric::Mc => self.mc,
+            AlertMetric::Liquidity => self.liquidity,
+            AlertMetric::V24hChangePercent => self.v24h_change_percent,
+            AlertMetric::V24hUsd => self.v24h_usd,
+        };
+        let comparator = AlertComparator::from_name(&self.comparator)?;
+
+        comparator.matches(value, self.threshold).then_some(value)
+    }
+}
+
+#[derive(Iden, Clone, Copy)]
+enum AlertIden {
+    #[iden = "alert_rule"]
+    RuleTable,
+    #[iden = "token"]
+    TokenTable,
+    TokenId,
+    RuleId,
+    Metric,
+    Comparator,
+    Threshold,
+    CooldownSec,
+    LastTriggeredAt,
+    Value,
+    Liquidity,
+    Mc,
+    V24hChangePercent,
+    V24hUsd,
+}
+
+pub const ALERT_COOLDOWN_SEC_DEFAULT: u32 = 3600;
+// endregion: -- Alert Types
+
+// region: -- AlertRuleBmc
+pub struct AlertRuleBmc;
+
+impl DbBmc for AlertRuleBmc {
+    const TABLE: &'static str = "alert_rule";
+    const OWNED: bool = true;
+    const TIMESTAMPED: bool = true;
+    const AUDITED: bool = true;
+}
+
+impl AlertRuleBmc {
+    pub async fn create(ctx: &Ctx, mm: &ModelManager, rule_c: AlertRuleForCreate) -> Result<i64> {
+        let AlertRuleForCreate {
+            token,
+            metric,
+            comparator,
+            threshold,
+            cooldown_sec,
+        } = rule_c;
+
+        let rule_fi = AlertRuleForInsert {
+            token_id: TokenBmc::resolve_id(ctx, mm, token).await?,
+            metric: metric.as_str().to_string(),
+            comparator: comparator.as_str().to_string(),
+            threshold,
+            cooldown_sec: cooldown_sec.unwrap_or(ALERT_COOLDOWN_SEC_DEFAULT),
+        };
+
+        base::create::<Self, _>(ctx, mm, rule_fi).await
+    }
+
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<AlertRule> {
+        base::get::<Self, _>(ctx, mm, id).await
+    }
+
+    pub async fn list(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<AlertRuleFilter>>,
+        list_options: Option<ListOptions>,
+    ) -> Result<Vec<AlertRule>> {
+        base::list::<Self, _, _>(ctx, mm, filters, list_options).await
+    }
+
+    pub async fn update(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        rule_u: AlertRuleForUpdate,
+    ) -> Result<()> {
+        base::update::<Self, _>(ctx, mm, id, rule_u).await
+    }
+
+    /// Delete the rule (its events are deleted by cascade)
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        base::delete::<Self>(ctx, mm, id).await
+    }
+
+    /// Evaluate the rules of the tokens (after a token write), and record an
+    /// AlertEvent for each fired rule. Returns the AlertEvent ids.
+    // NOTE: The rules of all the users are evaluated (whatever the Ctx, which is
+    // the token writer). The rules are locked (FOR UPDATE), so two concurrent
+    // evaluations cannot fire the same rule twice within its cooldown.
+    pub async fn evaluate(_ctx: &Ctx, mm: &ModelManager, token_ids: &[i64]) -> Result<Vec<i64>> {
+        let mm = &mm.new_with_txn()?;
+        mm.begin_txn().await?;
+
+        let dbx = mm.dbx();
+        let now = now_utc();
+        let mut event_ids = Vec::new();
+
+        for token_ids in token_ids.chunks(LIST_LIMIT_MAX as usize) {
+            // -- Select the candidate rules (with their token metrics)
+            let (rule_table, token_table) = (AlertIden::RuleTable, AlertIden::TokenTable);
+            let mut query = Query::select();
+            query
+                .from(Self::table_ref())
+                .column((rule_table, CommonIden::Id))
+                .column((rule_table, CommonIden::OwnerId))
+                .columns(
+                    [
+                        AlertIden::TokenId,
+                        AlertIden::Metric,
+                        AlertIden::Comparator,
+                        AlertIden::Threshold,
+                        AlertIden::CooldownSec,
+                        AlertIden::LastTriggeredAt,
+                    ]
+                    .map(|col| (rule_table, col)),
+                )
+                .columns(
+                    [
+                        AlertIden::Liquidity,
+                        AlertIden::Mc,
+                        AlertIden::V24hChangePercent,
+                        AlertIden::V24hUsd,
+                    ]
+                    .map(|col| (token_table, col)),
+                )
+                .inner_join(
+                    TokenBmc::table_ref(),
+                    Expr::col((token_table, CommonIden::Id))
+                        .equals((rule_table, AlertIden::TokenId)),
+                )
+                .and_where(
+                    Expr::col((rule_table, AlertIden::TokenId)).is_in(token_ids.iter().copied()),
+                )
+                .lock_with_tables(LockType::Update, [Self::table_ref()]);
+
+            let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+            let candidates = dbx
+                .fetch_all(sqlx::query_as_with::<_, AlertCandidate, _>(&sql, values))
+                .await?;
+
+            let fired: Vec<(AlertCandidate, f64)> = candidates
+                .into_iter()
+                .filter_map(|cand| cand.fired_value(now).map(|value| (cand, value)))
+                .collect();
+            if fired.is_empty() {
+                continue;
+            }
+
+            // -- Record the events
+            let mut query = Query::insert();
+            query.into_table(AlertEventBmc::table_ref()).columns([
+                AlertIden::RuleId.into_iden(),
+                AlertIden::TokenId.into_iden(),
+                AlertIden::Metric.into_iden(),
+                AlertIden::Comparator.into_iden(),
+                AlertIden::Threshold.into_iden(),
+                AlertIden::Value.into_iden(),
+                CommonIden::OwnerId.into_iden(),
+            ]);
+            for (cand, value) in fired.iter() {
+                query.values([
+                    cand.id.into(),
+                    cand.token_id.into(),
+                    cand.metric.as_str().into(),
+                    cand.comparator.as_str().into(),
+                    cand.threshold.into(),
+                    (*value).into(),
+                    cand.owner_id.into(),
+                ])?;
+            }
+            query.returning_col(CommonIden::Id);
+
+            let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+            let ids = dbx
+                .fetch_all(sqlx::query_as_with::<_, (i64,), _>(&sql, values))
+                .await?;
+            event_ids.extend(ids.into_iter().map(|(id,)| id));
+
+            // -- Start the rules cooldown
+            let mut query = Query::update();
+            query
+                .table(Self::table_ref())
+                .value(AlertIden::LastTriggeredAt, now)
+                .and_where(Expr::col(CommonIden::Id).is_in(fired.iter().map(|(cand, _)| cand.id)));
+
+            let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+            dbx.execute(sqlx::query_with(&sql, values)).await?;
+        }
+
+        mm.commit_txn().await?;
+
+        Ok(event_ids)
+    }
+}
+// endregion: -- AlertRuleBmc
+
+// region: -- AlertEventBmc
+pub struct AlertEventBmc;
+
+impl DbBmc for AlertEventBmc {
+    const TABLE: &'static str = "alert_event";
+    const OWNED: bool = true;
+}
+
+impl AlertEventBmc {
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<AlertEvent> {
+        base::get::<Self, _>(ctx, mm, id).await
+    }
+
+    pub async fn list(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<AlertEventFilter>>,
+        list_options: Option<ListOptions>,
+    ) -> Result<Vec<AlertEvent>> {
+        base::list::<Self, _, _>(ctx, mm, filters, list_options).await
+    }
+
+    /// Paged list ({items, total, next_cursor}), see base::list_paged
+    pub async fn list_paged(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<AlertEventFilter>>,
+        list_options: Option<ListOptions>,
+        cursor: Option<String>,
+    ) -> Result<ListPage<AlertEvent>> {
+        base::list_paged::<Self, _, _>(ctx, mm, filters, list_options, cursor).await
+    }
+}
+// endregion: -- AlertEventBmc
+
+// region: -- Tests
+#[cfg(test)]
+mod tests {
+    #![allow(unused)]
+    pub type Result<T> = core::result::Result<T, Error>;
+    pub type Error = Box<dyn std::error::Error>; // For early dev & tests.
+
+    use super::*;
+    use crate::_dev_utils;
+    use crate::model::token::{TokenForCreate, TokenForUpdate};
+    use serial_test::serial;
+
+    #[serial]
+    #[tokio::test]
+    async fn test_evaluate_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::new(1021)?;
+        let ctx_other = Ctx::new(1022)?;
+        let root_ctx = Ctx::root_ctx();
+        let token_id = TokenBmc::create(
+            &root_ctx,
+            &mm,
+            TokenForCreate {
+                address: "test_evaluate_ok-address".to_string(),
+                mc: 5.0,
+                ..Default::default()
+            },
+        )
+        .await?;
+        let fx_rule_c = |cooldown_sec: u32| AlertRuleForCreate {
+            token: TokenRef::Address("test_evaluate_ok-address".to_string()),
+            metric: AlertMetric::Mc,
+            comparator: AlertComparator::Gt,
+            threshold: 10.0,
+            cooldown_sec: Some(cooldown_sec),
+        };
+        let rule_id = AlertRuleBmc::create(&ctx, &mm, fx_rule_c(3600)).await?;
+        let rule_id_no_cooldown = AlertRuleBmc::create(&ctx, &mm, fx_rule_c(0)).await?;
+        let fx_update_mc = |mc: f64| TokenForUpdate {
+            mc: Some(mc),
+            ..Default::default()
+        };
+
+        // -- Exec & Check: below the threshold
+        TokenBmc::update(&root_ctx, &mm, token_id, fx_update_mc(8.0)).await?;
+        assert!(AlertEventBmc::list(&ctx, &mm, None, None).await?.is_empty());
+
+        // -- Exec & Check: crossed, both rules fire
+        TokenBmc::update(&root_ctx, &mm, token_id, fx_update_mc(20.0)).await?;
+        let events = AlertEventBmc::list(&ctx, &mm, None, None).await?;
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].value, 20.0);
+        assert_eq!(events[0].metric, "mc");
+        assert!(AlertRuleBmc::get(&ctx, &mm, rule_id)
+            .await?
+            .last_triggered_at
+            .is_some());
+
+        // -- Exec & Check: still above, only the rule without cooldown fires
+        TokenBmc::update(&root_ctx, &mm, token_id, fx_update_mc(30.0)).await?;
+        let events = AlertEventBmc::list(&ctx, &mm, None, None).await?;
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[2].rule_id, rule_id_no_cooldown);
+
+        // -- Check: the events are the rule owner's
+        assert!(AlertEventBmc::list(&ctx_other, &mm, None, None)
+            .await?
+            .is_empty());
+
+        // -- Clean (rules & events deleted by cascade)
+        TokenBmc::delete(&root_ctx, &mm, token_id).await?;
+
+        Ok(())
+    }
+}
+// endregion: -- Tests
@@ -0,0 +1,364 @@
+// NOTE: Audit log of the entity writes of the rpc calls. The web layer gives the
+// ModelManager an AuditInfo (rpc method & request uuid, see `mm.with_audit()`),
+// and the base create/update/delete (and their bulk/upsert versions) of the
+// `DbBmc::AUDITED` entities record one AuditLog row per written row, in the same
+// txn as the write (see sql/migrations/0008-audit-log.sql).
+// The writes without an AuditInfo (e.g., ingestion, dev seed) are not recorded.
+use crate::ctx::Ctx;
+use crate::model::base::{self, CommonIden, DbBmc};
+use crate::model::modql_utils::time_to_sea_value;
+use crate::model::ModelManager;
+use crate::model::{ListPage, Result};
+use lib_utils::time::Rfc3339;
+use modql::field::Fields;
+use modql::filter::{FilterNodes, ListOptions, OpValsInt64, OpValsString, OpValsValue};
+use sea_query::{Expr, Iden, IntoCondition, IntoIden, LockType, PostgresQueryBuilder, Query};
+use sea_query_binder::SqlxBinder;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serde_with::{serde_as, DisplayFromStr};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+// region: -- Audit Types
+/// The rpc call of the writes (set by the web layer with `mm.with_audit()`)
+#[derive(Debug, Clone)]
+pub struct AuditInfo {
+    pub rpc_method: String,
+    pub req_uuid: Uuid,
+}
+
+/// One audited row write. For an update, before/after only hold the changed
+/// fields, for a create only after (the new row), and for a delete only before.
+#[serde_as]
+#[derive(Debug, Clone, Fields, FromRow, Serialize)]
+pub struct AuditLog {
+    pub id: i64,
+    pub user_id: i64,
+    pub rpc_method: Option<String>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub req_uuid: Option<Uuid>,
+
+    pub entity: String,
+    pub entity_id: i64,
+    pub action: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+
+    #[serde_as(as = "Rfc3339")]
+    pub ctime: OffsetDateTime,
+}
+
+#[derive(FilterNodes, Deserialize, Default, Debug)]
+pub struct AuditLogFilter {
+    id: Option<OpValsInt64>,
+
+    user_id: Option<OpValsInt64>,
+    entity: Option<OpValsString>,
+    entity_id: Option<OpValsInt64>,
+    action: Option<OpValsString>,
+    rpc_method: Option<OpValsString>,
+
+    // NOTE: Time values are given as Rfc3339 strings (e.g., {"$gte": "2024-03-14T08:08:09Z"})
+    #[modql(to_sea_value_fn = "time_to_sea_value")]
+    ctime: Option<OpValsValue>,
+}
+
+#[derive(Iden)]
+enum AuditIden {
+    UserId,
+    RpcMethod,
+    ReqUuid,
+    Entity,
+    EntityId,
+    Action,
+    Before,
+    After,
+}
+
+// NOTE: The secrets are never copied into the audit log, only their change.
+const AUDIT_REDACTED_KEYS: &[&str] = &["pwd", "pwd_salt", "token_salt"];
+const AUDIT_REDACTED_VALUE: &str = "[redacted]";
+
+// NOTE: The modification stamps are not part of an update diff (always changed).
+const AUDIT_DIFF_SKIPPED_KEYS: &[&str] = &["mid", "mtime"];
+// endregion: -- Audit Types
+
+// region: -- AuditBmc
+pub struct AuditBmc;
+
+impl DbBmc for AuditBmc {
+    const TABLE: &'static str = "audit_log";
+}
+
+impl AuditBmc {
+    pub async fn list(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<AuditLogFilter>>,
+        list_options: Option<ListOptions>,
+    ) -> Result<Vec<AuditLog>> {
+        base::list::<Self, _, _>(ctx, mm, filters, list_options).await
+    }
+
+    /// Paged list ({items, total, next_cursor}), see base::list_paged
+    pub async fn list_paged(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<AuditLogFilter>>,
+        list_options: Option<ListOptions>,
+        cursor: Option<String>,
+    ) -> Result<ListPage<AuditLog>> {
+        base::list_paged::<Self, _, _>(ctx, mm, filters, list_options, cursor).await
+    }
+}
+// endregion: -- AuditBmc
+
+// region: -- Base Write Hooks
+// NOTE: Used by the base write fns as:
+// begin (txn) -> snapshot (before) -> write -> snapshot (after) -> record -> commit
+// They are no-ops when the write is not audited.
+
+fn is_audited<MC: DbBmc>(mm: &ModelManager) -> bool {
+    MC::AUDITED && mm.audit_info().is_some()
+}
+
+/// The ModelManager of the write, with an open txn when audited
+pub(in crate::model) async fn begin<MC: DbBmc>(mm: &ModelManager) -> Result<ModelManager> {
+    if !is_audited::<MC>(mm) {
+        return Ok(mm.clone());
+    }
+
+    let mm = mm.new_with_txn()?;
+    mm.begin_txn().await?;
+
+    Ok(mm)
+}
+
+/// Commit the txn opened by `begin`
+pub(in crate::model) async fn commit<MC: DbBmc>(mm: &ModelManager) -> Result<()> {
+    if is_audited::<MC>(mm) {
+        mm.commit_txn().await?;
+    }
+
+    Ok(())
+}
+
+/// The (id, row json) of the rows matching the condition (locked until the commit)
+pub(in crate::model) async fn snapshot<MC, C>(
+    mm: &ModelManager,
+    cond: C,
+) -> Result<Vec<(i64, Value)>>
+where
+    MC: DbBmc,
+    C: IntoCondition,
+{
+    if !is_audited::<MC>(mm) {
+        return Ok(Vec::new());
+    }
+
+    let mut query = Query::select();
+    query
+        .from(MC::table_ref())
+        .column(CommonIden::Id)
+        .expr(Expr::cust(format!(r#"to_jsonb("{}")"#, MC::TABLE)))
+        .cond_where(cond)
+        .lock(LockType::Update);
+
+    let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+    let rows = mm
+        .dbx()
+        .fetch_all(sqlx::query_as_with::<_, (i64, Value), _>(&sql, values))
+        .await?;
+
+    Ok(rows)
+}
+
+/// Record the writes of the rows, from their before/after snapshots
+/// (after only: create, both: update, before only: delete).
+// NOTE: An update that did not change any field is not recorded.
+pub(in crate::model) async fn record<MC: DbBmc>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    before: Vec<(i64, Value)>,
+    after: Vec<(i64, Value)>,
+) -> Result<()> {
+    let Some(audit_info) = mm.audit_info().filter(|_| is_audited::<MC>(mm)) else {
+        return Ok(());
+    };
+
+    // -- The (entity_id, action, before, after) rows
+    let mut writes: Vec<(i64, &str, Option<Value>, Option<Value>)> = Vec::new();
+    for (id, after_row) in after.iter() {
+        match before.iter().find(|(before_id, _)| before_id == id) {
+            None => writes.push((*id, "create", None, Some(redact(after_row.clone())))),
+            Some((_, before_row)) => {
+                let (before_diff, after_diff) = diff(before_row, after_row);
+                if !after_diff.is_empty() {
+                    writes.push((
+                        *id,
+                        "update",
+                        Some(redact(before_diff.into())),
+                        Some(redact(after_diff.into())),
+                    ));
+                }
+            }
+        }
+    }
+    for (id, before_row) in before.iter() {
+        if !after.iter().any(|(after_id, _)| after_id == id) {
+            writes.push((*id, "delete", Some(redact(before_row.clone())), None));
+        }
+    }
+    if writes.is_empty() {
+        return Ok(());
+    }
+
+    // -- Insert (one multi-rows INSERT)
+    let mut query = Query::insert();
+    query.into_table(AuditBmc::table_ref()).columns([
+        AuditIden::UserId.into_iden(),
+        AuditIden::RpcMethod.into_iden(),
+        AuditIden::ReqUuid.into_iden(),
+        AuditIden::Entity.into_iden(),
+        AuditIden::EntityId.into_iden(),
+        AuditIden::Action.into_iden(),
+        AuditIden::Before.into_iden(),
+        AuditIden::After.into_iden(),
+    ]);
+    for (entity_id, action, before, after) in writes {
+        query.values([
+            ctx.user_id().into(),
+            audit_info.rpc_method.as_str().into(),
+            audit_info.req_uuid.into(),
+            MC::TABLE.into(),
+            entity_id.into(),
+            action.into(),
+            before.into(),
+            after.into(),
+        ])?;
+    }
+
+    let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+    mm.dbx().execute(sqlx::query_with(&sql, values)).await?;
+
+    Ok(())
+}
+
+/// The changed fields of the row (before values, after values)
+fn diff(before: &Value, after: &Value) -> (Map<String, Value>, Map<String, Value>) {
+    let (mut before_diff, mut after_diff) = (Map::new(), Map::new());
+    let (Some(before), Some(after)) = (before.as_object(), after.as_object()) else {
+        return (before_diff, after_diff);
+    };
+
+    for (key, after_value) in after {
+        if AUDIT_DIFF_SKIPPED_KEYS.contains(&key.as_str()) {
+            continue;
+        }
+        let before_value = before.get(key).cloned().unwrap_or(Value::Null);
+        if &before_value != after_value {
+            before_diff.insert(key.clone(), before_value);
+            after_diff.insert(key.clone(), after_value.clone());
+        }
+    }
+
+    (before_diff, after_diff)
+}
+
+fn redact(mut row: Value) -> Value {
+    if let Some(row) = row.as_object_mut() {
+        for key in AUDIT_REDACTED_KEYS {
+            if let Some(value) = row.get_mut(*key) {
+                *value = AUDIT_REDACTED_VALUE.into();
+            }
+        }
+    }
+    row
+}
+// endregion: -- Base Write Hooks
+
+// region: -- Tests
+#[cfg(test)]
+mod tests {
+    #![allow(unused)]
+    pub type Result<T> = core::result::Result<T, Error>;
+    pub type Error = Box<dyn std::error::Error>; // For early dev & tests.
+
+    use super::*;
+    use crate::_dev_utils;
+    use crate::model::task::{TaskBmc, TaskForCreate, TaskForUpdate};
+    use serde_json::json;
+    use serial_test::serial;
+
+    #[serial]
+    #[tokio::test]
+    async fn test_audit_task_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::new(1021)?;
+        let root_ctx = Ctx::root_ctx();
+        let fx_req_uuid = Uuid::new_v4();
+        let mm_audit = mm.with_audit(AuditInfo {
+            rpc_method: "test_audit_task_ok".to_string(),
+            req_uuid: fx_req_uuid,
+        });
+        let fx_task_c = |title: &str| TaskForCreate {
+            title: title.to_string(),
+        };
+
+        // -- Exec: create, update, delete (audited)
+        let id = TaskBmc::create(&ctx, &mm_audit, fx_task_c("test_audit_task_ok")).await?;
+        TaskBmc::update(
+            &ctx,
+            &mm_audit,
+            id,
+            TaskForUpdate {
+                title: Some("test_audit_task_ok - updated".to_string()),
+                ..Default::default()
+            },
+        )
+        .await?;
+        TaskBmc::delete(&ctx, &mm_audit, id).await?;
+
+        // -- Exec: not audited (no AuditInfo)
+        let id_not_audited = TaskBmc::create(&ctx, &mm, fx_task_c("test_audit_task_ok")).await?;
+
+        // -- Check
+        let fx_filter = |entity_id: i64| -> Result<Vec<AuditLogFilter>> {
+            Ok(vec![serde_json::from_value(json!({
+                "entity": "task",
+                "entity_id": entity_id
+            }))?])
+        };
+        let filters = fx_filter(id)?;
+        let logs = AuditBmc::list(&root_ctx, &mm, Some(filters), None).await?;
+        let actions: Vec<&str> = logs.iter().map(|log| log.action.as_str()).collect();
+        assert_eq!(actions, ["create", "update", "delete"]);
+        assert!(logs.iter().all(|log| log.user_id == 1021
+            && log.req_uuid == Some(fx_req_uuid)
+            && log.rpc_method.as_deref() == Some("test_audit_task_ok")));
+        assert_eq!(logs[0].after.as_ref().ok_or("should have after")?["id"], id);
+        assert_eq!(logs[1].before, Some(json!({"title": "test_audit_task_ok"})));
+        assert_eq!(
+            logs[1].after,
+            Some(json!({"title": "test_audit_task_ok - updated"}))
+        );
+        assert_eq!(
+            logs[2].before.as_ref().ok_or("should have before")?["title"],
+            "test_audit_task_ok - updated"
+        );
+        assert!(logs[2].after.is_none());
+
+        let filters = fx_filter(id_not_audited)?;
+        let logs = AuditBmc::list(&root_ctx, &mm, Some(filters), None).await?;
+        assert!(logs.is_empty());
+
+        // -- Clean
+        TaskBmc::delete(&ctx, &mm, id_not_audited).await?;
+
+        Ok(())
+    }
+}
+// endregion: -- Tests
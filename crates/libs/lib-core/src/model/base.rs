@@ -1,12 +1,22 @@
+use crate::ctx::Ctx;
+use crate::model::audit;
+use crate::model::event::ModelEvent;
+use crate::model::ModelManager;
 use crate::model::{Error, Result};
-use crate::{ctx::Ctx, model::ModelManager};
-use modql::field::HasFields;
-use modql::filter::{FilterGroups, ListOptions};
+use lib_utils::b64::{b64u_decode_to_string, b64u_encode};
+use lib_utils::time::now_utc;
+use modql::field::{Field, Fields, HasFields};
+use modql::filter::{FilterGroups, ListOptions, OrderBy, OrderBys};
 use modql::SIden;
-use sea_query::{Condition, Expr, Iden, IntoIden, PostgresQueryBuilder, Query, TableRef};
+use sea_query::{
+    Alias, Condition, DynIden, Expr, Func, Iden, IntoIden, OnConflict, PostgresQueryBuilder, Query,
+    SimpleExpr, TableRef,
+};
 use sea_query_binder::SqlxBinder;
+use serde::Serialize;
+use serde_json::Value;
 use sqlx::postgres::PgRow;
-use sqlx::FromRow;
+use sqlx::{FromRow, Row};
 
 // NOTE: ! - Explanation of this design approach. Two video snippets:
 // TL;DR - We can use functions + Generics + Trait bounds to implement
@@ -20,17 +30,38 @@ use sqlx::FromRow;
 // REF: https://youtu.be/3cA_mk4vdWY?t=4739
 
 const LIST_LIMIT_DEFAULT: i64 = 300;
-const LIST_LIMIT_MAX: i64 = 1000;
+pub(crate) const LIST_LIMIT_MAX: i64 = 1000;
 
 // NOTE: This enum is like a Sea Query table and columns
 // REF: https://youtu.be/-dMH9UiwKqg?list=PL7r-PXl6ZPcCIOFaL7nVHXZvBmHNhrh_Q&t=561
 #[derive(Iden)]
 pub enum CommonIden {
     Id,
+    OwnerId,
+    Cid,
+    Ctime,
+    Mid,
+    Mtime,
 }
 
 pub trait DbBmc {
     const TABLE: &'static str;
+    // NOTE: U: When true, the table has an owner_id column. It gets filled from
+    // the Ctx on create, and get/list/update/delete are scoped to the Ctx user's
+    // rows (unless root Ctx).
+    const OWNED: bool = false;
+    // NOTE: U: When true, the rows of an OWNED table are readable by all the users
+    // (e.g., tokens, shared market data). Only the writes stay owner scoped.
+    const SHARED_READ: bool = false;
+    // NOTE: U: When true, the table has the cid/ctime (creator id/time) and
+    // mid/mtime (modifier id/time) columns, stamped on create/update.
+    const TIMESTAMPED: bool = false;
+    // NOTE: U: The unique (not id) column used as the upsert conflict target
+    // (e.g., token address). None when the entity does not support upsert.
+    const UNIQUE_COLUMN: Option<&'static str> = None;
+    // NOTE: U: When true, the writes (create/update/delete, bulk & upsert) of an
+    // rpc call are recorded in the audit log (see model::audit).
+    const AUDITED: bool = false;
 
     // Helper fn to get a sea query table reference
     fn table_ref() -> TableRef {
@@ -38,6 +69,87 @@ pub trait DbBmc {
     }
 }
 
+// region:    -- Ownership & Timestamps helpers
+/// Add the owner_id field (from the Ctx user) for a create
+fn add_owner_for_create<MC: DbBmc>(ctx: &Ctx, fields: &mut Fields) {
+    if MC::OWNED {
+        fields.push(Field::new(
+            CommonIden::OwnerId.into_iden(),
+            ctx.user_id().into(),
+        ));
+    }
+}
+
+/// Add the cid, ctime, mid and mtime fields for a create
+fn add_timestamps_for_create<MC: DbBmc>(ctx: &Ctx, fields: &mut Fields) {
+    if MC::TIMESTAMPED {
+        let user_id = ctx.user_id();
+        let now = now_utc();
+        fields.push(Field::new(CommonIden::Cid.into_iden(), user_id.into()));
+        fields.push(Field::new(CommonIden::Ctime.into_iden(), now.into()));
+        fields.push(Field::new(CommonIden::Mid.into_iden(), user_id.into()));
+        fields.push(Field::new(CommonIden::Mtime.into_iden(), now.into()));
+    }
+}
+
+/// Add the mid and mtime fields for an update
+fn add_timestamps_for_update<MC: DbBmc>(ctx: &Ctx, fields: &mut Fields) {
+    if MC::TIMESTAMPED {
+        fields.push(Field::new(
+            CommonIden::Mid.into_iden(),
+            ctx.user_id().into(),
+        ));
+        fields.push(Field::new(CommonIden::Mtime.into_iden(), now_utc().into()));
+    }
+}
+
+/// The owner_id condition scoping a query to the Ctx user's rows
+// NOTE: None when the table is not owned or when the Ctx is the root Ctx,
+// which can access all rows (e.g., for dev seeding, ingestion, admin jobs).
+// U: Admin users are scoped like any user, unless an admin rpc method
+// explicitly asks for all rows (see Ctx::for_all_owners).
+pub fn owner_cond<MC: DbBmc>(ctx: &Ctx) -> Option<SimpleExpr> {
+    (MC::OWNED && !ctx.is_all_owners()).then(|| Expr::col(CommonIden::OwnerId).eq(ctx.user_id()))
+}
+
+/// The owner_id condition of the reads (get/list), None for a SHARED_READ table
+pub fn read_owner_cond<MC: DbBmc>(ctx: &Ctx) -> Option<SimpleExpr> {
+    if MC::SHARED_READ {
+        return None;
+    }
+    owner_cond::<MC>(ctx)
+}
+
+/// The id condition, scoped to the Ctx user's rows (see owner_cond)
+fn id_cond<MC: DbBmc>(ctx: &Ctx, id: i64) -> Condition {
+    let cond = Condition::all().add(Expr::col(CommonIden::Id).eq(id));
+    match owner_cond::<MC>(ctx) {
+        Some(owner_cond) => cond.add(owner_cond),
+        None => cond,
+    }
+}
+
+/// The list condition (optional filters + read owner scope), for the queries
+/// built outside of base::list (e.g., list_paged, the token analytics).
+pub fn list_cond<MC, F>(ctx: &Ctx, filters: Option<F>) -> Result<Condition>
+where
+    MC: DbBmc,
+    F: Into<FilterGroups>,
+{
+    let mut cond = Condition::all();
+    if let Some(filters) = filters {
+        let filters: FilterGroups = filters.into();
+        let filters_cond: Condition = filters.try_into()?;
+        cond = cond.add(filters_cond);
+    }
+    if let Some(owner_cond) = read_owner_cond::<MC>(ctx) {
+        cond = cond.add(owner_cond);
+    }
+
+    Ok(cond)
+}
+// endregion: -- Ownership & Timestamps helpers
+
 pub fn finalize_list_options(list_options: Option<ListOptions>) -> Result<ListOptions> {
     // -- When Some, validate limit
     if let Some(mut list_options) = list_options {
@@ -69,15 +181,18 @@ pub fn finalize_list_options(list_options: Option<ListOptions>) -> Result<ListOp
 // NOTE: TIP: sqlb::HasFields allows us to extract the fields on data argument (E)
 // name and value, so that we can inject it without knowing the concrete type passed.
 // Again, this is the model::base layer, so we want it to be generic for all entity types.
-pub async fn create<MC, E>(_ctx: &Ctx, mm: &ModelManager, data: E) -> Result<i64>
+pub async fn create<MC, E>(ctx: &Ctx, mm: &ModelManager, data: E) -> Result<i64>
 where
     MC: DbBmc,
     E: HasFields,
 {
-    let db = mm.db();
+    let mm = &audit::begin::<MC>(mm).await?;
+    let dbx = mm.dbx();
 
     // -- Prep data & Extract fields (name / sea-query value expression)
-    let fields = data.not_none_fields();
+    let mut fields = data.not_none_fields();
+    add_owner_for_create::<MC>(ctx, &mut fields);
+    add_timestamps_for_create::<MC>(ctx, &mut fields);
     // Reformat our fields into a sea-query format for building our query
     // REF: https://youtu.be/-dMH9UiwKqg?list=PL7r-PXl6ZPcCIOFaL7nVHXZvBmHNhrh_Q&t=458
     let (columns, sea_values) = fields.for_sea_insert();
@@ -94,10 +209,22 @@ where
 
     // -- Exec query w/ SQLx
     let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
-    let (id,) = sqlx::query_as_with::<_, (i64,), _>(&sql, values)
-        .fetch_one(db)
+    let (id,) = dbx
+        .fetch_one(sqlx::query_as_with::<_, (i64,), _>(&sql, values))
         .await?;
 
+    mm.publish_event(ModelEvent::EntityCreated {
+        entity: MC::TABLE,
+        id,
+        user_id: ctx.user_id(),
+    })
+    .await;
+
+    // -- Audit
+    let after = audit::snapshot::<MC, _>(mm, Expr::col(CommonIden::Id).eq(id)).await?;
+    audit::record::<MC>(ctx, mm, Vec::new(), after).await?;
+    audit::commit::<MC>(mm).await?;
+
     Ok(id)
 }
 
@@ -111,13 +238,13 @@ where
 // REF: https://youtu.be/3cA_mk4vdWY?t=5298
 /// MC = Model Controller generic
 /// E = Entity
-pub async fn get<MC, E>(_ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<E>
+pub async fn get<MC, E>(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<E>
 where
     MC: DbBmc,
     E: for<'r> FromRow<'r, PgRow> + Unpin + Send,
     E: HasFields,
 {
-    let db = mm.db();
+    let dbx = mm.dbx();
     // U: Old. Now we have Sea Query + ModQL
     // let sql = format!("SELECT * FROM {} WHERE id = $1", MC::TABLE);
 
@@ -129,11 +256,14 @@ where
         .from(MC::table_ref())
         .columns(E::field_column_refs())
         .and_where(Expr::col(CommonIden::Id).eq(id));
+    if let Some(owner_cond) = read_owner_cond::<MC>(ctx) {
+        query.and_where(owner_cond);
+    }
 
     // -- Exec query w/ SQLx
     let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
-    let entity = sqlx::query_as_with::<_, E, _>(&sql, values)
-        .fetch_optional(db)
+    let entity = dbx
+        .fetch_optional(sqlx::query_as_with::<_, E, _>(&sql, values))
         .await?
         .ok_or(Error::EntityNotFound {
             entity: MC::TABLE,
@@ -143,13 +273,84 @@ where
     Ok(entity)
 }
 
+/// The entity when it matches the filters (and the owner scope), None otherwise.
+// NOTE: For the change notifications (e.g., the websocket subscriptions),
+// which check a written row against the subscription filters.
+pub async fn get_matching<MC, E, F>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    id: i64,
+    filters: Option<F>,
+) -> Result<Option<E>>
+where
+    MC: DbBmc,
+    E: for<'r> FromRow<'r, PgRow> + Unpin + Send,
+    E: HasFields,
+    F: Into<FilterGroups>,
+{
+    let dbx = mm.dbx();
+
+    // -- Build the query
+    let mut query = Query::select();
+    query
+        .from(MC::table_ref())
+        .columns(E::field_column_refs())
+        .and_where(Expr::col(CommonIden::Id).eq(id))
+        .cond_where(list_cond::<MC, F>(ctx, filters)?);
+
+    // -- Exec query
+    let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+    let entity = dbx
+        .fetch_optional(sqlx::query_as_with::<_, E, _>(&sql, values))
+        .await?;
+
+    Ok(entity)
+}
+
+/// True when the row (as json) matches the filters (and the read owner scope).
+// NOTE: The get_matching rule for a row that cannot be read anymore
+// (e.g., the row of an EntityDeleted event), checked by the db on the json
+// populated as a table row, so the filters work as in the queries.
+pub async fn row_matches<MC, F>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    row: &Value,
+    filters: Option<F>,
+) -> Result<bool>
+where
+    MC: DbBmc,
+    F: Into<FilterGroups>,
+{
+    let dbx = mm.dbx();
+
+    // -- Build the query
+    let mut query = Query::select();
+    query
+        .expr(Expr::val(1))
+        .from_function(
+            Func::cust(SIden("jsonb_populate_record"))
+                .arg(Expr::cust(format!(r#"NULL::"{}""#, MC::TABLE)))
+                .arg(Expr::val(row.clone()).cast_as(Alias::new("jsonb"))),
+            SIden(MC::TABLE),
+        )
+        .cond_where(list_cond::<MC, F>(ctx, filters)?);
+
+    // -- Exec query
+    let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+    let matched = dbx
+        .fetch_optional(sqlx::query_as_with::<_, (i32,), _>(&sql, values))
+        .await?;
+
+    Ok(matched.is_some())
+}
+
 // NOTE: U: Adding filtering ability w/ modql::filter::FilterGroups and Sea Query
 // FilterNodes are set up in groups, and groups can be composed together.
 // This makes the monomorphization of first(?) allows us to pass any types as
 // filters that implements the FilterNodes, which impls Into<FilterGroups>.
 // REF: https://youtu.be/-dMH9UiwKqg?list=PL7r-PXl6ZPcCIOFaL7nVHXZvBmHNhrh_Q&t=1611
 pub async fn list<MC, E, F>(
-    _ctx: &Ctx,
+    ctx: &Ctx,
     mm: &ModelManager,
     filters: Option<F>,
     list_options: Option<ListOptions>,
@@ -160,7 +361,7 @@ where
     E: HasFields,
     F: Into<FilterGroups>,
 {
-    let db = mm.db();
+    let dbx = mm.dbx();
     // let sql = format!("SELECT * FROM {} WHERE id = $1", MC::TABLE);
 
     // -- Build the query w/ sea-query
@@ -178,6 +379,11 @@ where
         query.cond_where(cond);
     }
 
+    // Scope to the Ctx user's rows
+    if let Some(owner_cond) = read_owner_cond::<MC>(ctx) {
+        query.and_where(owner_cond);
+    }
+
     // List options
     // NOTE:U: TIP! - The problem of doing an 'if let Some(list_options) is that our
     // call to list_options.apply_to_sea_query() will only run IF we
@@ -188,25 +394,213 @@ where
 
     // -- Exec query w/ SQLx
     let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
-    let entities = sqlx::query_as_with::<_, E, _>(&sql, values)
-        .fetch_all(db)
+    let entities = dbx
+        .fetch_all(sqlx::query_as_with::<_, E, _>(&sql, values))
         .await?;
 
     Ok(entities)
 }
 
+// region:    -- List Paged
+/// A page of entities with the total count of matching rows (filters and owner
+/// scope, before limit/offset/cursor) and the cursor of the next page.
+/// `next_cursor` is None when there are no more rows.
+#[derive(Debug, Serialize)]
+pub struct ListPage<E> {
+    pub items: Vec<E>,
+    pub total: i64,
+    pub next_cursor: Option<String>,
+}
+
+// NOTE: The page query selects an extra "_cursor" column (json of the order_bys
+// column values of the row), so each row carries its own keyset cursor.
+const CURSOR_COLUMN: &str = "_cursor";
+
+/// An entity row plus its keyset cursor (json text)
+struct CursorRow<E> {
+    entity: E,
+    cursor: String,
+}
+
+impl<'r, E> FromRow<'r, PgRow> for CursorRow<E>
+where
+    E: FromRow<'r, PgRow>,
+{
+    fn from_row(row: &'r PgRow) -> sqlx::Result<Self> {
+        Ok(CursorRow {
+            entity: E::from_row(row)?,
+            cursor: row.try_get(CURSOR_COLUMN)?,
+        })
+    }
+}
+
+/// Same as `list` but returns a `ListPage` (items, total, next_cursor).
+/// Supports both offset (`list_options.offset`) and keyset (`cursor`, the
+/// `next_cursor` of the previous page) pagination, on the `list_options.order_bys`
+/// columns (plus "id" as the tie breaker).
+// NOTE: Keyset pagination expects the order_bys columns to be NOT NULL
+// (NULL values don't compare, so such rows would be skipped).
+pub async fn list_paged<MC, E, F>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    filters: Option<F>,
+    list_options: Option<ListOptions>,
+    cursor: Option<String>,
+) -> Result<ListPage<E>>
+where
+    MC: DbBmc,
+    E: for<'r> FromRow<'r, PgRow> + Unpin + Send,
+    E: HasFields,
+    F: Into<FilterGroups>,
+{
+    let dbx = mm.dbx();
+
+    // -- Filters & Owner conditions (shared by the total and the page queries)
+    let mut cond = list_cond::<MC, F>(ctx, filters)?;
+
+    // -- Total
+    let mut query = Query::select();
+    query
+        .from(MC::table_ref())
+        .expr(Expr::col(CommonIden::Id).count())
+        .cond_where(cond.clone());
+    let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+    let (total,) = dbx
+        .fetch_one(sqlx::query_as_with::<_, (i64,), _>(&sql, values))
+        .await?;
+
+    // -- List options & Keyset order
+    let mut list_options = finalize_list_options(list_options)?;
+    let order_bys = keyset_order_bys(list_options.order_bys.take())?;
+    let limit = list_options.limit.unwrap_or(LIST_LIMIT_DEFAULT);
+    // Fetch one more row to know if there is a next page
+    list_options.limit = Some(limit + 1);
+    list_options.order_bys = Some(OrderBys::new(order_bys.clone()));
+    if let Some(cursor) = cursor {
+        cond = cond.add(keyset_cond::<MC>(&order_bys, &cursor)?);
+    }
+
+    // -- Page
+    let mut query = Query::select();
+    query
+        .from(MC::table_ref())
+        .columns(E::field_column_refs())
+        .expr_as(cursor_expr(&order_bys), Alias::new(CURSOR_COLUMN))
+        .cond_where(cond);
+    list_options.apply_to_sea_query(&mut query);
+
+    let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+    let mut rows = dbx
+        .fetch_all(sqlx::query_as_with::<_, CursorRow<E>, _>(&sql, values))
+        .await?;
+
+    // -- Next cursor
+    let has_more = rows.len() as i64 > limit;
+    rows.truncate(limit.max(0) as usize);
+    let next_cursor = rows
+        .last()
+        .filter(|_| has_more)
+        .map(|row| b64u_encode(&row.cursor));
+
+    Ok(ListPage {
+        items: rows.into_iter().map(|row| row.entity).collect(),
+        total,
+        next_cursor,
+    })
+}
+
+/// The validated order_bys, with "id" appended (if absent) for a total order
+fn keyset_order_bys(order_bys: Option<OrderBys>) -> Result<Vec<OrderBy>> {
+    let mut order_bys = order_bys.map(|o| o.order_bys()).unwrap_or_default();
+
+    // NOTE: The column names end up in custom sql (cursor_expr, keyset_cond),
+    // so only plain identifiers are allowed.
+    for order_by in order_bys.iter() {
+        let col = order_by_col(order_by);
+        let is_ident = col.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && col.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_ident {
+            return Err(Error::ListOrderByInvalid {
+                order_by: col.to_string(),
+            });
+        }
+    }
+    if !order_bys.iter().any(|o| order_by_col(o) == "id") {
+        order_bys.push(OrderBy::Asc("id".to_string()));
+    }
+
+    Ok(order_bys)
+}
+
+fn order_by_col(order_by: &OrderBy) -> &str {
+    match order_by {
+        OrderBy::Asc(col) | OrderBy::Desc(col) => col,
+    }
+}
+
+/// The row cursor select expression, e.g., `json_build_object('title', "title", 'id', "id")::text`
+fn cursor_expr(order_bys: &[OrderBy]) -> SimpleExpr {
+    let pairs: Vec<String> = order_bys
+        .iter()
+        .map(|o| format!(r#"'{col}', "{col}""#, col = order_by_col(o)))
+        .collect();
+    Expr::cust(format!("json_build_object({})::text", pairs.join(", ")))
+}
+
+/// The keyset condition for the rows after the cursor row, in the order_bys order.
+/// E.g., for `[title ASC, id ASC]`:
+/// `title > cur.title OR (title = cur.title AND id > cur.id)`
+// NOTE: The cursor values are cast back to their column types with
+// json_populate_record on the table row type.
+fn keyset_cond<MC: DbBmc>(order_bys: &[OrderBy], cursor: &str) -> Result<Condition> {
+    let cursor_json = b64u_decode_to_string(cursor)
+        .ok()
+        .filter(|json| serde_json::from_str::<serde_json::Map<String, Value>>(json).is_ok())
+        .ok_or(Error::ListCursorInvalid)?;
+
+    let cursor_val = |col: &str| {
+        Expr::cust_with_values(
+            format!(
+                r#"(SELECT "{col}" FROM json_populate_record(NULL::"{}", $1::json))"#,
+                MC::TABLE
+            ),
+            [cursor_json.clone()],
+        )
+    };
+
+    let mut cond = Condition::any();
+    for (i, order_by) in order_bys.iter().enumerate() {
+        let mut step_cond = Condition::all();
+        for prev in &order_bys[..i] {
+            let col = order_by_col(prev);
+            step_cond = step_cond.add(Expr::col(Alias::new(col)).eq(cursor_val(col)));
+        }
+        let col = order_by_col(order_by);
+        step_cond = step_cond.add(match order_by {
+            OrderBy::Asc(_) => Expr::col(Alias::new(col)).gt(cursor_val(col)),
+            OrderBy::Desc(_) => Expr::col(Alias::new(col)).lt(cursor_val(col)),
+        });
+        cond = cond.add(step_cond);
+    }
+
+    Ok(cond)
+}
+// endregion: -- List Paged
+
 // NOTE: Our Bmc API is going to be more general, so we're going to return void ().
 // However, our web API can be more convenient and return something else
 // REF: https://youtu.be/3cA_mk4vdWY?t=5801
-pub async fn update<MC, E>(_ctx: &Ctx, mm: &ModelManager, id: i64, data: E) -> Result<()>
+pub async fn update<MC, E>(ctx: &Ctx, mm: &ModelManager, id: i64, data: E) -> Result<()>
 where
     MC: DbBmc,
     E: HasFields,
 {
-    let db = mm.db();
+    let mm = &audit::begin::<MC>(mm).await?;
+    let dbx = mm.dbx();
 
     // -- Prep data
-    let fields = data.not_none_fields();
+    let mut fields = data.not_none_fields();
+    add_timestamps_for_update::<MC>(ctx, &mut fields);
     // Reformat our fields into a sea-query format for building our query
     let fields = fields.for_sea_update();
 
@@ -215,52 +609,436 @@ where
     query
         .table(MC::table_ref())
         .values(fields)
-        .and_where(Expr::col(CommonIden::Id).eq(id));
+        .cond_where(id_cond::<MC>(ctx, id));
+
+    // -- Audit before
+    let before = audit::snapshot::<MC, _>(mm, id_cond::<MC>(ctx, id)).await?;
 
     // -- Exec query
     let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
-    let count = sqlx::query_with(&sql, values)
-        .execute(db)
-        .await?
-        .rows_affected();
+    let count = dbx.execute(sqlx::query_with(&sql, values)).await?;
 
     // -- Check result
     if count == 0 {
-        Err(Error::EntityNotFound {
+        return Err(Error::EntityNotFound {
             entity: MC::TABLE,
             id,
-        })
-    } else {
-        Ok(())
+        });
     }
+
+    mm.publish_event(ModelEvent::EntityUpdated {
+        entity: MC::TABLE,
+        id,
+        user_id: ctx.user_id(),
+    })
+    .await;
+
+    // -- Audit
+    let after = audit::snapshot::<MC, _>(mm, Expr::col(CommonIden::Id).eq(id)).await?;
+    audit::record::<MC>(ctx, mm, before, after).await?;
+    audit::commit::<MC>(mm).await?;
+
+    Ok(())
 }
 
-pub async fn delete<MC>(_ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()>
+pub async fn delete<MC>(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()>
 where
     MC: DbBmc,
 {
-    let db = mm.db();
+    let mm = &audit::begin::<MC>(mm).await?;
+    let dbx = mm.dbx();
 
     // -- Build query
     let mut query = Query::delete();
     query
         .from_table(MC::table_ref())
-        .and_where(Expr::col(CommonIden::Id).eq(id));
+        .cond_where(id_cond::<MC>(ctx, id))
+        .returning(Query::returning().expr(row_json_expr::<MC>()));
+
+    // -- Audit before
+    let before = audit::snapshot::<MC, _>(mm, id_cond::<MC>(ctx, id)).await?;
 
     // -- Exec query
     let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
-    let count = sqlx::query_with(&sql, values)
-        .execute(db)
-        .await?
-        .rows_affected();
+    let row = dbx
+        .fetch_optional(sqlx::query_as_with::<_, (Value,), _>(&sql, values))
+        .await?;
 
     // -- Check result
-    if count == 0 {
-        Err(Error::EntityNotFound {
+    let Some((row,)) = row else {
+        return Err(Error::EntityNotFound {
             entity: MC::TABLE,
             id,
+        });
+    };
+
+    mm.publish_event(ModelEvent::EntityDeleted {
+        entity: MC::TABLE,
+        id,
+        user_id: ctx.user_id(),
+        row,
+    })
+    .await;
+
+    // -- Audit
+    audit::record::<MC>(ctx, mm, before, Vec::new()).await?;
+    audit::commit::<MC>(mm).await?;
+
+    Ok(())
+}
+
+// region:    -- Bulk (many)
+// NOTE: Postgres allows at most 65535 bind params per statement, so create_many
+// splits large inputs into several multi-rows INSERT (in one txn).
+const PG_BIND_PARAMS_MAX: usize = 65535;
+
+/// Create many entities with multi-rows INSERT statement(s).
+/// Returns the new ids (in the data order).
+pub async fn create_many<MC, E>(ctx: &Ctx, mm: &ModelManager, data: Vec<E>) -> Result<Vec<i64>>
+where
+    MC: DbBmc,
+    E: HasFields,
+{
+    insert_many::<MC, E>(ctx, mm, data, None).await
+}
+
+/// The multi-rows INSERT of create_many/upsert_many (with the optional ON CONFLICT)
+async fn insert_many<MC, E>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    data: Vec<E>,
+    on_conflict: Option<OnConflict>,
+) -> Result<Vec<i64>>
+where
+    MC: DbBmc,
+    E: HasFields,
+{
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // -- Prep data (same as create, for each item)
+    let rows: Vec<Vec<Field>> = data
+        .into_iter()
+        .map(|item| {
+            let mut fields = item.not_none_fields();
+            add_owner_for_create::<MC>(ctx, &mut fields);
+            add_timestamps_for_create::<MC>(ctx, &mut fields);
+            fields.into_vec()
+        })
+        .collect();
+
+    // -- Columns & Rows values
+    // NOTE: Items can have different not none fields, so the columns are the union
+    // of all the items fields, and a missing field gets the column DEFAULT.
+    let mut columns: Vec<(String, DynIden)> = Vec::new();
+    for field in rows.iter().flatten() {
+        let name = field.iden.to_string();
+        if !columns.iter().any(|(col_name, _)| col_name == &name) {
+            columns.push((name, field.iden.clone()));
+        }
+    }
+    let rows_values: Vec<Vec<SimpleExpr>> = rows
+        .into_iter()
+        .map(|fields| {
+            columns
+                .iter()
+                .map(|(col_name, _)| {
+                    fields
+                        .iter()
+                        .find(|f| &f.iden.to_string() == col_name)
+                        .map(|f| f.value.clone())
+                        .unwrap_or_else(|| Expr::cust("DEFAULT"))
+                })
+                .collect()
         })
-    } else {
-        Ok(())
+        .collect();
+
+    // -- Exec query (by chunks)
+    let mm = &mm.new_with_txn()?;
+    mm.begin_txn().await?;
+
+    // NOTE: The RETURNING `xmax = 0` is true for an inserted row, and false for
+    // a row updated by the ON CONFLICT (pg system column), for the model events.
+    let chunk_size = (PG_BIND_PARAMS_MAX / columns.len().max(1)).max(1);
+    let mut ids = Vec::with_capacity(rows_values.len());
+    // NOTE: The audit before rows of an upsert are the rows with the chunk unique values.
+    let unique_idx = MC::UNIQUE_COLUMN
+        .filter(|_| on_conflict.is_some())
+        .and_then(|unique_column| columns.iter().position(|(name, _)| name == unique_column));
+    for chunk in rows_values.chunks(chunk_size) {
+        // -- Audit before
+        let mut before = match unique_idx {
+            Some(idx) => {
+                let unique_values = chunk.iter().map(|values| values[idx].clone());
+                let cond = Expr::col(columns[idx].1.clone()).is_in(unique_values);
+                audit::snapshot::<MC, _>(mm, cond).await?
+            }
+            None => Vec::new(),
+        };
+
+        let mut query = Query::insert();
+        query
+            .into_table(MC::table_ref())
+            .columns(columns.iter().map(|(_, iden)| iden.clone()))
+            .returning(
+                Query::returning()
+                    .exprs([Expr::col(CommonIden::Id).into(), Expr::cust("xmax = 0")]),
+            );
+        for values in chunk {
+            query.values(values.clone())?;
+        }
+        if let Some(on_conflict) = on_conflict.clone() {
+            query.on_conflict(on_conflict);
+        }
+
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        let chunk_rows = mm
+            .dbx()
+            .fetch_all(sqlx::query_as_with::<_, (i64, bool), _>(&sql, values))
+            .await?;
+
+        let chunk_ids: Vec<i64> = chunk_rows.iter().map(|(id, _)| *id).collect();
+
+        // -- Check: every row of an upsert got inserted or updated
+        // NOTE: In the txn (before the commit), so nothing of the data is written.
+        if let (Some(idx), true) = (unique_idx, chunk_rows.len() != chunk.len()) {
+            let unique_col = columns[idx].1.clone();
+            let unique_values = chunk.iter().map(|values| values[idx].clone());
+            let mut query = Query::select();
+            query
+                .from(MC::table_ref())
+                .expr(Expr::col(unique_col.clone()).cast_as(Alias::new("text")))
+                .and_where(Expr::col(unique_col).is_in(unique_values))
+                .and_where(Expr::col(CommonIden::Id).is_not_in(chunk_ids.clone()));
+            let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+            let unique_values = mm
+                .dbx()
+                .fetch_all(sqlx::query_as_with::<_, (String,), _>(&sql, values))
+                .await?
+                .into_iter()
+                .map(|(value,)| value)
+                .collect();
+            return Err(Error::UpsertNotOwned {
+                entity: MC::TABLE,
+                unique_values,
+            });
+        }
+        for (id, inserted) in chunk_rows {
+            let user_id = ctx.user_id();
+            let event = if inserted {
+                ModelEvent::EntityCreated {
+                    entity: MC::TABLE,
+                    id,
+                    user_id,
+                }
+            } else {
+                ModelEvent::EntityUpdated {
+                    entity: MC::TABLE,
+                    id,
+                    user_id,
+                }
+            };
+            mm.publish_event(event).await;
+            ids.push(id);
+        }
+
+        // -- Audit
+        // NOTE: An existing row not updated (e.g., of another owner) is not a write.
+        before.retain(|(id, _)| chunk_ids.contains(id));
+        let after =
+            audit::snapshot::<MC, _>(mm, Expr::col(CommonIden::Id).is_in(chunk_ids)).await?;
+        audit::record::<MC>(ctx, mm, before, after).await?;
     }
+
+    mm.commit_txn().await?;
+
+    Ok(ids)
+}
+
+/// Update all the entities matching the filters with the same data.
+/// Returns the updated ids.
+pub async fn update_many<MC, E, F>(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    filters: F,
+    data: E,
+) -> Result<Vec<i64>>
+where
+    MC: DbBmc,
+    E: HasFields,
+    F: Into<FilterGroups>,
+{
+    let mm = &audit::begin::<MC>(mm).await?;
+    let dbx = mm.dbx();
+
+    // -- Prep data
+    let mut fields = data.not_none_fields();
+    add_timestamps_for_update::<MC>(ctx, &mut fields);
+    let fields = fields.for_sea_update();
+
+    // -- Build query
+    let cond = many_cond::<MC, F>(ctx, filters)?;
+    let mut query = Query::update();
+    query
+        .table(MC::table_ref())
+        .values(fields)
+        .cond_where(cond.clone())
+        .returning(Query::returning().columns([CommonIden::Id]));
+
+    // -- Audit before
+    let before = audit::snapshot::<MC, _>(mm, cond).await?;
+
+    // -- Exec query
+    let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+    let ids: Vec<i64> = dbx
+        .fetch_all(sqlx::query_as_with::<_, (i64,), _>(&sql, values))
+        .await?
+        .into_iter()
+        .map(|(id,)| id)
+        .collect();
+
+    for &id in ids.iter() {
+        mm.publish_event(ModelEvent::EntityUpdated {
+            entity: MC::TABLE,
+            id,
+            user_id: ctx.user_id(),
+        })
+        .await;
+    }
+
+    // -- Audit
+    let after = audit::snapshot::<MC, _>(mm, Expr::col(CommonIden::Id).is_in(ids.clone())).await?;
+    audit::record::<MC>(ctx, mm, before, after).await?;
+    audit::commit::<MC>(mm).await?;
+
+    Ok(ids)
+}
+
+/// Delete all the entities matching the filters.
+/// Returns the deleted ids.
+pub async fn delete_many<MC, F>(ctx: &Ctx, mm: &ModelManager, filters: F) -> Result<Vec<i64>>
+where
+    MC: DbBmc,
+    F: Into<FilterGroups>,
+{
+    let mm = &audit::begin::<MC>(mm).await?;
+    let dbx = mm.dbx();
+
+    // -- Build query
+    let cond = many_cond::<MC, F>(ctx, filters)?;
+    let mut query = Query::delete();
+    query
+        .from_table(MC::table_ref())
+        .cond_where(cond.clone())
+        .returning(
+            Query::returning().exprs([Expr::col(CommonIden::Id).into(), row_json_expr::<MC>()]),
+        );
+
+    // -- Audit before
+    let before = audit::snapshot::<MC, _>(mm, cond).await?;
+
+    // -- Exec query
+    let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+    let rows = dbx
+        .fetch_all(sqlx::query_as_with::<_, (i64, Value), _>(&sql, values))
+        .await?;
+
+    let mut ids = Vec::with_capacity(rows.len());
+    for (id, row) in rows {
+        ids.push(id);
+        mm.publish_event(ModelEvent::EntityDeleted {
+            entity: MC::TABLE,
+            id,
+            user_id: ctx.user_id(),
+            row,
+        })
+        .await;
+    }
+
+    // -- Audit
+    audit::record::<MC>(ctx, mm, before, Vec::new()).await?;
+    audit::commit::<MC>(mm).await?;
+
+    Ok(ids)
+}
+
+/// The whole row as jsonb (e.g., the deleted rows, for the EntityDeleted events)
+fn row_json_expr<MC: DbBmc>() -> SimpleExpr {
+    Expr::cust(format!(r#"to_jsonb("{}")"#, MC::TABLE))
+}
+
+/// The update_many/delete_many condition (filters + owner scope)
+// NOTE: Empty filters are refused, so a bad request can't update/delete all the rows.
+fn many_cond<MC, F>(ctx: &Ctx, filters: F) -> Result<Condition>
+where
+    MC: DbBmc,
+    F: Into<FilterGroups>,
+{
+    let filters: FilterGroups = filters.into();
+    let filters_cond: Condition = filters.try_into()?;
+    if filters_cond.is_empty() {
+        return Err(Error::FiltersEmpty { entity: MC::TABLE });
+    }
+
+    let mut cond = Condition::all().add(filters_cond);
+    if let Some(owner_cond) = owner_cond::<MC>(ctx) {
+        cond = cond.add(owner_cond);
+    }
+
+    Ok(cond)
+}
+// endregion: -- Bulk (many)
+
+// region:    -- Upsert
+/// Insert the entity, or when the `MC::UNIQUE_COLUMN` value already exists,
+/// update the existing row with the `U` (e.g., TokenForUpdate) fields.
+/// Returns the id (new or existing).
+pub async fn upsert<MC, E, U>(ctx: &Ctx, mm: &ModelManager, data: E) -> Result<i64>
+where
+    MC: DbBmc,
+    E: HasFields,
+    U: HasFields,
+{
+    let ids = upsert_many::<MC, E, U>(ctx, mm, vec![data]).await?;
+
+    ids.into_iter().next().ok_or(Error::UniqueViolation {
+        table: MC::TABLE.to_string(),
+        constraint: MC::UNIQUE_COLUMN.unwrap_or_default().to_string(),
+    })
+}
+
+/// Multi-rows version of upsert. Returns the ids (in the data order).
+// NOTE: The same unique value cannot be twice in the data
+// (pg "ON CONFLICT DO UPDATE command cannot affect row a second time").
+pub async fn upsert_many<MC, E, U>(ctx: &Ctx, mm: &ModelManager, data: Vec<E>) -> Result<Vec<i64>>
+where
+    MC: DbBmc,
+    E: HasFields,
+    U: HasFields,
+{
+    let unique_column =
+        MC::UNIQUE_COLUMN.ok_or(Error::UpsertNoUniqueColumn { entity: MC::TABLE })?;
+
+    // -- Build the ON CONFLICT
+    // NOTE: Only the U fields (and the mid/mtime) are updated (from the EXCLUDED row),
+    // so the owner_id, cid and ctime of the existing row are kept.
+    let mut update_idens: Vec<DynIden> = U::field_idens();
+    if MC::TIMESTAMPED {
+        update_idens.push(CommonIden::Mid.into_iden());
+        update_idens.push(CommonIden::Mtime.into_iden());
+    }
+    let mut on_conflict = OnConflict::column(SIden(unique_column));
+    on_conflict.update_columns(update_idens);
+    // NOTE: An existing row of another owner is not updated (and not returned),
+    // so insert_many fails with UpsertNotOwned (and the txn is rolled back).
+    // The owner_id is table qualified, as the EXCLUDED row has the same columns.
+    if owner_cond::<MC>(ctx).is_some() {
+        on_conflict
+            .action_and_where(Expr::col((SIden(MC::TABLE), CommonIden::OwnerId)).eq(ctx.user_id()));
+    }
+
+    // -- Exec query
+    insert_many::<MC, E>(ctx, mm, data, Some(on_conflict)).await
 }
+// endregion: -- Upsert
@@ -1,3 +1,4 @@
+use crate::ctx;
 use crate::model::store;
 use derive_more::From;
 use lib_auth::pwd;
@@ -27,6 +28,56 @@ pub enum Error {
         max: i64,
         actual: i64,
     },
+    ListCursorInvalid,
+    ListOrderByInvalid {
+        order_by: String,
+    },
+    // NOTE: update_many/delete_many require at least one filter
+    FiltersEmpty {
+        entity: &'static str,
+    },
+
+    // NOTE: U: From a unique constraint violation (pg 23505) sqlx::Error
+    // (see the From<sqlx::Error> below), or a failed upsert.
+    UniqueViolation {
+        table: String,
+        constraint: String,
+    },
+    UpsertNoUniqueColumn {
+        entity: &'static str,
+    },
+    // NOTE: The unique values (e.g., token addresses) of the existing rows
+    // of another owner, which an upsert cannot update.
+    UpsertNotOwned {
+        entity: &'static str,
+        unique_values: Vec<String>,
+    },
+
+    // -- User
+    UserUsernameInvalid {
+        reason: String,
+    },
+    UserPwdInvalid {
+        reason: String,
+    },
+    UserAlreadyExists {
+        username: String,
+    },
+    UserPwdNotMatching {
+        user_id: i64,
+    },
+    // NOTE: An admin cannot delete itself, nor the last admin (see UserBmc::delete)
+    UserDeleteSelf {
+        user_id: i64,
+    },
+    UserDeleteLastAdmin {
+        user_id: i64,
+    },
+
+    // -- Watchlist
+    TokenAddressNotFound {
+        address: String,
+    },
 
     // -- Modules
     // NOTE: When creating a new Model Manager, we add the Db as a
@@ -42,10 +93,13 @@ pub enum Error {
     Pwd(pwd::Error),
     #[from]
     Store(store::Error),
+    // NOTE: e.g., Ctx::for_all_owners() of a non admin Ctx (see UserBmc::delete)
+    #[from]
+    Ctx(ctx::Error),
 
     // -- Externals
     // NOTE: sqlx::Error implements DisplayFromStr so this works
-    #[from]
+    // U: From is implemented below (unique violation detection)
     Sqlx(#[serde_as(as = "DisplayFromStr")] sqlx::Error),
     #[from]
     SeaQuery(#[serde_as(as = "DisplayFromStr")] sea_query::error::Error),
@@ -61,6 +115,25 @@ pub enum Error {
     SerdeJson(#[serde_as(as = "DisplayFromStr")] serde_json::Error),
 }
 
+// region: -- Froms
+// NOTE: The unique violations become UniqueViolation (instead of the generic Sqlx),
+// so the callers (and the web layer) can match on them.
+impl From<sqlx::Error> for Error {
+    fn from(val: sqlx::Error) -> Self {
+        if let Some(db_err) = val.as_database_error() {
+            if db_err.is_unique_violation() {
+                return Self::UniqueViolation {
+                    table: db_err.table().unwrap_or_default().to_string(),
+                    constraint: db_err.constraint().unwrap_or_default().to_string(),
+                };
+            }
+        }
+
+        Self::Sqlx(val)
+    }
+}
+// endregion: -- Froms
+
 // // region: -- Froms
 // NOTE: U: Added derive_more::From to simplify.
 // // Help convert these errors into the 'model' module Error
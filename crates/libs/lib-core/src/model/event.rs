@@ -0,0 +1,457 @@
+//! Model change events (the "Event" of the Context -- Event layer)
+//!
+//! Design:
+//! - `base::create/update/delete` (and their bulk versions) publish a
+//!   `ModelEvent` on the `EventBus` of the ModelManager after a successful write,
+//!   so the other subsystems (e.g., websockets, audit, caches) can subscribe
+//!   without changing each Bmc.
+//! - The bus is a tokio broadcast channel, so a slow subscriber lags
+//!   (`RecvError::Lagged`) rather than blocking the writers.
+//! - In txn mode, the events are held until the outermost `commit_txn()`,
+//!   and dropped on rollback (subscribers only see committed changes).
+//! - The bus is per process. The writes of another process (e.g., the
+//!   token-ingest worker) reach it through Postgres NOTIFY/LISTEN: the writer
+//!   ModelManager opts in with `with_db_events_notify()`, and the reader with
+//!   `listen_db_events()` (e.g., the web-server, for /api/ws and /api/sse).
+//!   The events notified while no reader listens (e.g., web-server restart) are lost.
+
+use crate::core_config;
+use crate::model::alert::{AlertEventBmc, AlertRuleBmc};
+use crate::model::base::DbBmc;
+use crate::model::ingest_run::IngestRunBmc;
+use crate::model::store::Db;
+use crate::model::task::TaskBmc;
+use crate::model::token::TokenBmc;
+use crate::model::token_snapshot::TokenSnapshotBmc;
+use crate::model::user::UserBmc;
+use crate::model::watchlist::WatchlistBmc;
+use crate::model::Result;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::postgres::PgListener;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::warn;
+use uuid::Uuid;
+
+// region: -- ModelEvent
+/// A committed change of an entity (`entity` is the `DbBmc::TABLE`,
+/// and `user_id` the Ctx user that made the change).
+// NOTE: A deleted row cannot be read anymore, so EntityDeleted carries it
+// (as json), for the subscribers to check its owner and filters
+// (see base::row_matches).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type")]
+pub enum ModelEvent {
+    EntityCreated {
+        entity: &'static str,
+        id: i64,
+        user_id: i64,
+    },
+    EntityUpdated {
+        entity: &'static str,
+        id: i64,
+        user_id: i64,
+    },
+    EntityDeleted {
+        entity: &'static str,
+        id: i64,
+        user_id: i64,
+        #[serde(skip)]
+        row: Value,
+    },
+}
+
+impl ModelEvent {
+    pub fn entity(&self) -> &'static str {
+        match self {
+            ModelEvent::EntityCreated { entity, .. }
+            | ModelEvent::EntityUpdated { entity, .. }
+            | ModelEvent::EntityDeleted { entity, .. } => entity,
+        }
+    }
+
+    pub fn id(&self) -> i64 {
+        match self {
+            ModelEvent::EntityCreated { id, .. }
+            | ModelEvent::EntityUpdated { id, .. }
+            | ModelEvent::EntityDeleted { id, .. } => *id,
+        }
+    }
+
+    pub fn user_id(&self) -> i64 {
+        match self {
+            ModelEvent::EntityCreated { user_id, .. }
+            | ModelEvent::EntityUpdated { user_id, .. }
+            | ModelEvent::EntityDeleted { user_id, .. } => *user_id,
+        }
+    }
+}
+// endregion: -- ModelEvent
+
+// region: -- EventBus
+/// The number of events a subscriber can fall behind before it lags.
+const EVENT_BUS_CAPACITY: usize = 1024;
+
+#[derive(Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<ModelEvent>,
+    // NOTE: The bus id in the db notifications, so a bus skips its own.
+    origin: Uuid,
+    db_notify: bool,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(EVENT_BUS_CAPACITY);
+        EventBus {
+            tx,
+            origin: Uuid::new_v4(),
+            db_notify: false,
+        }
+    }
+
+    /// The bus, also sending its events to the db (see ModelManager::with_db_events_notify)
+    pub(in crate::model) fn with_db_notify(self) -> Self {
+        EventBus {
+            db_notify: true,
+            ..self
+        }
+    }
+
+    pub(in crate::model) fn db_notify(&self) -> bool {
+        self.db_notify
+    }
+
+    /// A new receiver of the events published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<ModelEvent> {
+        self.tx.subscribe()
+    }
+
+    // NOTE: No subscriber is not an error (the event is simply dropped).
+    pub(in crate::model) fn publish(&self, event: ModelEvent) {
+        let _ = self.tx.send(event);
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+// endregion: -- EventBus
+
+// region: -- Db Events Bridge
+/// The Postgres NOTIFY channel of the events
+const DB_EVENTS_CHANNEL: &str = "model_event";
+
+// NOTE: The NOTIFY payloads are limited to 8000 bytes. A bigger deleted row
+// is not sent (the readers then can't match it, see base::row_matches).
+const DB_EVENT_PAYLOAD_MAX: usize = 7900;
+
+// NOTE: The ModelEvent entity is a &'static str (the DbBmc::TABLE),
+// so a notified entity is mapped back to its table (unknown ones are skipped).
+const DB_EVENTS_ENTITIES: &[&str] = &[
+    AlertEventBmc::TABLE,
+    AlertRuleBmc::TABLE,
+    IngestRunBmc::TABLE,
+    TaskBmc::TABLE,
+    TokenBmc::TABLE,
+    TokenSnapshotBmc::TABLE,
+    UserBmc::TABLE,
+    WatchlistBmc::TABLE,
+];
+
+/// Send the events to the other processes (one NOTIFY per event, in one query)
+// NOTE: After the commit, so a failure is only logged (the writes are done).
+pub(in crate::model) async fn notify_db(db: &Db, bus: &EventBus, events: &[ModelEvent]) {
+    let payloads: Vec<String> = events
+        .iter()
+        .map(|event| to_db_payload(bus.origin, event))
+        .collect();
+
+    let res = sqlx::query("SELECT pg_notify($1, payload) FROM unnest($2::text[]) AS payload")
+        .bind(DB_EVENTS_CHANNEL)
+        .bind(payloads)
+        .execute(db)
+        .await;
+    if let Err(ex) = res {
+        warn!("{:<12} - db events notify failed - {ex}", "EVENTS");
+    }
+}
+
+/// Publish the events of the other processes on the bus (from a background task)
+pub(in crate::model) async fn listen_db(bus: EventBus) -> Result<()> {
+    // NOTE: Its own connection (not one of the pool), held by the listener.
+    let mut listener = PgListener::connect(&core_config().DB_URL).await?;
+    listener.listen(DB_EVENTS_CHANNEL).await?;
+
+    tokio::spawn(async move {
+        loop {
+            match listener.recv().await {
+                Ok(notification) => match from_db_payload(notification.payload()) {
+                    Some((origin, event)) if origin != bus.origin => bus.publish(event),
+                    Some(_) => (),
+                    None => warn!(
+                        "{:<12} - db event payload invalid - {}",
+                        "EVENTS",
+                        notification.payload()
+                    ),
+                },
+                // NOTE: The listener reconnects on the next recv (the events in between are lost).
+                Err(ex) => {
+                    warn!("{:<12} - db events listen failed - {ex}", "EVENTS");
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// The event json, with its origin and deleted row (not in the event json)
+fn to_db_payload(origin: Uuid, event: &ModelEvent) -> String {
+    let mut payload = serde_json::to_value(event).unwrap_or_default();
+    payload["origin"] = json!(origin.to_string());
+    if let ModelEvent::EntityDeleted { row, .. } = event {
+        payload["row"] = row.clone();
+    }
+
+    let payload = payload.to_string();
+    if payload.len() > DB_EVENT_PAYLOAD_MAX {
+        return to_db_payload(origin, &without_row(event));
+    }
+    payload
+}
+
+fn without_row(event: &ModelEvent) -> ModelEvent {
+    match event {
+        ModelEvent::EntityDeleted {
+            entity,
+            id,
+            user_id,
+            ..
+        } => ModelEvent::EntityDeleted {
+            entity,
+            id: *id,
+            user_id: *user_id,
+            row: Value::Null,
+        },
+        event => event.clone(),
+    }
+}
+
+fn from_db_payload(payload: &str) -> Option<(Uuid, ModelEvent)> {
+    let payload: Value = serde_json::from_str(payload).ok()?;
+    let origin = payload["origin"].as_str()?.parse().ok()?;
+    let entity = DB_EVENTS_ENTITIES
+        .iter()
+        .find(|entity| Some(**entity) == payload["entity"].as_str())?;
+    let id = payload["id"].as_i64()?;
+    let user_id = payload["user_id"].as_i64()?;
+
+    let event = match payload["type"].as_str()? {
+        "EntityCreated" => ModelEvent::EntityCreated {
+            entity,
+            id,
+            user_id,
+        },
+        "EntityUpdated" => ModelEvent::EntityUpdated {
+            entity,
+            id,
+            user_id,
+        },
+        "EntityDeleted" => ModelEvent::EntityDeleted {
+            entity,
+            id,
+            user_id,
+            row: payload["row"].clone(),
+        },
+        _ => return None,
+    };
+
+    Some((origin, event))
+}
+// endregion: -- Db Events Bridge
+
+// region: -- Tests
+#[cfg(test)]
+mod tests {
+    #![allow(unused)]
+    pub type Result<T> = core::result::Result<T, Error>;
+    pub type Error = Box<dyn std::error::Error>; // For early dev & tests.
+
+    use super::*;
+    use crate::_dev_utils;
+    use crate::ctx::Ctx;
+    use crate::model::task::{TaskBmc, TaskForCreate, TaskForUpdate};
+    use crate::model::token::{TokenBmc, TokenForCreate};
+    use crate::model::ModelManager;
+    use serial_test::serial;
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    /// The received events of the entity (the other entities events are skipped).
+    fn recv_events(rx: &mut broadcast::Receiver<ModelEvent>, entity: &str) -> Vec<ModelEvent> {
+        let mut events = Vec::new();
+        loop {
+            match rx.try_recv() {
+                Ok(event) if event.entity() == entity => events.push(event),
+                Ok(_) | Err(TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+        events
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_events_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let mut rx = mm.events().subscribe();
+        let fx_task_c = |title: &str| TaskForCreate {
+            title: title.to_string(),
+        };
+
+        // -- Exec & Check: create, update, delete
+        let id = TaskBmc::create(&ctx, &mm, fx_task_c("test_events_ok")).await?;
+        TaskBmc::update(
+            &ctx,
+            &mm,
+            id,
+            TaskForUpdate {
+                title: Some("test_events_ok - updated".to_string()),
+                ..Default::default()
+            },
+        )
+        .await?;
+        TaskBmc::delete(&ctx, &mm, id).await?;
+        let events = recv_events(&mut rx, "task");
+        assert_eq!(
+            events[..2],
+            [
+                ModelEvent::EntityCreated {
+                    entity: "task",
+                    id,
+                    user_id: 0
+                },
+                ModelEvent::EntityUpdated {
+                    entity: "task",
+                    id,
+                    user_id: 0
+                },
+            ]
+        );
+        let [_, _, ModelEvent::EntityDeleted {
+            entity: "task",
+            id: deleted_id,
+            user_id: 0,
+            row,
+        }] = events.as_slice()
+        else {
+            return Err(format!("unexpected events: {events:?}").into());
+        };
+        assert_eq!(*deleted_id, id);
+        assert_eq!(row["id"], id);
+        assert_eq!(row["title"], "test_events_ok - updated");
+        assert_eq!(row["owner_id"], 0);
+
+        // -- Exec & Check: txn, held until the commit
+        let mm_txn = mm.new_with_txn()?;
+        mm_txn.begin_txn().await?;
+        let id = TaskBmc::create(&ctx, &mm_txn, fx_task_c("test_events_ok - txn")).await?;
+        assert!(recv_events(&mut rx, "task").is_empty());
+        mm_txn.commit_txn().await?;
+        let events = recv_events(&mut rx, "task");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id(), id);
+
+        // -- Exec & Check: txn, dropped on rollback
+        let mm_txn = mm.new_with_txn()?;
+        mm_txn.begin_txn().await?;
+        TaskBmc::delete(&ctx, &mm_txn, id).await?;
+        mm_txn.rollback_txn().await?;
+        assert!(recv_events(&mut rx, "task").is_empty());
+
+        // -- Exec & Check: upsert, created then updated
+        let fx_token_c = || TokenForCreate {
+            address: "test_events_ok-address".to_string(),
+            ..Default::default()
+        };
+        let token_id = TokenBmc::upsert(&ctx, &mm, fx_token_c()).await?;
+        TokenBmc::upsert(&ctx, &mm, fx_token_c()).await?;
+        let events = recv_events(&mut rx, "token");
+        assert!(matches!(
+            events.as_slice(),
+            [
+                ModelEvent::EntityCreated { .. },
+                ModelEvent::EntityUpdated { .. }
+            ]
+        ));
+        assert!(events.iter().all(|event| event.id() == token_id));
+
+        // -- Clean
+        TaskBmc::delete(&ctx, &mm, id).await?;
+        TokenBmc::delete(&ctx, &mm, token_id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_db_events_bridge_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        // NOTE: Two ModelManagers (own buses), as two processes.
+        _dev_utils::init_dev().await;
+        let ctx = Ctx::root_ctx();
+        let mm_reader = ModelManager::new().await?;
+        mm_reader.listen_db_events().await?;
+        let mut reader_rx = mm_reader.events().subscribe();
+        let mm_writer = ModelManager::new().await?.with_db_events_notify();
+        mm_writer.listen_db_events().await?;
+        let mut writer_rx = mm_writer.events().subscribe();
+
+        // -- Exec
+        let id = TaskBmc::create(
+            &ctx,
+            &mm_writer,
+            TaskForCreate {
+                title: "test_db_events_bridge_ok".to_string(),
+            },
+        )
+        .await?;
+        TaskBmc::delete(&ctx, &mm_writer, id).await?;
+
+        // -- Check: the reader gets the events (with the deleted row)
+        let mut events = Vec::new();
+        while events.len() < 2 {
+            let event = tokio::time::timeout(Duration::from_secs(5), reader_rx.recv()).await??;
+            if event.entity() == "task" && event.id() == id {
+                events.push(event);
+            }
+        }
+        assert_eq!(
+            events[0],
+            ModelEvent::EntityCreated {
+                entity: "task",
+                id,
+                user_id: 0
+            }
+        );
+        let ModelEvent::EntityDeleted { row, .. } = &events[1] else {
+            return Err(format!("should be EntityDeleted, but was {:?}", events[1]).into());
+        };
+        assert_eq!(row["title"], "test_db_events_bridge_ok");
+
+        // -- Check: the writer gets its events once (its own notifications are skipped)
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let writer_events = recv_events(&mut writer_rx, "task");
+        let ids: Vec<i64> = writer_events.iter().map(|event| event.id()).collect();
+        assert_eq!(ids, [id, id]);
+
+        Ok(())
+    }
+}
+// endregion: -- Tests
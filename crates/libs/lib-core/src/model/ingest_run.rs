@@ -0,0 +1,196 @@
+// NOTE: The ingestion runs status (e.g., the token-ingest service), recorded
+// for observability: started as 'running', then finished as 'ok' or 'fail'.
+use crate::ctx::Ctx;
+use crate::model::base::{self, DbBmc};
+use crate::model::ModelManager;
+use crate::model::Result;
+use lib_utils::time::{now_utc, Rfc3339};
+use modql::field::Fields;
+use modql::filter::{FilterNodes, ListOptions, OpValsInt64, OpValsString};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+// region: -- IngestRun Types
+#[serde_as]
+#[derive(Debug, Clone, Fields, FromRow, Serialize)]
+pub struct IngestRun {
+    pub id: i64,
+    pub source: String,
+    pub status: String,
+
+    pub pages: i64,
+    pub tokens: i64,
+    pub retries: i64,
+    pub error: Option<String>,
+
+    #[serde_as(as = "Rfc3339")]
+    pub started_at: OffsetDateTime,
+    #[serde_as(as = "Option<Rfc3339>")]
+    pub finished_at: Option<OffsetDateTime>,
+}
+
+/// The status of a run, stored in the ingest_run.status column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IngestRunStatus {
+    Running,
+    Ok,
+    Fail,
+}
+
+impl IngestRunStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IngestRunStatus::Running => "running",
+            IngestRunStatus::Ok => "ok",
+            IngestRunStatus::Fail => "fail",
+        }
+    }
+}
+
+#[derive(Fields)]
+struct IngestRunForStart {
+    source: String,
+    status: String,
+}
+
+/// The counters of a finished run (error is Some when the run failed)
+#[derive(Debug, Default)]
+pub struct IngestRunForFinish {
+    pub pages: i64,
+    pub tokens: i64,
+    pub retries: i64,
+    pub error: Option<String>,
+}
+
+#[derive(Fields)]
+struct IngestRunForUpdate {
+    status: String,
+    pages: i64,
+    tokens: i64,
+    retries: i64,
+    error: Option<String>,
+    finished_at: OffsetDateTime,
+}
+
+/// Filter by custom fields (e.g., the failed runs of a source)
+#[derive(FilterNodes, Deserialize, Default, Debug)]
+pub struct IngestRunFilter {
+    id: Option<OpValsInt64>,
+
+    source: Option<OpValsString>,
+    status: Option<OpValsString>,
+}
+// endregion: -- IngestRun Types
+
+// region: -- IngestRunBmc
+pub struct IngestRunBmc;
+
+impl DbBmc for IngestRunBmc {
+    const TABLE: &'static str = "ingest_run";
+}
+
+impl IngestRunBmc {
+    /// Record the start of a run ('running'). Returns the run id.
+    pub async fn start(ctx: &Ctx, mm: &ModelManager, source: &str) -> Result<i64> {
+        let run_fs = IngestRunForStart {
+            source: source.to_string(),
+            status: IngestRunStatus::Running.as_str().to_string(),
+        };
+
+        base::create::<Self, _>(ctx, mm, run_fs).await
+    }
+
+    /// Record the end of a run, 'ok' or 'fail' (when run_ff.error is Some).
+    pub async fn finish(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        run_ff: IngestRunForFinish,
+    ) -> Result<()> {
+        let IngestRunForFinish {
+            pages,
+            tokens,
+            retries,
+            error,
+        } = run_ff;
+        let status = match error {
+            None => IngestRunStatus::Ok,
+            Some(_) => IngestRunStatus::Fail,
+        };
+        let run_fu = IngestRunForUpdate {
+            status: status.as_str().to_string(),
+            pages,
+            tokens,
+            retries,
+            error,
+            finished_at: now_utc(),
+        };
+
+        base::update::<Self, _>(ctx, mm, id, run_fu).await
+    }
+
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<IngestRun> {
+        base::get::<Self, _>(ctx, mm, id).await
+    }
+
+    pub async fn list(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<IngestRunFilter>>,
+        list_options: Option<ListOptions>,
+    ) -> Result<Vec<IngestRun>> {
+        base::list::<Self, _, _>(ctx, mm, filters, list_options).await
+    }
+}
+// endregion: -- IngestRunBmc
+
+// region: -- Tests
+#[cfg(test)]
+mod tests {
+    #![allow(unused)]
+    pub type Result<T> = core::result::Result<T, Error>;
+    pub type Error = Box<dyn std::error::Error>; // For early dev & tests.
+
+    use super::*;
+    use crate::_dev_utils;
+    use serial_test::serial;
+
+    #[serial]
+    #[tokio::test]
+    async fn test_start_finish_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_source = "test_start_finish_ok";
+
+        // -- Exec & Check: start
+        let id = IngestRunBmc::start(&ctx, &mm, fx_source).await?;
+        let run = IngestRunBmc::get(&ctx, &mm, id).await?;
+        assert_eq!(run.status, "running");
+        assert!(run.finished_at.is_none());
+
+        // -- Exec & Check: finish (fail)
+        IngestRunBmc::finish(
+            &ctx,
+            &mm,
+            id,
+            IngestRunForFinish {
+                pages: 2,
+                retries: 1,
+                error: Some("boom".to_string()),
+                ..Default::default()
+            },
+        )
+        .await?;
+        let run = IngestRunBmc::get(&ctx, &mm, id).await?;
+        assert_eq!(run.status, "fail");
+        assert_eq!(run.pages, 2);
+        assert_eq!(run.error.as_deref(), Some("boom"));
+        assert!(run.finished_at.is_some());
+
+        Ok(())
+    }
+}
+// endregion: -- Tests
@@ -22,27 +22,45 @@
 //! - In frameworks like Axum, Tauri, `ModelManager` are typically used as App State.
 //! - ModelManager are designed to be passed as an argument
 //!   to all Model Controllers functions.
-
+//!
 //! NOTE: We're adding a store layer as well. I believe
 //! it'll go inside the Model Manager as a Model Controller,
 //! but we'll see...
-
+//!
 //! NOTE: This model module is the only one that touches the Db store
 //! REF: https://youtu.be/-dMH9UiwKqg?list=PL7r-PXl6ZPcCIOFaL7nVHXZvBmHNhrh_Q&t=1273
 
 // region:       -- Modules
 
-mod base;
+pub mod alert;
+pub mod audit;
+pub(crate) mod base;
 mod error;
+pub mod event;
+pub mod ingest_run;
+mod modql_utils;
+pub mod refresh_token;
 mod store;
 pub mod task;
 pub mod token;
+pub mod token_snapshot;
 pub mod user;
+pub mod watchlist;
 
 // Re-export our model module Error and Result aliases
+pub use self::base::ListPage;
 pub use self::error::{Error, Result};
 
-use crate::model::store::{new_db_pool, Db};
+use crate::core_config;
+use crate::model::audit::AuditInfo;
+use crate::model::event::{EventBus, ModelEvent};
+use crate::model::store::{new_db_pool, new_db_pool_lazy, Dbx};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+// NOTE: Exposed to the crate (e.g., _dev_utils::dev_db) so the dev db
+// schema comes from the same migration files.
+pub(crate) use crate::model::store::migrate::run_migrations;
 
 // endregion:    -- Modules
 
@@ -74,7 +92,16 @@ pub struct ModelManager {
     // redis: RedisConnector,
     // s3: S3Bucket,
     // etc.
-    db: Db,
+    // NOTE: U: The db pool is now wrapped in the Dbx executor, which
+    // optionally holds a (shared) transaction. See new_with_txn().
+    dbx: Dbx,
+    // NOTE: U: The model change events (see model::event). The bus is shared by
+    // all the ModelManager clones, and the pending events by the ones of a txn.
+    events: EventBus,
+    pending_events: Arc<Mutex<Vec<ModelEvent>>>,
+    // NOTE: U: The rpc call of the writes, for the audit log (see model::audit).
+    // None for the writes outside of an rpc call (e.g., ingestion).
+    audit: Option<Arc<AuditInfo>>,
 }
 
 impl ModelManager {
@@ -84,20 +111,155 @@ impl ModelManager {
         // let mc = ModelController::new().await?;
         let db = new_db_pool().await?;
 
+        // NOTE: U: Apply the pending sql/migrations (opt-in, see CoreConfig.DB_MIGRATE)
+        if core_config().DB_MIGRATE {
+            run_migrations(&db, Path::new(&core_config().DB_MIGRATIONS_DIR)).await?;
+        }
+
         // Ok(ModelManager { mc })
-        Ok(ModelManager { db })
+        Ok(ModelManager {
+            dbx: Dbx::new(db, false),
+            events: EventBus::new(),
+            pending_events: Arc::default(),
+            audit: None,
+        })
+    }
+
+    /// Constructor with a lazy db pool (no connection until the first query),
+    /// for the tests that never touch the db (see _dev_utils::init_test_no_db).
+    pub(crate) fn new_lazy() -> Result<Self> {
+        Ok(ModelManager {
+            dbx: Dbx::new(new_db_pool_lazy()?, false),
+            events: EventBus::new(),
+            pending_events: Arc::default(),
+            audit: None,
+        })
+    }
+
+    /// Returns a ModelManager in transaction mode (same db pool).
+    /// All the `*Bmc` calls with it run inside the transaction opened with
+    /// `begin_txn()` (until `commit_txn()`/`rollback_txn()`).
+    // NOTE: If this ModelManager is already in txn mode, it is cloned (sharing
+    // the same transaction) with a new txn scope, so begin/commit calls nest.
+    pub fn new_with_txn(&self) -> Result<ModelManager> {
+        if self.dbx.with_txn() {
+            return Ok(ModelManager {
+                dbx: self.dbx.new_txn_scope(),
+                ..self.clone()
+            });
+        }
+
+        Ok(ModelManager {
+            dbx: Dbx::new(self.dbx.db().clone(), true),
+            events: self.events.clone(),
+            pending_events: Arc::default(),
+            audit: self.audit.clone(),
+        })
+    }
+
+    /// Returns a ModelManager (same db pool, txn and events) recording its
+    /// writes in the audit log with the rpc call info (see model::audit).
+    pub fn with_audit(&self, audit_info: AuditInfo) -> ModelManager {
+        ModelManager {
+            audit: Some(Arc::new(audit_info)),
+            ..self.clone()
+        }
+    }
+
+    pub(in crate::model) fn audit_info(&self) -> Option<&AuditInfo> {
+        self.audit.as_deref()
+    }
+
+    /// Returns the ModelManager also sending its committed events to the other
+    /// processes (Postgres NOTIFY), e.g., for a worker writing outside of the web-server.
+    pub fn with_db_events_notify(self) -> ModelManager {
+        ModelManager {
+            events: self.events.with_db_notify(),
+            ..self
+        }
+    }
+
+    /// Publish the events of the other processes (see with_db_events_notify)
+    /// on the events bus, from a background task.
+    pub async fn listen_db_events(&self) -> Result<()> {
+        event::listen_db(self.events.clone()).await
     }
+
+    pub async fn begin_txn(&self) -> Result<()> {
+        self.dbx.begin_txn().await?;
+        Ok(())
+    }
+
+    /// Commit the transaction (the outermost commit also publishes the
+    /// events held during the transaction).
+    pub async fn commit_txn(&self) -> Result<()> {
+        let committed = self.dbx.commit_txn().await.inspect_err(|_| {
+            self.take_pending_events();
+        })?;
+
+        if committed {
+            self.dispatch_events(self.take_pending_events()).await;
+        }
+
+        Ok(())
+    }
+
+    pub async fn rollback_txn(&self) -> Result<()> {
+        self.take_pending_events();
+        self.dbx.rollback_txn().await?;
+        Ok(())
+    }
+
+    /// The model change events bus (e.g., `mm.events().subscribe()`)
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    /// Publish the event now, or when a txn is open, at its commit.
+    pub(in crate::model) async fn publish_event(&self, event: ModelEvent) {
+        if self.dbx.is_txn_open().await {
+            self.pending_events
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .push(event);
+        } else {
+            self.dispatch_events(vec![event]).await;
+        }
+    }
+
+    async fn dispatch_events(&self, events: Vec<ModelEvent>) {
+        if events.is_empty() {
+            return;
+        }
+        if self.events.db_notify() {
+            event::notify_db(self.dbx.db(), &self.events, &events).await;
+        }
+        for event in events {
+            self.events.publish(event);
+        }
+    }
+
+    fn take_pending_events(&self) -> Vec<ModelEvent> {
+        std::mem::take(
+            &mut *self
+                .pending_events
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        )
+    }
+
+    // NOTE: U: Now returns the Dbx (db pool + optional txn) executor.
     // NOTE: Only want to expose our Db (the db pool) ONLY
     // to the Model layer, and the 'new' accessible to other
     // modules such as main.rs.
-    // E.g., If we tried from main.rs to use 'let db = mm.db()' it would fail!
+    // E.g., If we tried from main.rs to use 'let dbx = mm.dbx()' it would fail!
     // NOTE: To restrict a function to ONLY sub-modules (i.e., store, error, model)
     // we use (in crate::model) syntax.
     // NOTE: What we end up with is our ModelManager::new() is accessible to
     // all other modules in the code base. Whereas ONLY the model layer
     // has access to the store (Db). Specifically, this returns the
     // sqlx db pool reference ONLY for the model layer.
-    pub(in crate::model) fn db(&self) -> &Db {
-        &self.db
+    pub(in crate::model) fn dbx(&self) -> &Dbx {
+        &self.dbx
     }
 }
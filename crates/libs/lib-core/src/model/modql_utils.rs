@@ -0,0 +1,15 @@
+// NOTE: Helpers for modql filters on types that are not plain json values
+// (e.g., OffsetDateTime), used with #[modql(to_sea_value_fn = "...")].
+use lib_utils::time::parse_utc;
+use modql::filter::{IntoSeaError, SeaResult};
+use serde_json::Value;
+
+/// Convert a json Rfc3339 time string (e.g., "2024-03-14T08:08:09Z") into a sea-query time value
+pub fn time_to_sea_value(json_value: Value) -> SeaResult<sea_query::Value> {
+    let moment = json_value
+        .as_str()
+        .ok_or_else(|| IntoSeaError::custom(format!("time should be a string: {json_value}")))?;
+    let time = parse_utc(moment).map_err(|err| IntoSeaError::custom(err.to_string()))?;
+
+    Ok(time.into())
+}
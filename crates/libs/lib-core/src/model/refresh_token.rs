@@ -0,0 +1,184 @@
+// NOTE: Persisted refresh tokens (see lib_auth::token::generate_refresh_token
+// and web-server /api/refresh). Only the token ident (jti) is stored, the
+// signature is validated with the user token_salt.
+use crate::ctx::Ctx;
+use crate::model::base::{self, DbBmc};
+use crate::model::ModelManager;
+use crate::model::Result;
+use modql::field::{Fields, HasFields};
+use sea_query::{Expr, Iden, PostgresQueryBuilder, Query};
+use sea_query_binder::SqlxBinder;
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+// region: -- RefreshToken Types
+#[derive(Debug, Clone, Fields, FromRow)]
+pub struct RefreshToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub jti: Uuid,
+    pub family_id: Uuid,
+
+    pub exp: OffsetDateTime,
+    pub used: bool,
+    pub revoked: bool,
+}
+
+#[derive(Fields)]
+pub struct RefreshTokenForCreate {
+    pub user_id: i64,
+    pub jti: Uuid,
+    pub family_id: Uuid,
+    pub exp: OffsetDateTime,
+}
+
+#[derive(Iden)]
+enum RefreshTokenIden {
+    Id,
+    Jti,
+    FamilyId,
+    Used,
+    Revoked,
+}
+// endregion: -- RefreshToken Types
+
+// region: -- RefreshTokenBmc
+pub struct RefreshTokenBmc;
+
+impl DbBmc for RefreshTokenBmc {
+    const TABLE: &'static str = "refresh_token";
+}
+
+impl RefreshTokenBmc {
+    pub async fn create(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        refresh_token_c: RefreshTokenForCreate,
+    ) -> Result<i64> {
+        base::create::<Self, _>(ctx, mm, refresh_token_c).await
+    }
+
+    pub async fn first_by_jti(
+        _ctx: &Ctx,
+        mm: &ModelManager,
+        jti: Uuid,
+    ) -> Result<Option<RefreshToken>> {
+        let dbx = mm.dbx();
+
+        // -- Build query
+        let mut query = Query::select();
+        query
+            .from(Self::table_ref())
+            .columns(RefreshToken::field_idens())
+            .and_where(Expr::col(RefreshTokenIden::Jti).eq(jti));
+
+        // -- Exec query
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        let refresh_token = dbx
+            .fetch_optional(sqlx::query_as_with::<_, RefreshToken, _>(&sql, values))
+            .await?;
+
+        Ok(refresh_token)
+    }
+
+    /// Mark the refresh token as used.
+    /// Returns false if it was already used (i.e., a reuse).
+    // NOTE: The "used = false" condition makes this check-and-set atomic,
+    // so two concurrent refreshes with the same token cannot both succeed.
+    pub async fn mark_used(_ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<bool> {
+        let dbx = mm.dbx();
+
+        // -- Build query
+        let mut query = Query::update();
+        query
+            .table(Self::table_ref())
+            .value(RefreshTokenIden::Used, true)
+            .and_where(Expr::col(RefreshTokenIden::Id).eq(id))
+            .and_where(Expr::col(RefreshTokenIden::Used).eq(false));
+
+        // -- Exec query
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        let count = dbx.execute(sqlx::query_with(&sql, values)).await?;
+
+        Ok(count == 1)
+    }
+
+    /// Revoke all the refresh tokens of a family (e.g., on reuse detection)
+    pub async fn revoke_family(_ctx: &Ctx, mm: &ModelManager, family_id: Uuid) -> Result<()> {
+        let dbx = mm.dbx();
+
+        // -- Build query
+        let mut query = Query::update();
+        query
+            .table(Self::table_ref())
+            .value(RefreshTokenIden::Revoked, true)
+            .and_where(Expr::col(RefreshTokenIden::FamilyId).eq(family_id));
+
+        // -- Exec query
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        dbx.execute(sqlx::query_with(&sql, values)).await?;
+
+        Ok(())
+    }
+}
+// endregion: -- RefreshTokenBmc
+
+// region: -- Tests
+#[cfg(test)]
+mod tests {
+    #![allow(unused)]
+    pub type Result<T> = core::result::Result<T, Error>;
+    pub type Error = Box<dyn std::error::Error>; // For early dev & tests.
+
+    use super::*;
+    use crate::_dev_utils;
+    use crate::model::user::{User, UserBmc};
+    use lib_utils::time::now_utc;
+    use serial_test::serial;
+
+    #[serial]
+    #[tokio::test]
+    async fn test_mark_used_and_revoke_family_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let user: User = UserBmc::first_by_username(&ctx, &mm, "demo1")
+            .await?
+            .ok_or("Should have user 'demo1'")?;
+        let fx_family_id = Uuid::new_v4();
+        let fx_jtis = [Uuid::new_v4(), Uuid::new_v4()];
+        for jti in fx_jtis {
+            RefreshTokenBmc::create(
+                &ctx,
+                &mm,
+                RefreshTokenForCreate {
+                    user_id: user.id,
+                    jti,
+                    family_id: fx_family_id,
+                    exp: now_utc(),
+                },
+            )
+            .await?;
+        }
+        let refresh_token = RefreshTokenBmc::first_by_jti(&ctx, &mm, fx_jtis[0])
+            .await?
+            .ok_or("Should have refresh token")?;
+
+        // -- Exec & Check: single use
+        assert!(RefreshTokenBmc::mark_used(&ctx, &mm, refresh_token.id).await?);
+        assert!(!RefreshTokenBmc::mark_used(&ctx, &mm, refresh_token.id).await?);
+
+        // -- Exec & Check: revoke family
+        RefreshTokenBmc::revoke_family(&ctx, &mm, fx_family_id).await?;
+        for jti in fx_jtis {
+            let refresh_token = RefreshTokenBmc::first_by_jti(&ctx, &mm, jti)
+                .await?
+                .ok_or("Should have refresh token")?;
+            assert!(refresh_token.revoked);
+        }
+
+        Ok(())
+    }
+}
+// endregion: -- Tests
@@ -0,0 +1,263 @@
+//! Dbx - The db executor of the ModelManager
+//!
+//! Design:
+//! - Wraps the db pool, and (when created in txn mode) an optional shared
+//!   transaction. All the model queries go through the Dbx `fetch_*`/`execute`,
+//!   so the `*Bmc` functions transparently run inside the transaction when
+//!   the ModelManager has one open, and on the pool otherwise.
+//! - `begin_txn`/`commit_txn` nest (counter), so a Bmc function can wrap its own
+//!   multi-step work in a transaction and still be part of a caller transaction.
+//! - Each nested scope (see `new_txn_scope`) tracks its own begins. When it is
+//!   dropped with a begin not committed (e.g., a Bmc error returned with `?`),
+//!   the transaction becomes rollback-only, and the outermost `commit_txn`
+//!   rolls it back and fails (TxnRollbackOnly) instead of silently committing.
+//! - A dropped (not committed) transaction is rolled back by sqlx.
+
+use super::{Db, Error, Result};
+use sqlx::postgres::PgRow;
+use sqlx::query::{Query, QueryAs};
+use sqlx::{FromRow, IntoArguments, Postgres, Transaction};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+// NOTE: The query fns return the sqlx::Error as is (not wrapped in a store::Error),
+// so the model layer can keep matching on it (e.g., RowNotFound, db error codes).
+type SqlxResult<T> = core::result::Result<T, sqlx::Error>;
+
+#[derive(Clone)]
+pub struct Dbx {
+    db_pool: Db,
+    txn_holder: Arc<Mutex<Option<TxnHolder>>>,
+    txn_state: Arc<TxnState>,
+    txn_scope: Arc<TxnScope>,
+    with_txn: bool,
+}
+
+struct TxnHolder {
+    txn: Transaction<'static, Postgres>,
+    counter: i32,
+}
+
+/// The txn state shared by all the scopes of a Dbx
+#[derive(Default)]
+struct TxnState {
+    // NOTE: Incremented when the txn ends, so a scope dropped later
+    // cannot mark the next txn as rollback-only.
+    generation: AtomicU64,
+    rollback_only: AtomicBool,
+    /// The begins of the dropped scopes, never committed
+    abandoned: AtomicI32,
+}
+
+/// The begins (not yet committed) of one scope
+struct TxnScope {
+    state: Arc<TxnState>,
+    open: AtomicI32,
+    generation: AtomicU64,
+}
+
+impl TxnScope {
+    fn new(state: Arc<TxnState>) -> Self {
+        TxnScope {
+            state,
+            open: AtomicI32::new(0),
+            generation: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Drop for TxnScope {
+    fn drop(&mut self) {
+        let open = *self.open.get_mut();
+        let generation = *self.generation.get_mut();
+        if open > 0 && generation == self.state.generation.load(Ordering::SeqCst) {
+            self.state.rollback_only.store(true, Ordering::SeqCst);
+            self.state.abandoned.fetch_add(open, Ordering::SeqCst);
+        }
+    }
+}
+
+// region: -- Constructor & Txn
+impl Dbx {
+    pub fn new(db_pool: Db, with_txn: bool) -> Self {
+        let txn_state = Arc::new(TxnState::default());
+        Dbx {
+            db_pool,
+            txn_holder: Arc::default(),
+            txn_scope: Arc::new(TxnScope::new(txn_state.clone())),
+            txn_state,
+            with_txn,
+        }
+    }
+
+    /// Returns a Dbx sharing the same txn, with its own scope (nested begin/commit)
+    pub fn new_txn_scope(&self) -> Self {
+        Dbx {
+            txn_scope: Arc::new(TxnScope::new(self.txn_state.clone())),
+            ..self.clone()
+        }
+    }
+
+    pub fn db(&self) -> &Db {
+        &self.db_pool
+    }
+
+    pub fn with_txn(&self) -> bool {
+        self.with_txn
+    }
+
+    pub async fn begin_txn(&self) -> Result<()> {
+        if !self.with_txn {
+            return Err(Error::TxnCantBeginNotInTxnMode);
+        }
+
+        let mut txh_g = self.txn_holder.lock().await;
+        match txh_g.as_mut() {
+            // Nested begin, just increment the counter
+            Some(txh) => txh.counter += 1 - self.take_abandoned(),
+            None => {
+                let txn = self.db_pool.begin().await?;
+                *txh_g = Some(TxnHolder { txn, counter: 1 });
+            }
+        }
+
+        let generation = self.txn_state.generation.load(Ordering::SeqCst);
+        self.txn_scope
+            .generation
+            .store(generation, Ordering::SeqCst);
+        self.txn_scope.open.fetch_add(1, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    /// Returns true when the transaction got committed (the outermost commit).
+    // NOTE: The outermost commit of a rollback-only txn (see TxnScope)
+    // rolls it back and returns Err(TxnRollbackOnly).
+    pub async fn commit_txn(&self) -> Result<bool> {
+        let mut txh_g = self.txn_holder.lock().await;
+        let Some(txh) = txh_g.as_mut() else {
+            return Err(Error::TxnCantCommitNoOpenTxn);
+        };
+        txh.counter -= self.take_abandoned();
+        self.txn_scope.open.fetch_sub(1, Ordering::SeqCst);
+
+        // Nested commit, the outermost commit does the actual commit
+        if txh.counter > 1 {
+            txh.counter -= 1;
+            return Ok(false);
+        }
+
+        let txh = txh_g.take();
+        let rollback_only = self.end_txn_state();
+        if let Some(txh) = txh {
+            if rollback_only {
+                txh.txn.rollback().await?;
+                return Err(Error::TxnRollbackOnly);
+            }
+            txh.txn.commit().await?;
+        }
+
+        Ok(true)
+    }
+
+    pub async fn is_txn_open(&self) -> bool {
+        self.with_txn && self.txn_holder.lock().await.is_some()
+    }
+
+    /// Rollback the whole transaction (even when nested)
+    pub async fn rollback_txn(&self) -> Result<()> {
+        let mut txh_g = self.txn_holder.lock().await;
+        let Some(txh) = txh_g.take() else {
+            return Err(Error::TxnCantRollbackNoOpenTxn);
+        };
+        self.txn_scope.open.store(0, Ordering::SeqCst);
+        self.end_txn_state();
+        txh.txn.rollback().await?;
+
+        Ok(())
+    }
+
+    /// Take the count of the begins abandoned by the dropped scopes
+    fn take_abandoned(&self) -> i32 {
+        self.txn_state.abandoned.swap(0, Ordering::SeqCst)
+    }
+
+    /// Reset the txn state for the next txn, returning if it was rollback-only
+    fn end_txn_state(&self) -> bool {
+        self.txn_state.generation.fetch_add(1, Ordering::SeqCst);
+        self.txn_state.abandoned.store(0, Ordering::SeqCst);
+        self.txn_state.rollback_only.swap(false, Ordering::SeqCst)
+    }
+}
+// endregion: -- Constructor & Txn
+
+// region: -- Query Executors
+impl Dbx {
+    pub async fn fetch_one<'q, O, A>(&self, query: QueryAs<'q, Postgres, O, A>) -> SqlxResult<O>
+    where
+        O: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+        A: IntoArguments<'q, Postgres> + 'q,
+    {
+        if self.with_txn {
+            let mut txh_g = self.txn_holder.lock().await;
+            if let Some(txh) = txh_g.as_mut() {
+                return query.fetch_one(&mut *txh.txn).await;
+            }
+        }
+
+        query.fetch_one(self.db()).await
+    }
+
+    pub async fn fetch_optional<'q, O, A>(
+        &self,
+        query: QueryAs<'q, Postgres, O, A>,
+    ) -> SqlxResult<Option<O>>
+    where
+        O: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+        A: IntoArguments<'q, Postgres> + 'q,
+    {
+        if self.with_txn {
+            let mut txh_g = self.txn_holder.lock().await;
+            if let Some(txh) = txh_g.as_mut() {
+                return query.fetch_optional(&mut *txh.txn).await;
+            }
+        }
+
+        query.fetch_optional(self.db()).await
+    }
+
+    pub async fn fetch_all<'q, O, A>(
+        &self,
+        query: QueryAs<'q, Postgres, O, A>,
+    ) -> SqlxResult<Vec<O>>
+    where
+        O: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+        A: IntoArguments<'q, Postgres> + 'q,
+    {
+        if self.with_txn {
+            let mut txh_g = self.txn_holder.lock().await;
+            if let Some(txh) = txh_g.as_mut() {
+                return query.fetch_all(&mut *txh.txn).await;
+            }
+        }
+
+        query.fetch_all(self.db()).await
+    }
+
+    /// Execute the query and return the number of rows affected
+    pub async fn execute<'q, A>(&self, query: Query<'q, Postgres, A>) -> SqlxResult<u64>
+    where
+        A: IntoArguments<'q, Postgres> + 'q,
+    {
+        if self.with_txn {
+            let mut txh_g = self.txn_holder.lock().await;
+            if let Some(txh) = txh_g.as_mut() {
+                return Ok(query.execute(&mut *txh.txn).await?.rows_affected());
+            }
+        }
+
+        Ok(query.execute(self.db()).await?.rows_affected())
+    }
+}
+// endregion: -- Query Executors
@@ -6,7 +6,33 @@ pub type Result<T> = core::result::Result<T, Error>;
 pub enum Error {
     // Eventually we'll use sqlx and sqlb for errors (I think...)
     FailToCreatePool(String),
+
+    // -- Migrations
+    MigrationDirRead { dir: String, cause: String },
+    // NOTE: An applied migration file was edited (or removed) after the fact.
+    // We refuse to run, since the db schema no longer matches the files.
+    MigrationChecksumMismatch { name: String },
+    MigrationFileMissing { name: String },
+    MigrationFail { name: String, cause: String },
+
+    // -- Txn (see Dbx)
+    TxnCantBeginNotInTxnMode,
+    TxnCantCommitNoOpenTxn,
+    TxnCantRollbackNoOpenTxn,
+    // NOTE: A nested scope returned an error (see Dbx), so the txn got rolled back
+    TxnRollbackOnly,
+
+    // -- Externals
+    Sqlx(String),
+}
+
+// region: -- Froms
+impl From<sqlx::Error> for Error {
+    fn from(val: sqlx::Error) -> Self {
+        Self::Sqlx(val.to_string())
+    }
 }
+// endregion: -- Froms
 
 // region: -- Error Boilerplate
 // NOTE: This ultimately helps our BACKEND services Request Log Lines
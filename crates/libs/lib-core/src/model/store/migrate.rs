@@ -0,0 +1,239 @@
+//! Versioned SQL migrations
+//!
+//! Design:
+//! - Migrations are the `*.sql` files of a directory (e.g., `sql/migrations`),
+//!   applied in file name order (e.g., `0001-initial-schema.sql`, `0002-...`).
+//! - Applied migrations are tracked in the `_migrations` table, with the
+//!   checksum of the file content at the time it was applied.
+//! - If an applied file was changed (or removed), we refuse to run. A new
+//!   migration file must be added instead.
+//! - The whole run is one transaction, guarded by a pg advisory lock, so
+//!   concurrent services starting at the same time won't apply twice.
+
+use super::{Db, Error, Result};
+use lib_utils::b64::b64u_encode;
+use sha2::{Digest, Sha256};
+use sqlx::Executor;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+// NOTE: Arbitrary (app wide) key for pg_advisory_xact_lock
+const MIGRATIONS_LOCK_KEY: i64 = 20_240_011;
+
+const SQL_CREATE_MIGRATIONS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS "_migrations" (
+  name varchar(256) PRIMARY KEY,
+  checksum varchar(64) NOT NULL,
+  applied_at timestamptz NOT NULL DEFAULT now()
+)"#;
+
+// region: -- Migration File
+#[derive(Debug)]
+pub struct MigrationFile {
+    pub name: String,
+    pub sql: String,
+    pub checksum: String,
+}
+
+/// Read the `*.sql` files of the migrations dir, sorted by name.
+pub fn read_migration_files(dir: &Path) -> Result<Vec<MigrationFile>> {
+    let dir_read_err = |ex: std::io::Error| Error::MigrationDirRead {
+        dir: dir.to_string_lossy().to_string(),
+        cause: ex.to_string(),
+    };
+
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .map_err(dir_read_err)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sql"))
+        .collect();
+    paths.sort();
+
+    let mut files = Vec::with_capacity(paths.len());
+    for path in paths {
+        let sql = fs::read_to_string(&path).map_err(dir_read_err)?;
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let checksum = b64u_encode(Sha256::digest(sql.as_bytes()));
+
+        files.push(MigrationFile {
+            name,
+            sql,
+            checksum,
+        });
+    }
+
+    Ok(files)
+}
+// endregion: -- Migration File
+
+// region: -- Run
+/// Apply the pending migrations of `dir`.
+/// Returns the names of the newly applied migrations.
+pub async fn run_migrations(db: &Db, dir: &Path) -> Result<Vec<String>> {
+    let files = read_migration_files(dir)?;
+
+    let mut txn = db.begin().await?;
+
+    // -- Lock & Setup
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(MIGRATIONS_LOCK_KEY)
+        .execute(&mut *txn)
+        .await?;
+    sqlx::query(SQL_CREATE_MIGRATIONS_TABLE)
+        .execute(&mut *txn)
+        .await?;
+
+    // -- Validate the applied migrations
+    let applied: Vec<(String, String)> =
+        sqlx::query_as(r#"SELECT name, checksum FROM "_migrations" ORDER BY name"#)
+            .fetch_all(&mut *txn)
+            .await?;
+    for (name, checksum) in applied.iter() {
+        match files.iter().find(|f| &f.name == name) {
+            None => return Err(Error::MigrationFileMissing { name: name.clone() }),
+            Some(file) if &file.checksum != checksum => {
+                return Err(Error::MigrationChecksumMismatch { name: name.clone() })
+            }
+            Some(_) => (),
+        }
+    }
+
+    // -- Apply the pending migrations
+    let mut newly_applied = Vec::new();
+    for file in files
+        .iter()
+        .filter(|f| !applied.iter().any(|(name, _)| name == &f.name))
+    {
+        info!("{:<12} - run_migrations - {}", "MIGRATE", file.name);
+
+        // NOTE: Executing the raw &str (no bind arguments) goes through the
+        // simple query protocol, so a file can hold multiple statements.
+        (&mut *txn)
+            .execute(file.sql.as_str())
+            .await
+            .map_err(|ex| Error::MigrationFail {
+                name: file.name.clone(),
+                cause: ex.to_string(),
+            })?;
+        sqlx::query(r#"INSERT INTO "_migrations" (name, checksum) VALUES ($1, $2)"#)
+            .bind(&file.name)
+            .bind(&file.checksum)
+            .execute(&mut *txn)
+            .await?;
+
+        newly_applied.push(file.name.clone());
+    }
+
+    txn.commit().await?;
+
+    Ok(newly_applied)
+}
+
+// endregion: -- Run
+
+// region: -- Tests
+#[cfg(test)]
+mod tests {
+    #![allow(unused)]
+    pub type Result<T> = core::result::Result<T, Error>;
+    pub type Error = Box<dyn std::error::Error>; // For early dev & tests.
+
+    use super::*;
+    use crate::_dev_utils;
+    use serial_test::serial;
+    use std::path::PathBuf;
+    use uuid::Uuid;
+
+    const FX_MIGRATION_NAME: &str = "9999-test-migrate.sql";
+
+    /// Copy the real migrations into a tmp dir (as they're already applied
+    /// by init_test) so we can add/edit a test migration.
+    fn fx_migrations_dir() -> Result<PathBuf> {
+        let dir = std::env::temp_dir().join(format!("test_migrate_{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir)?;
+        let migrations_dir = _dev_utils::base_dir().join("sql/migrations");
+        for file in read_migration_files(&migrations_dir)? {
+            fs::write(dir.join(&file.name), &file.sql)?;
+        }
+
+        Ok(dir)
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_run_migrations_ok_and_err_checksum() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let db = mm.dbx().db();
+        let dir = fx_migrations_dir()?;
+        fs::write(
+            dir.join(FX_MIGRATION_NAME),
+            "CREATE TABLE _test_migrate (id bigint); INSERT INTO _test_migrate VALUES (1);",
+        )?;
+
+        // -- Exec & Check: only the new migration is applied
+        let applied = run_migrations(db, &dir).await?;
+        assert_eq!(applied, vec![FX_MIGRATION_NAME.to_string()]);
+        let (count,): (i64,) = sqlx::query_as("SELECT count(*) FROM _test_migrate")
+            .fetch_one(db)
+            .await?;
+        assert_eq!(count, 1);
+
+        // -- Exec & Check: nothing pending
+        let applied = run_migrations(db, &dir).await?;
+        assert!(applied.is_empty());
+
+        // -- Exec & Check: applied file changed
+        fs::write(
+            dir.join(FX_MIGRATION_NAME),
+            "CREATE TABLE _test_migrate (id bigint);",
+        )?;
+        let res = run_migrations(db, &dir).await;
+        assert!(
+            matches!(&res, Err(super::Error::MigrationChecksumMismatch { name }) if name == FX_MIGRATION_NAME),
+            "should be MigrationChecksumMismatch, but was {res:?}"
+        );
+
+        // -- Clean
+        sqlx::query(r#"DELETE FROM "_migrations" WHERE name = $1"#)
+            .bind(FX_MIGRATION_NAME)
+            .execute(db)
+            .await?;
+        sqlx::query("DROP TABLE _test_migrate").execute(db).await?;
+        fs::remove_dir_all(dir)?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_run_migrations_err_file_missing() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let dir = fx_migrations_dir()?;
+        let first = read_migration_files(&dir)?
+            .into_iter()
+            .next()
+            .ok_or("should have at least one migration")?;
+        fs::remove_file(dir.join(&first.name))?;
+
+        // -- Exec
+        let res = run_migrations(mm.dbx().db(), &dir).await;
+
+        // -- Check
+        assert!(
+            matches!(&res, Err(super::Error::MigrationFileMissing { name }) if name == &first.name),
+            "should be MigrationFileMissing, but was {res:?}"
+        );
+
+        // -- Clean
+        fs::remove_dir_all(dir)?;
+
+        Ok(())
+    }
+}
+// endregion: -- Tests
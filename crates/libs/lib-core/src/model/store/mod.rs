@@ -1,5 +1,9 @@
 // region: -- Modules
+mod dbx;
 mod error;
+pub mod migrate;
+
+pub use self::dbx::Dbx;
 
 pub use self::error::{Error, Result};
 
@@ -22,6 +26,15 @@ pub async fn new_db_pool() -> Result<Db> {
         .map_err(|ex| Error::FailToCreatePool(ex.to_string()))
 }
 
+/// A db pool that only connects on its first query (e.g., for the tests
+/// that never touch the db).
+pub fn new_db_pool_lazy() -> Result<Db> {
+    PgPoolOptions::new()
+        .max_connections(1)
+        .connect_lazy(&core_config().DB_URL)
+        .map_err(|ex| Error::FailToCreatePool(ex.to_string()))
+}
+
 // FIXME: 1) This is not an ideal situation; however, with sqlx 0.7.1, when executing `cargo test`, some tests that use sqlx fail at a
 //         rather low level (in the tokio scheduler). It appears to be a low-level thread/async issue, as removing/adding
 //         tests causes different tests to fail. The cause remains uncertain, but setting max_connections to 1 resolves the issue.
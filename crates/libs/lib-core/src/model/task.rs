@@ -1,10 +1,15 @@
 use crate::model::base::{self, DbBmc};
-use crate::model::Result;
+use crate::model::modql_utils::time_to_sea_value;
+use crate::model::{ListPage, Result};
 use crate::{ctx::Ctx, model::ModelManager};
+use lib_utils::time::Rfc3339;
 use modql::field::Fields;
-use modql::filter::{FilterNodes, ListOptions, OpValsBool, OpValsInt64, OpValsString};
+use modql::filter::{FilterNodes, ListOptions, OpValsBool, OpValsInt64, OpValsString, OpValsValue};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_with::serde_as;
 use sqlx::FromRow;
+use time::OffsetDateTime;
 
 // region: -- Task Types
 // NOTE: At a high level, structs are views on your db tables.
@@ -13,11 +18,23 @@ use sqlx::FromRow;
 // to change the creator of a task, or read certain properties.
 // Therefore, we break up these structs to assist.
 /// Sent back from model layer
+#[serde_as]
 #[derive(Debug, Clone, Fields, FromRow, Serialize)]
 pub struct Task {
     pub id: i64,
     pub title: String,
     pub done: bool,
+
+    // -- Ownership (filled from the Ctx in base::create)
+    pub owner_id: i64,
+
+    // -- Timestamps (stamped in base::create/update)
+    pub cid: i64,
+    #[serde_as(as = "Rfc3339")]
+    pub ctime: OffsetDateTime,
+    pub mid: i64,
+    #[serde_as(as = "Rfc3339")]
+    pub mtime: OffsetDateTime,
     // -- sqlb example:
     // #[field(skip)] // sqlb::Fields
     // pub something_else: String,
@@ -56,6 +73,16 @@ pub struct TaskFilter {
 
     title: Option<OpValsString>,
     done: Option<OpValsBool>,
+
+    owner_id: Option<OpValsInt64>,
+
+    cid: Option<OpValsInt64>,
+    // NOTE: Time values are given as Rfc3339 strings (e.g., {"$gte": "2024-03-14T08:08:09Z"})
+    #[modql(to_sea_value_fn = "time_to_sea_value")]
+    ctime: Option<OpValsValue>,
+    mid: Option<OpValsInt64>,
+    #[modql(to_sea_value_fn = "time_to_sea_value")]
+    mtime: Option<OpValsValue>,
 }
 // endregion: -- Task Types
 
@@ -64,6 +91,9 @@ pub struct TaskBmc;
 
 impl DbBmc for TaskBmc {
     const TABLE: &'static str = "task";
+    const OWNED: bool = true;
+    const TIMESTAMPED: bool = true;
+    const AUDITED: bool = true;
 }
 
 impl TaskBmc {
@@ -96,6 +126,26 @@ impl TaskBmc {
         base::get::<Self, _>(ctx, mm, id).await
     }
 
+    /// The task when it matches the filters, None otherwise (see base::get_matching)
+    pub async fn get_matching(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        filters: Option<Vec<TaskFilter>>,
+    ) -> Result<Option<Task>> {
+        base::get_matching::<Self, _, _>(ctx, mm, id, filters).await
+    }
+
+    /// True when the task row (as json) matches the filters (see base::row_matches)
+    pub async fn row_matches(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        row: &Value,
+        filters: Option<Vec<TaskFilter>>,
+    ) -> Result<bool> {
+        base::row_matches::<Self, _>(ctx, mm, row, filters).await
+    }
+
     // NOTE: ModQL ListOptions - Offset, OrderBy, Limit
     pub async fn list(
         ctx: &Ctx,
@@ -116,6 +166,17 @@ impl TaskBmc {
         // Ok(tasks)
     }
 
+    /// Paged list ({items, total, next_cursor}), see base::list_paged
+    pub async fn list_paged(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<TaskFilter>>,
+        list_options: Option<ListOptions>,
+        cursor: Option<String>,
+    ) -> Result<ListPage<Task>> {
+        base::list_paged::<Self, _, _>(ctx, mm, filters, list_options, cursor).await
+    }
+
     pub async fn update(
         ctx: &Ctx,
         mm: &ModelManager,
@@ -144,6 +205,32 @@ impl TaskBmc {
         //
         // Ok(())
     }
+
+    // -- Bulk (one statement, see base::create_many/update_many/delete_many)
+    pub async fn create_many(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        task_cs: Vec<TaskForCreate>,
+    ) -> Result<Vec<i64>> {
+        base::create_many::<Self, _>(ctx, mm, task_cs).await
+    }
+
+    pub async fn update_many(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Vec<TaskFilter>,
+        task_u: TaskForUpdate,
+    ) -> Result<Vec<i64>> {
+        base::update_many::<Self, _, _>(ctx, mm, filters, task_u).await
+    }
+
+    pub async fn delete_many(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Vec<TaskFilter>,
+    ) -> Result<Vec<i64>> {
+        base::delete_many::<Self, _>(ctx, mm, filters).await
+    }
 }
 // endregion: -- TaskBmc
 
@@ -156,6 +243,8 @@ mod tests {
 
     use super::*;
     use crate::_dev_utils;
+    use crate::ctx::Role;
+    use crate::model::user::{UserBmc, UserForCreate};
     // use crate::model::error::Error;
 
     use serde_json::json;
@@ -336,6 +425,97 @@ mod tests {
         Ok(())
     }
 
+    #[serial]
+    #[tokio::test]
+    async fn test_list_paged_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_titles = &[
+            "test_list_paged_ok-task b",
+            "test_list_paged_ok-task a",
+            "test_list_paged_ok-task d",
+            "test_list_paged_ok-task b",
+            "test_list_paged_ok-task c",
+        ];
+        let fx_tasks = _dev_utils::seed_tasks(&ctx, &mm, fx_titles).await?;
+        let fx_filters = || {
+            serde_json::from_value::<Option<Vec<TaskFilter>>>(json!([{
+                "title": {"$startsWith": "test_list_paged_ok"}
+            }]))
+        };
+        let fx_list_options: ListOptions = serde_json::from_value(json!({
+            "limit": 2,
+            "order_bys": "!title",
+        }))?;
+
+        // -- Exec: keyset (cursor) pages
+        let mut cursor = None;
+        let mut tasks = Vec::new();
+        for _ in 0..5 {
+            let page = TaskBmc::list_paged(
+                &ctx,
+                &mm,
+                fx_filters()?,
+                Some(fx_list_options.clone()),
+                cursor,
+            )
+            .await?;
+            assert_eq!(page.total, 5);
+            tasks.extend(page.items);
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        // -- Check: title desc, then id asc (tie breaker for the two "b")
+        let titles: Vec<&str> = tasks
+            .iter()
+            .map(|t| t.title.trim_start_matches("test_list_paged_ok-task "))
+            .collect();
+        assert_eq!(titles, ["d", "c", "b", "b", "a"]);
+        assert!(tasks[2].id < tasks[3].id);
+
+        // -- Exec & Check: offset (last page)
+        let list_options: ListOptions = serde_json::from_value(json!({
+            "limit": 2,
+            "offset": 4,
+            "order_bys": "!title",
+        }))?;
+        let page = TaskBmc::list_paged(&ctx, &mm, fx_filters()?, Some(list_options), None).await?;
+        assert_eq!(page.total, 5);
+        assert_eq!(page.items.len(), 1);
+        assert!(page.items[0].title.ends_with(" a"));
+        assert!(page.next_cursor.is_none());
+
+        // -- Exec & Check: invalid cursor & order_by
+        let res = TaskBmc::list_paged(
+            &ctx,
+            &mm,
+            fx_filters()?,
+            None,
+            Some("not-a-cursor".to_string()),
+        )
+        .await;
+        assert!(matches!(res, Err(crate::model::Error::ListCursorInvalid)));
+        let list_options: ListOptions = serde_json::from_value(json!({
+            "order_bys": "title; DROP TABLE task",
+        }))?;
+        let res = TaskBmc::list_paged(&ctx, &mm, fx_filters()?, Some(list_options), None).await;
+        assert!(matches!(
+            res,
+            Err(crate::model::Error::ListOrderByInvalid { .. })
+        ));
+
+        // -- Clean
+        for task in fx_tasks.iter() {
+            TaskBmc::delete(&ctx, &mm, task.id).await?;
+        }
+
+        Ok(())
+    }
+
     #[serial]
     #[tokio::test]
     async fn test_update_ok() -> Result<()> {
@@ -395,5 +575,292 @@ mod tests {
 
         Ok(())
     }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_txn_commit_and_rollback_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_title_commit = "test_txn_commit_and_rollback_ok - commit";
+        let fx_title_rollback = "test_txn_commit_and_rollback_ok - rollback";
+
+        // -- Exec: commit
+        let mm_txn = mm.new_with_txn()?;
+        mm_txn.begin_txn().await?;
+        let id_commit = TaskBmc::create(
+            &ctx,
+            &mm_txn,
+            TaskForCreate {
+                title: fx_title_commit.to_string(),
+            },
+        )
+        .await?;
+        mm_txn.commit_txn().await?;
+
+        // -- Exec: rollback (the get inside the txn sees the row)
+        let mm_txn = mm.new_with_txn()?;
+        mm_txn.begin_txn().await?;
+        let id_rollback = TaskBmc::create(
+            &ctx,
+            &mm_txn,
+            TaskForCreate {
+                title: fx_title_rollback.to_string(),
+            },
+        )
+        .await?;
+        let task = TaskBmc::get(&ctx, &mm_txn, id_rollback).await?;
+        assert_eq!(task.title, fx_title_rollback);
+        mm_txn.rollback_txn().await?;
+
+        // -- Check
+        let task = TaskBmc::get(&ctx, &mm, id_commit).await?;
+        assert_eq!(task.title, fx_title_commit);
+        let res = TaskBmc::get(&ctx, &mm, id_rollback).await;
+        assert!(
+            matches!(res, Err(crate::model::Error::EntityNotFound { .. })),
+            "rolled back task should not be found"
+        );
+
+        // -- Check: no begin outside of txn mode
+        assert!(mm.begin_txn().await.is_err());
+
+        // -- Clean
+        TaskBmc::delete(&ctx, &mm, id_commit).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_txn_nested_err_outer_commit_err() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_title = "test_txn_nested_err_outer_commit_err - task 01";
+
+        // -- Exec
+        let mm_txn = mm.new_with_txn()?;
+        mm_txn.begin_txn().await?;
+        let id = TaskBmc::create(
+            &ctx,
+            &mm_txn,
+            TaskForCreate {
+                title: fx_title.to_string(),
+            },
+        )
+        .await?;
+        // NOTE: UserBmc::create runs in its own (nested) txn, and fails
+        // after its begin_txn (invalid username).
+        let res = UserBmc::create(
+            &ctx,
+            &mm_txn,
+            UserForCreate {
+                username: "a b".to_string(),
+                pwd_clear: "welcome".to_string(),
+            },
+        )
+        .await;
+        assert!(res.is_err(), "nested create should fail");
+        let res = mm_txn.commit_txn().await;
+
+        // -- Check
+        assert!(
+            matches!(
+                res,
+                Err(crate::model::Error::Store(
+                    crate::model::store::Error::TxnRollbackOnly
+                ))
+            ),
+            "outer commit should fail, but was {res:?}"
+        );
+        let res = TaskBmc::get(&ctx, &mm, id).await;
+        assert!(
+            matches!(res, Err(crate::model::Error::EntityNotFound { .. })),
+            "rolled back task should not be found"
+        );
+
+        // -- Check: the next txn of the same ModelManager commits
+        mm_txn.begin_txn().await?;
+        let id = TaskBmc::create(
+            &ctx,
+            &mm_txn,
+            TaskForCreate {
+                title: fx_title.to_string(),
+            },
+        )
+        .await?;
+        mm_txn.commit_txn().await?;
+        TaskBmc::get(&ctx, &mm, id).await?;
+
+        // -- Clean
+        TaskBmc::delete(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_owner_scoping_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let root_ctx = Ctx::root_ctx();
+        let ctx_owner = Ctx::new(1000)?;
+        let ctx_other = Ctx::new(1001)?;
+        let fx_title = "test_owner_scoping_ok - task 01";
+
+        // -- Exec
+        let task = _dev_utils::seed_tasks(&ctx_owner, &mm, &[fx_title])
+            .await?
+            .remove(0);
+
+        // -- Check: owner/cid/mid from Ctx
+        assert_eq!(task.owner_id, 1000);
+        assert_eq!(task.cid, 1000);
+        assert_eq!(task.mid, 1000);
+
+        // -- Check: other user cannot see or change the task
+        let res = TaskBmc::get(&ctx_other, &mm, task.id).await;
+        assert!(matches!(
+            res,
+            Err(crate::model::Error::EntityNotFound { entity: "task", .. })
+        ));
+        let tasks = TaskBmc::list(&ctx_other, &mm, None, None).await?;
+        assert!(tasks.iter().all(|t| t.id != task.id));
+        let res = TaskBmc::update(&ctx_other, &mm, task.id, TaskForUpdate::default()).await;
+        assert!(res.is_err(), "other user should not update the task");
+        let res = TaskBmc::delete(&ctx_other, &mm, task.id).await;
+        assert!(res.is_err(), "other user should not delete the task");
+
+        // -- Check: admin ctx is scoped too, unless explicitly for all owners
+        let ctx_admin = Ctx::new_with_role(1002, Role::Admin)?;
+        let res = TaskBmc::get(&ctx_admin, &mm, task.id).await;
+        assert!(res.is_err(), "admin should not see the task by default");
+        TaskBmc::get(&ctx_admin.for_all_owners()?, &mm, task.id).await?;
+        assert!(ctx_other.for_all_owners().is_err());
+
+        // -- Check: root ctx sees all, and mid is set on update
+        TaskBmc::update(
+            &root_ctx,
+            &mm,
+            task.id,
+            TaskForUpdate {
+                done: Some(true),
+                ..Default::default()
+            },
+        )
+        .await?;
+        let task = TaskBmc::get(&ctx_owner, &mm, task.id).await?;
+        assert!(task.done);
+        assert_eq!(task.owner_id, 1000);
+        assert_eq!(task.mid, 0);
+        assert!(task.mtime > task.ctime);
+
+        // -- Clean
+        TaskBmc::delete(&ctx_owner, &mm, task.id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_list_by_timestamps_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_titles = &[
+            "test_list_by_timestamps_ok-task 01",
+            "test_list_by_timestamps_ok-task 02",
+        ];
+        let fx_tasks = _dev_utils::seed_tasks(&ctx, &mm, fx_titles).await?;
+        let fx_ctime = lib_utils::time::format_time(fx_tasks[1].ctime);
+
+        // -- Exec
+        let filters: Vec<TaskFilter> = serde_json::from_value(json!([{
+            "title": {"$startsWith": "test_list_by_timestamps_ok"},
+            "ctime": {"$gte": fx_ctime},
+        }]))?;
+        let list_options: ListOptions = serde_json::from_value(json!({
+            "order_bys": "!mtime",
+        }))?;
+        let tasks = TaskBmc::list(&ctx, &mm, Some(filters), Some(list_options)).await?;
+
+        // -- Check
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].title, fx_titles[1]);
+
+        // -- Exec & Check: sort by mtime
+        let filters: Vec<TaskFilter> = serde_json::from_value(json!([{
+            "title": {"$startsWith": "test_list_by_timestamps_ok"},
+        }]))?;
+        let list_options: ListOptions = serde_json::from_value(json!({
+            "order_bys": "!mtime",
+        }))?;
+        let tasks = TaskBmc::list(&ctx, &mm, Some(filters), Some(list_options)).await?;
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].title, fx_titles[1]);
+        assert_eq!(tasks[1].title, fx_titles[0]);
+
+        // -- Clean
+        for task in fx_tasks.iter() {
+            TaskBmc::delete(&ctx, &mm, task.id).await?;
+        }
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_create_update_delete_many_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_prefix = "test_create_update_delete_many_ok";
+        let fx_task_cs: Vec<TaskForCreate> = (1..=3)
+            .map(|i| TaskForCreate {
+                title: format!("{fx_prefix}-task {i}"),
+            })
+            .collect();
+        let fx_filters = || -> serde_json::Result<Vec<TaskFilter>> {
+            serde_json::from_value(json!([{ "title": {"$startsWith": fx_prefix} }]))
+        };
+
+        // -- Exec & Check: create_many (ids in the data order)
+        let ids = TaskBmc::create_many(&ctx, &mm, fx_task_cs).await?;
+        assert_eq!(ids.len(), 3);
+        let task = TaskBmc::get(&ctx, &mm, ids[2]).await?;
+        assert_eq!(task.title, format!("{fx_prefix}-task 3"));
+        assert!(!task.done);
+
+        // -- Exec & Check: update_many
+        let task_u = TaskForUpdate {
+            done: Some(true),
+            ..Default::default()
+        };
+        let updated_ids = TaskBmc::update_many(&ctx, &mm, fx_filters()?, task_u).await?;
+        assert_eq!(updated_ids.len(), 3);
+        for id in ids.iter() {
+            assert!(TaskBmc::get(&ctx, &mm, *id).await?.done);
+        }
+
+        // -- Exec & Check: empty filters are refused
+        let res = TaskBmc::delete_many(&ctx, &mm, Vec::new()).await;
+        assert!(
+            matches!(
+                &res,
+                Err(crate::model::Error::FiltersEmpty { entity: "task" })
+            ),
+            "should be FiltersEmpty, but was {res:?}"
+        );
+
+        // -- Exec & Check: delete_many
+        let mut deleted_ids = TaskBmc::delete_many(&ctx, &mm, fx_filters()?).await?;
+        deleted_ids.sort();
+        assert_eq!(deleted_ids, ids);
+        let tasks = TaskBmc::list(&ctx, &mm, Some(fx_filters()?), None).await?;
+        assert!(tasks.is_empty());
+
+        Ok(())
+    }
 }
 // endregion: -- Tests
@@ -2,14 +2,22 @@
 // REF: https://docs.birdeye.so/docs/token-list
 // REF: https://docs.birdeye.so/reference/get_defi-tokenlist
 
-use crate::model::base::{self, DbBmc};
-use crate::model::Result;
+use crate::model::alert::AlertRuleBmc;
+use crate::model::base::{self, CommonIden, DbBmc, LIST_LIMIT_MAX};
+use crate::model::token_snapshot::TokenSnapshotBmc;
+use crate::model::{Error, ListPage, Result};
 use crate::{ctx::Ctx, model::ModelManager};
-use modql::field::Fields;
-use modql::filter::{FilterNodes, ListOptions, OpValsBool, OpValsInt64, OpValsString};
+use lib_utils::time::Rfc3339;
+use modql::field::{Fields, HasFields};
+use modql::filter::{FilterNodes, ListOptions, OpValsFloat64, OpValsInt64, OpValsString};
+use sea_query::{Alias, Expr, Func, Iden, Order, PostgresQueryBuilder, Query};
+use sea_query_binder::SqlxBinder;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use serde_with::{serde_as, DefaultOnNull};
-use sqlx::FromRow;
+use sqlx::postgres::PgRow;
+use sqlx::{FromRow, Row};
+use time::OffsetDateTime;
 
 // region: -- Token Types
 // NOTE: At a high level, structs are views on your db tables.
@@ -65,6 +73,39 @@ pub struct BirdeyeDataResponse {
     pub total: i64,
 }
 
+impl BirdeyeDataResponse {
+    /// The TokenForCreate of each token of the (tokenlist) response
+    // U: BirdeyeTokenResponse doesn't have the update_unix_time & update_time, they
+    // come from the BirdeyeDataResponse. Also, need to unwrap the v24h_change_percent
+    // Option<f64>, since the column is NOT NULL.
+    pub fn into_token_cs(self) -> Vec<TokenForCreate> {
+        let BirdeyeDataResponse {
+            update_unix_time,
+            update_time,
+            tokens,
+            ..
+        } = self;
+
+        tokens
+            .into_iter()
+            .map(|token| TokenForCreate {
+                update_unix_time,
+                update_time: update_time.clone(),
+                address: token.address,
+                decimals: token.decimals,
+                symbol: token.symbol,
+                name: token.name,
+                mc: token.mc,
+                v24h_change_percent: token.v24h_change_percent.unwrap_or_default(),
+                v24h_usd: token.v24h_usd,
+                liquidity: token.liquidity,
+                logo_uri: token.logo_uri,
+                last_trade_unix_time: token.last_trade_unix_time,
+            })
+            .collect()
+    }
+}
+
 // Q: Do I need a perfect matching struct to use serde_json::from_str()
 // to convert to a Rust object? In _dev_utils/mod.rs I want to seed_tokens() from JSON file.
 // REF: https://stackoverflow.com/questions/48595735/invalid-type-map-expected-a-sequence-when-deserializing-a-nested-json-struct
@@ -94,6 +135,7 @@ pub struct BirdeyeTokenResponse {
 }
 
 /// Sent back from model layer
+#[serde_as]
 #[derive(Debug, Clone, Fields, FromRow, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Token {
@@ -113,6 +155,17 @@ pub struct Token {
     #[serde(rename = "v24hUSD")]
     pub v24h_usd: f64,
     pub last_trade_unix_time: i64,
+
+    // -- Ownership (filled from the Ctx in base::create)
+    pub owner_id: i64,
+
+    // -- Timestamps (stamped in base::create/update)
+    pub cid: i64,
+    #[serde_as(as = "Rfc3339")]
+    pub ctime: OffsetDateTime,
+    pub mid: i64,
+    #[serde_as(as = "Rfc3339")]
+    pub mtime: OffsetDateTime,
 }
 
 /// Sent to model layer to update data structure
@@ -160,25 +213,135 @@ pub struct TokenForUpdate {
 // NOTE: modql traits in detail:
 // - FilterNodes: ModQL trait to turn type into list of nodes for Sea Query
 // - Deserialize: Allows type to have the '$' notation e.g., MongoDB
+// U: The DOUBLE PRECISION columns are OpValsFloat64 (OpValsInt64 rejected
+// the float values, e.g., {"v24h_change_percent": {"$gt": 1.5}}).
+// U: symbol & name are citext columns (0005-token-citext.sql), so their
+// string ops are case-insensitive. The address is case-sensitive.
+// NOTE: The values are cast as citext too, otherwise pg compares the bound
+// text values as text (case-sensitive $eq, $in, $lt, ...).
 #[derive(FilterNodes, Deserialize, Default, Debug)]
 pub struct TokenFilter {
     // NOTE: TIP! Jeremy prefers to place the keys up top
     // with other props below with a line between.
     id: Option<OpValsInt64>,
 
+    #[modql(cast_as = "citext")]
     symbol: Option<OpValsString>,
+    #[modql(cast_as = "citext")]
+    name: Option<OpValsString>,
     address: Option<OpValsString>,
-    v24h_change_percent: Option<OpValsInt64>,
-    v24h_usd: Option<OpValsInt64>,
+    decimals: Option<OpValsInt64>,
+    update_unix_time: Option<OpValsInt64>,
+    last_trade_unix_time: Option<OpValsInt64>,
+
+    liquidity: Option<OpValsFloat64>,
+    mc: Option<OpValsFloat64>,
+    v24h_change_percent: Option<OpValsFloat64>,
+    v24h_usd: Option<OpValsFloat64>,
+}
+
+/// A token, by id or by address (e.g., `{"token_id": 1000}` or `{"address": "So11..."}`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenRef {
+    TokenId(i64),
+    Address(String),
 }
 
 // endregion: -- Token Types
 
+// region: -- Token Analytics Types
+/// The top movers side: the biggest v24h_change_percent gains, or losses
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MoverDirection {
+    #[default]
+    Gainers,
+    Losers,
+}
+
+/// A token with its market cap rank (1 is the biggest mc)
+#[derive(Debug, FromRow, Serialize)]
+pub struct TokenRank {
+    pub rank: i64,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub token: Token,
+}
+
+/// The aggregates of the tokens (of the filters), None when there are no tokens
+#[derive(Debug, Serialize)]
+pub struct TokenStats {
+    pub count: i64,
+    pub mc: MetricStats,
+    pub liquidity: MetricStats,
+    pub v24h_usd: MetricStats,
+}
+
+/// The aggregates of one metric (p50/p90/p99 are the continuous percentiles)
+#[derive(Debug, Serialize)]
+pub struct MetricStats {
+    pub sum: Option<f64>,
+    pub avg: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub p50: Option<f64>,
+    pub p90: Option<f64>,
+    pub p99: Option<f64>,
+}
+
+// NOTE: The stats query aliases are '{metric}_{stat}' (e.g., 'mc_p90'),
+// so the nested TokenStats is read from the flat row by hand.
+impl FromRow<'_, PgRow> for TokenStats {
+    fn from_row(row: &PgRow) -> sqlx::Result<Self> {
+        let metric = |metric: &str| -> sqlx::Result<MetricStats> {
+            let stat =
+                |stat: &str| row.try_get::<Option<f64>, _>(format!("{metric}_{stat}").as_str());
+            Ok(MetricStats {
+                sum: stat("sum")?,
+                avg: stat("avg")?,
+                min: stat("min")?,
+                max: stat("max")?,
+                p50: stat("p50")?,
+                p90: stat("p90")?,
+                p99: stat("p99")?,
+            })
+        };
+
+        Ok(TokenStats {
+            count: row.try_get("count")?,
+            mc: metric("mc")?,
+            liquidity: metric("liquidity")?,
+            v24h_usd: metric("v24h_usd")?,
+        })
+    }
+}
+
+#[derive(Iden)]
+enum TokenIden {
+    Address,
+    Liquidity,
+    Mc,
+    V24hChangePercent,
+}
+
+const TOP_MOVERS_LIMIT_DEFAULT: i64 = 10;
+const STATS_METRICS: [&str; 3] = ["mc", "liquidity", "v24h_usd"];
+const STATS_PERCENTILES: [(&str, f64); 3] = [("p50", 0.5), ("p90", 0.9), ("p99", 0.99)];
+// endregion: -- Token Analytics Types
+
 // region: -- TokenBmc
 pub struct TokenBmc;
 
 impl DbBmc for TokenBmc {
     const TABLE: &'static str = "token";
+    const OWNED: bool = true;
+    // NOTE: Tokens are shared market data (e.g., ingested by the root Ctx),
+    // so all the users can read them, but only their owner can write them.
+    const SHARED_READ: bool = true;
+    const TIMESTAMPED: bool = true;
+    const UNIQUE_COLUMN: Option<&'static str> = Some("address");
+    const AUDITED: bool = true;
 }
 
 impl TokenBmc {
@@ -190,7 +353,12 @@ impl TokenBmc {
     pub async fn create(ctx: &Ctx, mm: &ModelManager, token_c: TokenForCreate) -> Result<i64> {
         // NOTE: Annotations can be inferred, but the compiler will see that
         // it's equivalent to: create::<TaskBmc, model::task::TaskForCreate>(ctx, mm, task_c)
-        base::create::<Self, _>(ctx, mm, token_c).await
+        // U: Each write also records the token metrics snapshot (same txn).
+        // U: And evaluates the alert rules of the token (see with_after_write).
+        with_after_write(ctx, mm, async |mm| {
+            base::create::<Self, _>(ctx, mm, token_c).await
+        })
+        .await
 
         // -- BEFORE base layer:
         // let db = mm.db();
@@ -211,6 +379,26 @@ impl TokenBmc {
         base::get::<Self, _>(ctx, mm, id).await
     }
 
+    /// The token when it matches the filters, None otherwise (see base::get_matching)
+    pub async fn get_matching(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        filters: Option<Vec<TokenFilter>>,
+    ) -> Result<Option<Token>> {
+        base::get_matching::<Self, _, _>(ctx, mm, id, filters).await
+    }
+
+    /// True when the token row (as json) matches the filters (see base::row_matches)
+    pub async fn row_matches(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        row: &Value,
+        filters: Option<Vec<TokenFilter>>,
+    ) -> Result<bool> {
+        base::row_matches::<Self, _>(ctx, mm, row, filters).await
+    }
+
     // NOTE: ModQL ListOptions - Offset, OrderBy, Limit
     pub async fn list(
         ctx: &Ctx,
@@ -231,13 +419,30 @@ impl TokenBmc {
         // Ok(tasks)
     }
 
+    /// Paged list ({items, total, next_cursor}), see base::list_paged
+    pub async fn list_paged(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<TokenFilter>>,
+        list_options: Option<ListOptions>,
+        cursor: Option<String>,
+    ) -> Result<ListPage<Token>> {
+        base::list_paged::<Self, _, _>(ctx, mm, filters, list_options, cursor).await
+    }
+
     pub async fn update(
         ctx: &Ctx,
         mm: &ModelManager,
         id: i64,
         token_u: TokenForUpdate,
     ) -> Result<()> {
-        base::update::<Self, _>(ctx, mm, id, token_u).await
+        with_after_write(ctx, mm, async |mm| {
+            base::update::<Self, _>(ctx, mm, id, token_u).await?;
+            Ok(id)
+        })
+        .await?;
+
+        Ok(())
     }
 
     pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
@@ -259,8 +464,275 @@ impl TokenBmc {
         //
         // Ok(())
     }
+
+    /// Create the token, or update the existing token (same address)
+    /// with the TokenForUpdate fields. Returns the token id.
+    pub async fn upsert(ctx: &Ctx, mm: &ModelManager, token_c: TokenForCreate) -> Result<i64> {
+        with_after_write(ctx, mm, async |mm| {
+            base::upsert::<Self, _, TokenForUpdate>(ctx, mm, token_c).await
+        })
+        .await
+    }
+
+    /// Multi-rows upsert (e.g., a Birdeye snapshot import). Returns the ids in the data order.
+    pub async fn upsert_many(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        token_cs: Vec<TokenForCreate>,
+    ) -> Result<Vec<i64>> {
+        with_after_write(ctx, mm, async |mm| {
+            base::upsert_many::<Self, _, TokenForUpdate>(ctx, mm, token_cs).await
+        })
+        .await
+    }
+
+    // -- Bulk (one statement, see base::create_many/update_many/delete_many)
+    pub async fn create_many(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        token_cs: Vec<TokenForCreate>,
+    ) -> Result<Vec<i64>> {
+        with_after_write(ctx, mm, async |mm| {
+            base::create_many::<Self, _>(ctx, mm, token_cs).await
+        })
+        .await
+    }
+
+    pub async fn update_many(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Vec<TokenFilter>,
+        token_u: TokenForUpdate,
+    ) -> Result<Vec<i64>> {
+        with_after_write(ctx, mm, async |mm| {
+            base::update_many::<Self, _, _>(ctx, mm, filters, token_u).await
+        })
+        .await
+    }
+
+    pub async fn delete_many(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Vec<TokenFilter>,
+    ) -> Result<Vec<i64>> {
+        base::delete_many::<Self, _>(ctx, mm, filters).await
+    }
+
+    /// The id of the token (by id or address), when readable by the Ctx user.
+    // NOTE: Same read scope as get/list (see base::read_owner_cond), so the
+    // watchlists and alert rules can reference any token (SHARED_READ).
+    pub async fn resolve_id(ctx: &Ctx, mm: &ModelManager, token_ref: TokenRef) -> Result<i64> {
+        let dbx = mm.dbx();
+
+        // -- Build query
+        let mut query = Query::select();
+        query
+            .from(Self::table_ref())
+            .column(CommonIden::Id)
+            .and_where_option(base::read_owner_cond::<Self>(ctx));
+        match &token_ref {
+            TokenRef::TokenId(id) => query.and_where(Expr::col(CommonIden::Id).eq(*id)),
+            TokenRef::Address(address) => {
+                query.and_where(Expr::col(TokenIden::Address).eq(address.as_str()))
+            }
+        };
+
+        // -- Exec query
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        let id = dbx
+            .fetch_optional(sqlx::query_as_with::<_, (i64,), _>(&sql, values))
+            .await?
+            .map(|(id,)| id);
+
+        id.ok_or_else(|| match token_ref {
+            TokenRef::TokenId(id) => Error::EntityNotFound {
+                entity: "token",
+                id,
+            },
+            TokenRef::Address(address) => Error::TokenAddressNotFound { address },
+        })
+    }
 }
-// endregion: -- TaskBmc
+
+/// Run the token write in a txn, followed (in the same txn) by the metrics
+/// snapshot and the alert rules evaluation (AlertRuleBmc::evaluate) of the
+/// written ids. Returns the write result (the id or ids).
+async fn with_after_write<T, F>(ctx: &Ctx, mm: &ModelManager, write: F) -> Result<T>
+where
+    T: WrittenIds,
+    F: AsyncFnOnce(&ModelManager) -> Result<T>,
+{
+    let mm = &mm.new_with_txn()?;
+    mm.begin_txn().await?;
+
+    let written = write(mm).await?;
+    let ids = written.ids();
+    TokenSnapshotBmc::record(ctx, mm, ids).await?;
+    AlertRuleBmc::evaluate(ctx, mm, ids).await?;
+
+    mm.commit_txn().await?;
+
+    Ok(written)
+}
+
+/// The ids of a write result (one id, or the bulk ids)
+trait WrittenIds {
+    fn ids(&self) -> &[i64];
+}
+
+impl WrittenIds for i64 {
+    fn ids(&self) -> &[i64] {
+        std::slice::from_ref(self)
+    }
+}
+
+impl WrittenIds for Vec<i64> {
+    fn ids(&self) -> &[i64] {
+        self
+    }
+}
+// endregion: -- TokenBmc
+
+// region: -- TokenBmc Analytics
+// NOTE: The analytics are over all the tokens (whoever the owner is), since
+// tokens are SHARED_READ (base::list_cond adds no owner scope), and the
+// filters narrow them down (e.g., an address prefix).
+impl TokenBmc {
+    /// The tokens (of the filters) with the biggest v24h_change_percent gains
+    /// (or losses), with at least `min_liquidity` (to leave out the illiquid tokens).
+    pub async fn top_movers(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<TokenFilter>>,
+        direction: MoverDirection,
+        min_liquidity: Option<f64>,
+        limit: Option<i64>,
+    ) -> Result<Vec<Token>> {
+        let limit = limit.unwrap_or(TOP_MOVERS_LIMIT_DEFAULT);
+        if limit > LIST_LIMIT_MAX {
+            return Err(Error::ListLimitOverMax {
+                max: LIST_LIMIT_MAX,
+                actual: limit,
+            });
+        }
+        let order = match direction {
+            MoverDirection::Gainers => Order::Desc,
+            MoverDirection::Losers => Order::Asc,
+        };
+
+        let dbx = mm.dbx();
+
+        // -- Build query
+        let mut query = Query::select();
+        query
+            .from(Self::table_ref())
+            .columns(Token::field_idens())
+            .cond_where(base::list_cond::<Self, _>(ctx, filters)?)
+            .and_where_option(
+                min_liquidity
+                    .map(|min_liquidity| Expr::col(TokenIden::Liquidity).gte(min_liquidity)),
+            )
+            .order_by(TokenIden::V24hChangePercent, order)
+            .order_by(CommonIden::Id, Order::Asc)
+            .limit(limit as u64);
+
+        // -- Exec query
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        let tokens = dbx
+            .fetch_all(sqlx::query_as_with::<_, Token, _>(&sql, values))
+            .await?;
+
+        Ok(tokens)
+    }
+
+    /// The tokens (of the filters) ranked by market cap, biggest first.
+    // NOTE: The rank is over the filtered tokens (the window runs after the WHERE),
+    // and before the limit/offset, so the pages keep the rank. Always in the rank
+    // order (hence the limit/offset only, no order_bys).
+    pub async fn rank_by_mc(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<TokenFilter>>,
+        limit: Option<i64>,
+        offset: Option<i64>,
+    ) -> Result<Vec<TokenRank>> {
+        let list_options = base::finalize_list_options(Some(ListOptions {
+            limit,
+            offset,
+            order_bys: None,
+        }))?;
+
+        let dbx = mm.dbx();
+
+        // -- Build query
+        let mut query = Query::select();
+        query
+            .from(Self::table_ref())
+            .columns(Token::field_idens())
+            .expr_as(
+                Expr::cust(r#"rank() OVER (ORDER BY "mc" DESC)"#),
+                Alias::new("rank"),
+            )
+            .cond_where(base::list_cond::<Self, _>(ctx, filters)?)
+            .order_by(TokenIden::Mc, Order::Desc)
+            .order_by(CommonIden::Id, Order::Asc);
+        if let Some(limit) = list_options.limit {
+            query.limit(limit as u64);
+        }
+        if let Some(offset) = list_options.offset {
+            query.offset(offset as u64);
+        }
+
+        // -- Exec query
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        let ranks = dbx
+            .fetch_all(sqlx::query_as_with::<_, TokenRank, _>(&sql, values))
+            .await?;
+
+        Ok(ranks)
+    }
+
+    /// The count and the mc/liquidity/v24h_usd aggregates of the tokens (of the filters).
+    pub async fn stats(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<TokenFilter>>,
+    ) -> Result<TokenStats> {
+        let dbx = mm.dbx();
+
+        // -- Build query
+        let mut query = Query::select();
+        query
+            .from(Self::table_ref())
+            .expr_as(Expr::col(CommonIden::Id).count(), Alias::new("count"));
+        for metric in STATS_METRICS {
+            let col = || Expr::col(Alias::new(metric));
+            query
+                .expr_as(col().sum(), Alias::new(format!("{metric}_sum")))
+                .expr_as(Func::avg(col()), Alias::new(format!("{metric}_avg")))
+                .expr_as(col().min(), Alias::new(format!("{metric}_min")))
+                .expr_as(col().max(), Alias::new(format!("{metric}_max")));
+            for (name, fraction) in STATS_PERCENTILES {
+                query.expr_as(
+                    Expr::cust(format!(
+                        r#"percentile_cont({fraction}) WITHIN GROUP (ORDER BY "{metric}")"#
+                    )),
+                    Alias::new(format!("{metric}_{name}")),
+                );
+            }
+        }
+        query.cond_where(base::list_cond::<Self, _>(ctx, filters)?);
+
+        // -- Exec query
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        let stats = dbx
+            .fetch_one(sqlx::query_as_with::<_, TokenStats, _>(&sql, values))
+            .await?;
+
+        Ok(stats)
+    }
+}
+// endregion: -- TokenBmc Analytics
 
 // region: -- Tests
 #[cfg(test)]
@@ -300,7 +772,9 @@ mod tests {
         // U: :let @a='' to empty register first, THEN: :%s/regex/\=setreg('A', submatch(0))/n
         let fx_update_unix_time = 1692203008;
         let fx_update_time = "2023-08-16T16:23:28";
-        let fx_address = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        // NOTE: Not the real USDC address, as the address is unique and
+        // the seeded tokens (seed_tokens) may already have it.
+        let fx_address = "test_create_ok-EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
         let fx_decimals = 6;
         let fx_liquidity = 287392581.54247737;
         let fx_logo_uri = "https://img.fotofolio.xyz/?url=https%3A%2F%2Fraw.githubusercontent.com%2Fsolana-labs%2Ftoken-list%2Fmain%2Fassets%2Fmainnet%2FEPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v%2Flogo.png";
@@ -485,6 +959,78 @@ mod tests {
         Ok(())
     }
 
+    #[serial]
+    #[tokio::test]
+    async fn test_list_by_filter_ops_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        _dev_utils::seed_tokens(&ctx, &mm).await?;
+        // (filter, expected symbols sorted), against the seeded TOKEN_LIST.json
+        let fx_cases = [
+            // -- Float64 ops
+            (json!({"mc": {"$gt": 5e9}}), vec!["JUP", "PYTH", "SOL"]),
+            (
+                json!({"liquidity": {"$gte": 1e8}}),
+                vec!["JUP", "SOL", "USDC"],
+            ),
+            (
+                json!({"v24h_change_percent": {"$gt": 200}}),
+                vec!["HUND", "MOBILE"],
+            ),
+            (
+                json!({"v24h_change_percent": {"$gte": -0.4, "$lte": -0.3}}),
+                vec!["JUP", "USDC"],
+            ),
+            (json!({"v24h_usd": {"$lt": 5e6}}), vec!["FIAT"]),
+            (
+                json!({"decimals": {"$not": 6}, "mc": {"$gt": 2e9}}),
+                vec!["Bonk", "JTO", "SOL"],
+            ),
+            // -- Int64 ops
+            (
+                json!({"decimals": {"$in": [5]}}),
+                vec!["Bonk", "STAN", "WEN"],
+            ),
+            (
+                json!({"last_trade_unix_time": {"$lt": 1710395640}}),
+                vec!["CAPO", "HUND", "USDC", "WHALE", "whoren"],
+            ),
+            // -- String ops (symbol & name are case-insensitive)
+            (json!({"symbol": "usdc"}), vec!["USDC", "USDC"]),
+            (
+                json!({"symbol": {"$endsWith": "SOL"}}),
+                vec!["JitoSOL", "SOL", "bSOL", "mSOL"],
+            ),
+            (
+                json!({"name": {"$contains": "WIF"}}),
+                vec!["$WIF", "BALLZ", "WIFS"],
+            ),
+            (
+                json!({"name": {"$startsWith": "jupiter"}}),
+                vec!["JLP", "JUP"],
+            ),
+            (
+                json!({"name": {"$containsAny": ["poor", "rich"]}}),
+                vec!["CAPO", "FIAT", "NICK"],
+            ),
+            (json!({"address": {"$startsWith": "so111"}}), vec![]),
+        ];
+
+        for (filter, expected) in fx_cases {
+            // -- Exec
+            let filters: Vec<TokenFilter> = serde_json::from_value(json!([filter]))?;
+            let tokens = TokenBmc::list(&ctx, &mm, Some(filters), None).await?;
+
+            // -- Check
+            let mut symbols: Vec<String> = tokens.into_iter().map(|t| t.symbol).collect();
+            symbols.sort();
+            assert_eq!(symbols, expected, "filter: {filter}");
+        }
+
+        Ok(())
+    }
+
     #[serial]
     #[tokio::test]
     async fn test_update_ok() -> Result<()> {
@@ -559,5 +1105,184 @@ mod tests {
 
         Ok(())
     }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_upsert_ok_and_err_unique() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let ctx_other = Ctx::new(1001)?;
+        let fx_address = "test_upsert_ok_and_err_unique-address";
+        let fx_token_c = |mc: f64, symbol: &str| TokenForCreate {
+            address: fx_address.to_string(),
+            symbol: symbol.to_string(),
+            mc,
+            ..Default::default()
+        };
+
+        // -- Exec & Check: insert, then update (same address)
+        let id = TokenBmc::upsert(&ctx, &mm, fx_token_c(1.0, "UPS")).await?;
+        let id_2 = TokenBmc::upsert(&ctx, &mm, fx_token_c(2.0, "UPS2")).await?;
+        assert_eq!(id, id_2);
+        let token = TokenBmc::get(&ctx, &mm, id).await?;
+        assert_eq!(token.mc, 2.0);
+        // symbol is not a TokenForUpdate field, so not updated
+        assert_eq!(token.symbol, "UPS");
+        assert!(token.mtime > token.ctime);
+
+        // -- Exec & Check: create with the same address
+        let res = TokenBmc::create(&ctx, &mm, fx_token_c(3.0, "UPS")).await;
+        assert!(
+            matches!(
+                &res,
+                Err(crate::model::Error::UniqueViolation { table, constraint })
+                    if table == "token" && constraint == "token_address_key"
+            ),
+            "should be UniqueViolation, but was {res:?}"
+        );
+
+        // -- Exec & Check: the token of another owner is not updated,
+        // and the other rows of the upsert are not written (rolled back)
+        let fx_address_new = "test_upsert_ok_and_err_unique-address-new";
+        let res = TokenBmc::upsert_many(
+            &ctx_other,
+            &mm,
+            vec![
+                TokenForCreate {
+                    address: fx_address_new.to_string(),
+                    symbol: "UPSNEW".to_string(),
+                    ..Default::default()
+                },
+                fx_token_c(4.0, "UPS"),
+            ],
+        )
+        .await;
+        assert!(
+            matches!(
+                &res,
+                Err(crate::model::Error::UpsertNotOwned { entity: "token", unique_values })
+                    if unique_values == &[fx_address]
+            ),
+            "should be UpsertNotOwned, but was {res:?}"
+        );
+        assert_eq!(TokenBmc::get(&ctx, &mm, id).await?.mc, 2.0);
+        let filters: Vec<TokenFilter> =
+            serde_json::from_value(json!([{ "address": fx_address_new }]))?;
+        let tokens = TokenBmc::list(&ctx, &mm, Some(filters), None).await?;
+        assert!(tokens.is_empty(), "new token should be rolled back");
+
+        // -- Clean
+        TokenBmc::delete(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_analytics_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_prefix = "test_analytics_ok";
+        // (symbol, mc, liquidity, v24h_change_percent, v24h_usd)
+        let fx_tokens = [
+            ("AN1", 100.0, 1000.0, 50.0, 10.0),
+            ("AN2", 300.0, 10.0, 80.0, 20.0),
+            ("AN3", 200.0, 5000.0, -30.0, 30.0),
+            ("AN4", 400.0, 2000.0, 5.0, 40.0),
+        ];
+        let token_cs = fx_tokens
+            .iter()
+            .map(
+                |(symbol, mc, liquidity, v24h_change_percent, v24h_usd)| TokenForCreate {
+                    address: format!("{fx_prefix}-{symbol}"),
+                    symbol: symbol.to_string(),
+                    mc: *mc,
+                    liquidity: *liquidity,
+                    v24h_change_percent: *v24h_change_percent,
+                    v24h_usd: *v24h_usd,
+                    ..Default::default()
+                },
+            )
+            .collect();
+        let ids = TokenBmc::create_many(&ctx, &mm, token_cs).await?;
+        // NOTE: The analytics see all the tokens, so the fixtures are
+        // isolated by their address prefix.
+        let fx_filters = || -> serde_json::Result<Option<Vec<TokenFilter>>> {
+            serde_json::from_value(json!([{"address": {"$startsWith": fx_prefix}}])).map(Some)
+        };
+
+        // -- Exec & Check: top movers (AN2 has not enough liquidity)
+        let gainers = TokenBmc::top_movers(
+            &ctx,
+            &mm,
+            fx_filters()?,
+            MoverDirection::Gainers,
+            Some(100.0),
+            Some(2),
+        )
+        .await?;
+        let symbols: Vec<&str> = gainers.iter().map(|t| t.symbol.as_str()).collect();
+        assert_eq!(symbols, ["AN1", "AN4"]);
+        let losers =
+            TokenBmc::top_movers(&ctx, &mm, fx_filters()?, MoverDirection::Losers, None, None)
+                .await?;
+        let symbols: Vec<&str> = losers.iter().map(|t| t.symbol.as_str()).collect();
+        assert_eq!(symbols, ["AN3", "AN4", "AN1", "AN2"]);
+
+        // -- Exec & Check: a user Ctx sees the same tokens (not owner scoped)
+        let user_ctx = Ctx::new(1018)?;
+        let losers = TokenBmc::top_movers(
+            &user_ctx,
+            &mm,
+            fx_filters()?,
+            MoverDirection::Losers,
+            None,
+            None,
+        )
+        .await?;
+        assert_eq!(losers.len(), 4);
+
+        // -- Exec & Check: rank (the second page keeps the rank)
+        let ranks = TokenBmc::rank_by_mc(&ctx, &mm, fx_filters()?, Some(2), Some(2)).await?;
+        let ranks: Vec<(i64, &str)> = ranks
+            .iter()
+            .map(|r| (r.rank, r.token.symbol.as_str()))
+            .collect();
+        assert_eq!(ranks, [(3, "AN3"), (4, "AN1")]);
+
+        // -- Exec & Check: stats
+        let stats = TokenBmc::stats(&user_ctx, &mm, fx_filters()?).await?;
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.mc.sum, Some(1000.0));
+        assert_eq!(stats.mc.avg, Some(250.0));
+        assert_eq!(stats.mc.min, Some(100.0));
+        assert_eq!(stats.mc.max, Some(400.0));
+        assert_eq!(stats.mc.p50, Some(250.0));
+        assert_eq!(stats.v24h_usd.sum, Some(100.0));
+        let filters: Vec<TokenFilter> = serde_json::from_value(json!([
+            {"address": {"$startsWith": fx_prefix}, "symbol": {"$in": ["AN1", "AN2"]}}
+        ]))?;
+        let stats = TokenBmc::stats(&ctx, &mm, Some(filters)).await?;
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.liquidity.max, Some(1000.0));
+
+        // -- Exec & Check: stats of no tokens
+        let filters: Vec<TokenFilter> = serde_json::from_value(json!([
+            {"address": "test_analytics_ok-none"}
+        ]))?;
+        let stats = TokenBmc::stats(&ctx, &mm, Some(filters)).await?;
+        assert_eq!(stats.count, 0);
+        assert_eq!(stats.mc.sum, None);
+        assert_eq!(stats.mc.p99, None);
+
+        // -- Clean
+        for id in ids {
+            TokenBmc::delete(&ctx, &mm, id).await?;
+        }
+
+        Ok(())
+    }
 }
 // endregion: -- Tests
@@ -0,0 +1,323 @@
+// NOTE: Token metrics history (time series). The token row is overwritten on
+// each ingestion, so TokenBmc records a snapshot of the metrics on each write,
+// one per token and Birdeye update_unix_time (see sql/migrations/0003-token-snapshot.sql).
+use crate::ctx::Ctx;
+use crate::model::base::{CommonIden, DbBmc, LIST_LIMIT_MAX};
+use crate::model::token::TokenBmc;
+use crate::model::ModelManager;
+use crate::model::Result;
+use modql::field::{Fields, HasFields};
+use sea_query::{Alias, Expr, Iden, OnConflict, Order, PostgresQueryBuilder, Query};
+use sea_query_binder::SqlxBinder;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+// region: -- TokenSnapshot Types
+#[derive(Debug, Clone, Fields, FromRow, Serialize)]
+pub struct TokenSnapshot {
+    pub id: i64,
+    pub token_id: i64,
+    pub update_unix_time: i64,
+
+    pub liquidity: f64,
+    pub mc: f64,
+    pub v24h_change_percent: f64,
+    pub v24h_usd: f64,
+}
+
+/// The downsampling bucket of the token history
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryBucket {
+    Hour,
+    Day,
+}
+
+impl HistoryBucket {
+    fn secs(self) -> i64 {
+        match self {
+            HistoryBucket::Hour => 3600,
+            HistoryBucket::Day => 86400,
+        }
+    }
+}
+
+/// The metrics min/max/last of the snapshots of one bucket
+#[derive(Debug, FromRow, Serialize)]
+pub struct TokenSnapshotBucket {
+    /// The bucket start (unix time, aligned on the bucket size)
+    pub bucket_start: i64,
+    pub count: i64,
+
+    pub liquidity_min: f64,
+    pub liquidity_max: f64,
+    pub liquidity_last: f64,
+    pub mc_min: f64,
+    pub mc_max: f64,
+    pub mc_last: f64,
+    pub v24h_change_percent_min: f64,
+    pub v24h_change_percent_max: f64,
+    pub v24h_change_percent_last: f64,
+    pub v24h_usd_min: f64,
+    pub v24h_usd_max: f64,
+    pub v24h_usd_last: f64,
+}
+
+#[derive(Iden)]
+enum TokenSnapshotIden {
+    TokenId,
+    UpdateUnixTime,
+    Liquidity,
+    Mc,
+    V24hChangePercent,
+    V24hUsd,
+}
+
+// NOTE: The snapshot metrics columns (same names in the token table)
+const METRIC_COLUMNS: [&str; 4] = ["liquidity", "mc", "v24h_change_percent", "v24h_usd"];
+// endregion: -- TokenSnapshot Types
+
+// region: -- TokenSnapshotBmc
+pub struct TokenSnapshotBmc;
+
+impl DbBmc for TokenSnapshotBmc {
+    const TABLE: &'static str = "token_snapshot";
+}
+
+impl TokenSnapshotBmc {
+    /// Record the current metrics of the tokens (called by the TokenBmc writes).
+    // NOTE: One INSERT ... SELECT from the token table, so the snapshot is what
+    // was actually written. A snapshot already recorded for the same
+    // update_unix_time is kept (ON CONFLICT DO NOTHING).
+    pub async fn record(_ctx: &Ctx, mm: &ModelManager, token_ids: &[i64]) -> Result<()> {
+        let dbx = mm.dbx();
+
+        for token_ids in token_ids.chunks(LIST_LIMIT_MAX as usize) {
+            // -- Build query
+            let mut select = Query::select();
+            select
+                .from(TokenBmc::table_ref())
+                .column(CommonIden::Id)
+                .columns([
+                    TokenSnapshotIden::UpdateUnixTime,
+                    TokenSnapshotIden::Liquidity,
+                    TokenSnapshotIden::Mc,
+                    TokenSnapshotIden::V24hChangePercent,
+                    TokenSnapshotIden::V24hUsd,
+                ])
+                .and_where(Expr::col(CommonIden::Id).is_in(token_ids.iter().copied()));
+
+            let mut query = Query::insert();
+            query
+                .into_table(Self::table_ref())
+                .columns([
+                    TokenSnapshotIden::TokenId,
+                    TokenSnapshotIden::UpdateUnixTime,
+                    TokenSnapshotIden::Liquidity,
+                    TokenSnapshotIden::Mc,
+                    TokenSnapshotIden::V24hChangePercent,
+                    TokenSnapshotIden::V24hUsd,
+                ])
+                .select_from(select)?
+                .on_conflict(
+                    OnConflict::columns([
+                        TokenSnapshotIden::TokenId,
+                        TokenSnapshotIden::UpdateUnixTime,
+                    ])
+                    .do_nothing()
+                    .to_owned(),
+                );
+
+            // -- Exec query
+            let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+            dbx.execute(sqlx::query_with(&sql, values)).await?;
+        }
+
+        Ok(())
+    }
+
+    /// The token snapshots between from/to (unix times, inclusive), oldest first.
+    /// Returns at most LIST_LIMIT_MAX snapshots (use a bucket for longer ranges).
+    pub async fn list_for_token(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        token_id: i64,
+        from: Option<i64>,
+        to: Option<i64>,
+    ) -> Result<Vec<TokenSnapshot>> {
+        // NOTE: The token get checks the token exists and is visible to the Ctx.
+        TokenBmc::get(ctx, mm, token_id).await?;
+
+        let dbx = mm.dbx();
+
+        // -- Build query
+        let mut query = Query::select();
+        query
+            .from(Self::table_ref())
+            .columns(TokenSnapshot::field_idens())
+            .and_where(Expr::col(TokenSnapshotIden::TokenId).eq(token_id))
+            .and_where_option(
+                from.map(|from| Expr::col(TokenSnapshotIden::UpdateUnixTime).gte(from)),
+            )
+            .and_where_option(to.map(|to| Expr::col(TokenSnapshotIden::UpdateUnixTime).lte(to)))
+            .order_by(TokenSnapshotIden::UpdateUnixTime, Order::Asc)
+            .limit(LIST_LIMIT_MAX as u64);
+
+        // -- Exec query
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        let snapshots = dbx
+            .fetch_all(sqlx::query_as_with::<_, TokenSnapshot, _>(&sql, values))
+            .await?;
+
+        Ok(snapshots)
+    }
+
+    /// The token history between from/to (unix times, inclusive) downsampled
+    /// in buckets (min/max/last of each metric), oldest first.
+    /// Returns at most LIST_LIMIT_MAX buckets (narrow the range for more).
+    pub async fn list_buckets_for_token(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        token_id: i64,
+        from: Option<i64>,
+        to: Option<i64>,
+        bucket: HistoryBucket,
+    ) -> Result<Vec<TokenSnapshotBucket>> {
+        TokenBmc::get(ctx, mm, token_id).await?;
+
+        let dbx = mm.dbx();
+
+        // -- Build query
+        let mut query = Query::select();
+        query
+            .from(Self::table_ref())
+            .expr_as(
+                Expr::cust_with_values(
+                    r#""update_unix_time" - "update_unix_time" % $1"#,
+                    [bucket.secs()],
+                ),
+                Alias::new("bucket_start"),
+            )
+            .expr_as(Expr::cust("count(*)"), Alias::new("count"));
+        for metric in METRIC_COLUMNS {
+            query
+                .expr_as(
+                    Expr::col(Alias::new(metric)).min(),
+                    Alias::new(format!("{metric}_min")),
+                )
+                .expr_as(
+                    Expr::col(Alias::new(metric)).max(),
+                    Alias::new(format!("{metric}_max")),
+                )
+                .expr_as(
+                    // NOTE: The last value of the bucket (latest update_unix_time)
+                    Expr::cust(format!(
+                        r#"(array_agg("{metric}" ORDER BY "update_unix_time" DESC))[1]"#
+                    )),
+                    Alias::new(format!("{metric}_last")),
+                );
+        }
+        query
+            .and_where(Expr::col(TokenSnapshotIden::TokenId).eq(token_id))
+            .and_where_option(
+                from.map(|from| Expr::col(TokenSnapshotIden::UpdateUnixTime).gte(from)),
+            )
+            .and_where_option(to.map(|to| Expr::col(TokenSnapshotIden::UpdateUnixTime).lte(to)))
+            .group_by_col(Alias::new("bucket_start"))
+            .order_by(Alias::new("bucket_start"), Order::Asc)
+            .limit(LIST_LIMIT_MAX as u64);
+
+        // -- Exec query
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        let buckets = dbx
+            .fetch_all(sqlx::query_as_with::<_, TokenSnapshotBucket, _>(
+                &sql, values,
+            ))
+            .await?;
+
+        Ok(buckets)
+    }
+}
+// endregion: -- TokenSnapshotBmc
+
+// region: -- Tests
+#[cfg(test)]
+mod tests {
+    #![allow(unused)]
+    pub type Result<T> = core::result::Result<T, Error>;
+    pub type Error = Box<dyn std::error::Error>; // For early dev & tests.
+
+    use super::*;
+    use crate::_dev_utils;
+    use crate::model::token::{TokenForCreate, TokenForUpdate};
+    use serial_test::serial;
+
+    #[serial]
+    #[tokio::test]
+    async fn test_record_and_list_buckets_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        // 2024-03-14T08:00:00Z (hour aligned)
+        let fx_t0 = 1710403200;
+        let id = TokenBmc::create(
+            &ctx,
+            &mm,
+            TokenForCreate {
+                address: "test_record_and_list_buckets_ok-address".to_string(),
+                update_unix_time: fx_t0,
+                mc: 1.0,
+                ..Default::default()
+            },
+        )
+        .await?;
+        // Two more snapshots in the first hour, one in the next hour
+        for (offset, mc) in [(60, 3.0), (120, 2.0), (3600, 10.0)] {
+            TokenBmc::update(
+                &ctx,
+                &mm,
+                id,
+                TokenForUpdate {
+                    update_unix_time: Some(fx_t0 + offset),
+                    mc: Some(mc),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        }
+
+        // -- Exec & Check: raw snapshots (with range)
+        let snapshots = TokenSnapshotBmc::list_for_token(&ctx, &mm, id, None, None).await?;
+        let mcs: Vec<f64> = snapshots.iter().map(|s| s.mc).collect();
+        assert_eq!(mcs, vec![1.0, 3.0, 2.0, 10.0]);
+        let snapshots =
+            TokenSnapshotBmc::list_for_token(&ctx, &mm, id, Some(fx_t0 + 60), Some(fx_t0 + 120))
+                .await?;
+        assert_eq!(snapshots.len(), 2);
+
+        // -- Exec & Check: hourly buckets
+        let buckets = TokenSnapshotBmc::list_buckets_for_token(
+            &ctx,
+            &mm,
+            id,
+            None,
+            None,
+            HistoryBucket::Hour,
+        )
+        .await?;
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].bucket_start, fx_t0);
+        assert_eq!(buckets[0].count, 3);
+        assert_eq!(buckets[0].mc_min, 1.0);
+        assert_eq!(buckets[0].mc_max, 3.0);
+        assert_eq!(buckets[0].mc_last, 2.0);
+        assert_eq!(buckets[1].bucket_start, fx_t0 + 3600);
+        assert_eq!(buckets[1].mc_last, 10.0);
+
+        // -- Clean (snapshots deleted by cascade)
+        TokenBmc::delete(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
+}
+// endregion: -- Tests
@@ -1,11 +1,15 @@
 // use crate::crypt::{pwd, EncryptContent};
-use crate::ctx::Ctx;
-use crate::model::base::{self, DbBmc};
+use crate::ctx::{Ctx, Role};
+use crate::model::alert::AlertRuleBmc;
+use crate::model::base::{self, CommonIden, DbBmc};
+use crate::model::task::TaskBmc;
+use crate::model::watchlist::WatchlistBmc;
 use crate::model::ModelManager;
-use crate::model::Result;
+use crate::model::{Error, ListPage, Result};
 use lib_auth::pwd::{self, ContentToHash};
 use modql::field::{Fields, HasFields};
-use sea_query::{Expr, Iden, PostgresQueryBuilder, Query, SimpleExpr};
+use modql::filter::{FilterNode, FilterNodes, ListOptions, OpValsInt64, OpValsString};
+use sea_query::{Expr, Iden, LockType, PostgresQueryBuilder, Query};
 use sea_query_binder::SqlxBinder;
 use serde::{Deserialize, Serialize};
 use sqlx::postgres::PgRow;
@@ -19,6 +23,7 @@ pub struct User {
     // and sent back... where?
     pub id: i64,
     pub username: String,
+    pub role: String,
 }
 
 // NOTE: For app api. (e.g., UserBmc::create argument)
@@ -37,6 +42,18 @@ pub struct UserForInsert {
     pub username: String,
 }
 
+// NOTE: The pwd & token_salt writes go through base::update (audited, redacted),
+// so they have their own Fields structs (not part of any client data).
+#[derive(Fields)]
+struct UserForUpdatePwd {
+    pwd: String,
+}
+
+#[derive(Fields)]
+struct UserForUpdateTokenSalt {
+    token_salt: Uuid,
+}
+
 // NOTE: Read only to validate login info.
 // Used for log in logic
 #[derive(Clone, FromRow, Fields, Debug)]
@@ -59,11 +76,22 @@ pub struct UserForLogin {
 pub struct UserForAuth {
     pub id: i64,
     pub username: String,
+    // NOTE: Parsed into a ctx::Role for the Ctx (see mw_ctx_resolve)
+    pub role: String,
 
     // --token info
     pub token_salt: Uuid,
 }
 
+/// Filter by custom fields (e.g., for the admin list_users rpc)
+#[derive(FilterNodes, Deserialize, Default, Debug)]
+pub struct UserFilter {
+    id: Option<OpValsInt64>,
+
+    username: Option<OpValsString>,
+    role: Option<OpValsString>,
+}
+
 /// Marker trait
 // NOTE: These bounds are what we have in DbBmc E (entity) type
 pub trait UserBy: HasFields + for<'r> FromRow<'r, PgRow> + Unpin + Send {}
@@ -84,7 +112,9 @@ impl UserBy for UserForAuth {}
 pub enum UserIden {
     Id,
     Username,
+    Role,
     Pwd,
+    TokenSalt,
 }
 
 // endregion: -- User Types
@@ -94,9 +124,51 @@ pub struct UserBmc;
 
 impl DbBmc for UserBmc {
     const TABLE: &'static str = "user";
+    const AUDITED: bool = true;
 }
 
 impl UserBmc {
+    // NOTE: The pwd is hashed with the row's pwd_salt, which is only known
+    // after the insert, so we insert first and then update_pwd().
+    // U: Both steps now run in one transaction.
+    pub async fn create(ctx: &Ctx, mm: &ModelManager, user_c: UserForCreate) -> Result<i64> {
+        let UserForCreate {
+            username,
+            pwd_clear,
+        } = user_c;
+
+        // NOTE: On any early Err return, the (not committed) txn is rolled back on drop.
+        let mm = &mm.new_with_txn()?;
+        mm.begin_txn().await?;
+
+        // -- Validate username & pwd, check duplicate
+        validate_username(&username)?;
+        validate_pwd_clear(&pwd_clear)?;
+        if Self::first_by_username::<User>(ctx, mm, &username)
+            .await?
+            .is_some()
+        {
+            return Err(Error::UserAlreadyExists { username });
+        }
+
+        // -- Insert & Set pwd
+        let user_fi = UserForInsert {
+            username: username.clone(),
+        };
+        // NOTE: A concurrent create can still hit the username unique constraint.
+        let user_id = base::create::<Self, _>(ctx, mm, user_fi)
+            .await
+            .map_err(|ex| match ex {
+                Error::UniqueViolation { .. } => Error::UserAlreadyExists { username },
+                ex => ex,
+            })?;
+        Self::update_pwd(ctx, mm, user_id, &pwd_clear).await?;
+
+        mm.commit_txn().await?;
+
+        Ok(user_id)
+    }
+
     pub async fn get<E>(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<E>
     where
         E: UserBy,
@@ -117,7 +189,7 @@ impl UserBmc {
     {
         // NOTE: This function deviates from base, so we go back to custom
         // sqlx and sqlb.
-        let db = mm.db();
+        let dbx = mm.dbx();
 
         // -- Build the query w/ sea-query
         // NOTE: The builder pattern in sea-query is a "Ref Mut" pattern
@@ -132,15 +204,141 @@ impl UserBmc {
 
         // -- Exec query
         let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
-        let user = sqlx::query_as_with::<_, E, _>(&sql, values)
-            .fetch_optional(db)
+        let user = dbx
+            .fetch_optional(sqlx::query_as_with::<_, E, _>(&sql, values))
             .await?;
 
         Ok(user)
     }
 
+    pub async fn list(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<UserFilter>>,
+        list_options: Option<ListOptions>,
+    ) -> Result<Vec<User>> {
+        base::list::<Self, _, _>(ctx, mm, filters, list_options).await
+    }
+
+    /// Paged list ({items, total, next_cursor}), see base::list_paged
+    pub async fn list_paged(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<UserFilter>>,
+        list_options: Option<ListOptions>,
+        cursor: Option<String>,
+    ) -> Result<ListPage<User>> {
+        base::list_paged::<Self, _, _>(ctx, mm, filters, list_options, cursor).await
+    }
+
+    /// Delete the user, with the rows it owns (tasks, watchlists, alert rules).
+    // NOTE: owner_id has no FK on "user" (the root Ctx, user_id 0, also owns rows,
+    // and is not a "user" row), so the owned rows are deleted here, in the same txn.
+    // The tokens stay (shared market data, their owner_id is only the creator),
+    // as do the audit_log rows. The alert events go with their rules (FK cascade).
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        if id == ctx.user_id() {
+            return Err(Error::UserDeleteSelf { user_id: id });
+        }
+
+        let mm = &mm.new_with_txn()?;
+        mm.begin_txn().await?;
+
+        // -- Guard the last admin
+        if Self::lock_admin_ids(mm).await? == [id] {
+            return Err(Error::UserDeleteLastAdmin { user_id: id });
+        }
+
+        // -- Delete the owned rows & the user
+        let ctx_all = ctx.for_all_owners()?;
+        let owner_filter = || FilterNode::from(("owner_id", id));
+        base::delete_many::<TaskBmc, _>(&ctx_all, mm, owner_filter()).await?;
+        base::delete_many::<WatchlistBmc, _>(&ctx_all, mm, owner_filter()).await?;
+        base::delete_many::<AlertRuleBmc, _>(&ctx_all, mm, owner_filter()).await?;
+        base::delete::<Self>(ctx, mm, id).await?;
+
+        mm.commit_txn().await?;
+
+        Ok(())
+    }
+
+    /// The admin user ids, locked until the end of the (caller's) txn.
+    // NOTE: The lock makes two concurrent admin deletes wait on each other,
+    // so they cannot both pass the last admin guard.
+    async fn lock_admin_ids(mm: &ModelManager) -> Result<Vec<i64>> {
+        let mut query = Query::select();
+        query
+            .from(Self::table_ref())
+            .column(CommonIden::Id)
+            .and_where(Expr::col(UserIden::Role).eq(Role::Admin.as_str()))
+            .lock(LockType::Update);
+
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        let ids = mm
+            .dbx()
+            .fetch_all(sqlx::query_as_with::<_, (i64,), _>(&sql, values))
+            .await?
+            .into_iter()
+            .map(|(id,)| id)
+            .collect();
+
+        Ok(ids)
+    }
+
+    /// Change the pwd of a user after validating its current (old) pwd
+    pub async fn change_pwd(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        pwd_old_clear: &str,
+        pwd_new_clear: &str,
+    ) -> Result<()> {
+        validate_pwd_clear(pwd_new_clear)?;
+
+        let mm = &mm.new_with_txn()?;
+        mm.begin_txn().await?;
+
+        let user: UserForLogin = Self::get(ctx, mm, id).await?;
+
+        // -- Validate the old pwd
+        let Some(pwd) = user.pwd else {
+            return Err(Error::UserPwdNotMatching { user_id: id });
+        };
+        pwd::validate_pwd(
+            ContentToHash {
+                content: pwd_old_clear.to_string(),
+                salt: user.pwd_salt,
+            },
+            pwd,
+        )
+        .await
+        .map_err(|_| Error::UserPwdNotMatching { user_id: id })?;
+
+        // -- Update to the new pwd & revoke the existing web tokens
+        Self::update_pwd(ctx, mm, id, pwd_new_clear).await?;
+        Self::rotate_token_salt(ctx, mm, id).await?;
+
+        mm.commit_txn().await?;
+
+        Ok(())
+    }
+
+    /// Set a new random token_salt for the user.
+    // NOTE: Web tokens are signed with the user token_salt, so all the previously
+    // issued tokens will fail validate_web_token() (SignatureNotMatching).
+    // Used on logoff, pwd change and admin revoke (revoke_user_sessions rpc).
+    pub async fn rotate_token_salt(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        let user_u = UserForUpdateTokenSalt {
+            token_salt: Uuid::new_v4(),
+        };
+
+        base::update::<Self, _>(ctx, mm, id, user_u).await
+    }
+
     pub async fn update_pwd(ctx: &Ctx, mm: &ModelManager, id: i64, pwd_clear: &str) -> Result<()> {
-        let db = mm.db();
+        // NOTE: get + update in one txn (nested when the caller has one open)
+        let mm = &mm.new_with_txn()?;
+        mm.begin_txn().await?;
 
         // -- Prep password. Assumes we already have the user id
         let user: UserForLogin = Self::get(ctx, mm, id).await?;
@@ -150,25 +348,52 @@ impl UserBmc {
         })
         .await?;
 
-        // -- Build query
-        let mut query = Query::update();
-        query
-            .table(Self::table_ref())
-            .value(UserIden::Pwd, SimpleExpr::from(pwd))
-            .and_where(Expr::col(UserIden::Id).eq(id));
+        // -- Update
+        base::update::<Self, _>(ctx, mm, id, UserForUpdatePwd { pwd }).await?;
 
-        // -- Exec query
-        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
-        // NOTE: We could consider checking this result and returning an Err or Ok
-        let _count = sqlx::query_with(&sql, values)
-            .execute(db)
-            .await?
-            .rows_affected();
+        mm.commit_txn().await?;
 
         Ok(())
     }
 }
 
+/// Username rules: 3 to 64 chars of ascii letters, digits, '_', '-' or '.',
+/// starting with a letter.
+fn validate_username(username: &str) -> Result<()> {
+    let invalid = |reason: &'static str| {
+        Err(Error::UserUsernameInvalid {
+            reason: reason.to_string(),
+        })
+    };
+
+    if !(3..=64).contains(&username.len()) {
+        return invalid("must be 3 to 64 characters");
+    }
+    if !username.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return invalid("must start with a letter");
+    }
+    if !username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        return invalid("only letters, digits, '_', '-' and '.' allowed");
+    }
+
+    Ok(())
+}
+
+/// Minimal length of a (new) clear pwd
+const PWD_MIN_LEN: usize = 6;
+
+fn validate_pwd_clear(pwd_clear: &str) -> Result<()> {
+    if pwd_clear.chars().count() < PWD_MIN_LEN {
+        return Err(Error::UserPwdInvalid {
+            reason: format!("must be at least {PWD_MIN_LEN} characters"),
+        });
+    }
+
+    Ok(())
+}
 // endregion: -- UserBmc
 
 // region: -- Tests
@@ -180,6 +405,12 @@ mod tests {
 
     use super::*;
     use crate::_dev_utils;
+    use crate::model::alert::{AlertComparator, AlertMetric, AlertRuleForCreate};
+    use crate::model::audit::{AuditBmc, AuditInfo, AuditLogFilter};
+    use crate::model::task::TaskForCreate;
+    use crate::model::token::{TokenBmc, TokenForCreate, TokenRef};
+    use crate::model::watchlist::WatchlistForCreate;
+    use serde_json::json;
     use serial_test::serial;
 
     #[serial]
@@ -202,5 +433,265 @@ mod tests {
 
         Ok(())
     }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_create_ok_and_change_pwd() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_username = "test_create_ok-user-01";
+
+        // -- Exec
+        let id = UserBmc::create(
+            &ctx,
+            &mm,
+            UserForCreate {
+                username: fx_username.to_string(),
+                pwd_clear: "pwd-01".to_string(),
+            },
+        )
+        .await?;
+
+        // -- Check: create
+        let user: User = UserBmc::get(&ctx, &mm, id).await?;
+        assert_eq!(user.username, fx_username);
+        assert_eq!(user.role, "user");
+
+        // -- Check: duplicate
+        let res = UserBmc::create(
+            &ctx,
+            &mm,
+            UserForCreate {
+                username: fx_username.to_string(),
+                pwd_clear: "pwd-02".to_string(),
+            },
+        )
+        .await;
+        assert!(
+            matches!(res, Err(crate::model::Error::UserAlreadyExists { .. })),
+            "UserAlreadyExists not matching"
+        );
+
+        // -- Check: change_pwd
+        let res = UserBmc::change_pwd(&ctx, &mm, id, "wrong-pwd", "pwd-02").await;
+        assert!(
+            matches!(res, Err(crate::model::Error::UserPwdNotMatching { .. })),
+            "UserPwdNotMatching not matching"
+        );
+        UserBmc::change_pwd(&ctx, &mm, id, "pwd-01", "pwd-02").await?;
+        UserBmc::change_pwd(&ctx, &mm, id, "pwd-02", "pwd-03").await?;
+
+        // -- Clean
+        UserBmc::delete(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_rotate_token_salt_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let user: UserForAuth = UserBmc::first_by_username(&ctx, &mm, "demo1")
+            .await?
+            .ok_or("Should have user 'demo1'")?;
+        let fx_token = lib_auth::token::generate_web_token(&user.username, user.token_salt)?;
+
+        // -- Exec
+        UserBmc::rotate_token_salt(&ctx, &mm, user.id).await?;
+
+        // -- Check
+        let user: UserForAuth = UserBmc::get(&ctx, &mm, user.id).await?;
+        let res = lib_auth::token::validate_web_token(&fx_token, user.token_salt);
+        assert!(
+            matches!(res, Err(lib_auth::token::Error::SignatureNotMatching)),
+            "SignatureNotMatching not matching"
+        );
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_change_pwd_audited_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_username = "test_change_pwd_audited_ok-user-01";
+        let id = UserBmc::create(
+            &ctx,
+            &mm,
+            UserForCreate {
+                username: fx_username.to_string(),
+                pwd_clear: "pwd-01".to_string(),
+            },
+        )
+        .await?;
+        let mm_audit = mm.with_audit(AuditInfo {
+            rpc_method: "change_pwd".to_string(),
+            req_uuid: Uuid::new_v4(),
+        });
+
+        // -- Exec
+        UserBmc::change_pwd(&ctx, &mm_audit, id, "pwd-01", "pwd-02").await?;
+
+        // -- Check: one update per write, with the secrets redacted
+        let filters: Vec<AuditLogFilter> = vec![serde_json::from_value(json!({
+            "entity": "user",
+            "entity_id": id
+        }))?];
+        let logs = AuditBmc::list(&ctx, &mm, Some(filters), None).await?;
+        let changes: Vec<_> = logs
+            .iter()
+            .map(|log| (log.action.as_str(), log.before.clone(), log.after.clone()))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (
+                    "update",
+                    Some(json!({"pwd": "[redacted]"})),
+                    Some(json!({"pwd": "[redacted]"}))
+                ),
+                (
+                    "update",
+                    Some(json!({"token_salt": "[redacted]"})),
+                    Some(json!({"token_salt": "[redacted]"}))
+                ),
+            ]
+        );
+        assert!(logs
+            .iter()
+            .all(|log| log.rpc_method.as_deref() == Some("change_pwd")));
+
+        // -- Clean
+        UserBmc::delete(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_delete_owned_rows_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let root_ctx = Ctx::root_ctx();
+        let fx_username = "test_delete_owned_rows_ok-user-01";
+        let fx_address = "test_delete_owned_rows_ok-address";
+        let id = UserBmc::create(
+            &root_ctx,
+            &mm,
+            UserForCreate {
+                username: fx_username.to_string(),
+                pwd_clear: "pwd-01".to_string(),
+            },
+        )
+        .await?;
+        let ctx = Ctx::new(id)?;
+        let token_id = TokenBmc::create(
+            &root_ctx,
+            &mm,
+            TokenForCreate {
+                address: fx_address.to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+        TaskBmc::create(
+            &ctx,
+            &mm,
+            TaskForCreate {
+                title: "test_delete_owned_rows_ok-task".to_string(),
+            },
+        )
+        .await?;
+        let watchlist_id = WatchlistBmc::create(
+            &ctx,
+            &mm,
+            WatchlistForCreate {
+                name: "test_delete_owned_rows_ok-watchlist".to_string(),
+            },
+        )
+        .await?;
+        WatchlistBmc::add_token(&ctx, &mm, watchlist_id, TokenRef::TokenId(token_id)).await?;
+        AlertRuleBmc::create(
+            &ctx,
+            &mm,
+            AlertRuleForCreate {
+                token: TokenRef::TokenId(token_id),
+                metric: AlertMetric::Mc,
+                comparator: AlertComparator::Gt,
+                threshold: 10.0,
+                cooldown_sec: None,
+            },
+        )
+        .await?;
+
+        // -- Exec
+        UserBmc::delete(&root_ctx, &mm, id).await?;
+
+        // -- Check: the owned rows are gone, the (shared) token stays
+        assert!(TaskBmc::list(&ctx, &mm, None, None).await?.is_empty());
+        assert!(WatchlistBmc::list(&ctx, &mm, None, None).await?.is_empty());
+        assert!(AlertRuleBmc::list(&ctx, &mm, None, None).await?.is_empty());
+        TokenBmc::get(&root_ctx, &mm, token_id).await?;
+
+        // -- Clean
+        TokenBmc::delete(&root_ctx, &mm, token_id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_delete_err_self_and_last_admin() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let root_ctx = Ctx::root_ctx();
+        let admin: User = UserBmc::first_by_username(&root_ctx, &mm, "admin1")
+            .await?
+            .ok_or("Should have user 'admin1'")?;
+        let ctx_admin = Ctx::new_with_role(admin.id, Role::Admin)?;
+
+        // -- Exec & Check: self
+        let res = UserBmc::delete(&ctx_admin, &mm, admin.id).await;
+        assert!(
+            matches!(res, Err(crate::model::Error::UserDeleteSelf { .. })),
+            "UserDeleteSelf not matching"
+        );
+
+        // -- Exec & Check: last admin
+        let res = UserBmc::delete(&root_ctx, &mm, admin.id).await;
+        assert!(
+            matches!(res, Err(crate::model::Error::UserDeleteLastAdmin { .. })),
+            "UserDeleteLastAdmin not matching"
+        );
+        UserBmc::get::<User>(&root_ctx, &mm, admin.id).await?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_username() -> Result<()> {
+        assert!(validate_username("demo1").is_ok());
+        assert!(validate_username("jane.doe-01_a").is_ok());
+        assert!(validate_username("ab").is_err());
+        assert!(validate_username("1abc").is_err());
+        assert!(validate_username("bad name").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_pwd_clear() -> Result<()> {
+        assert!(validate_pwd_clear("welcome").is_ok());
+        assert!(validate_pwd_clear("pwd-01").is_ok());
+        assert!(validate_pwd_clear("").is_err());
+        assert!(validate_pwd_clear("pwd").is_err());
+
+        Ok(())
+    }
 }
 // endregion: -- Tests
@@ -0,0 +1,434 @@
+// NOTE: Token watchlists per user. A watchlist is owned by the Ctx user
+// (DbBmc OWNED), and its tokens are the watchlist_item memberships
+// (see sql/migrations/0006-watchlist.sql).
+use crate::ctx::Ctx;
+use crate::model::audit;
+use crate::model::base::{self, CommonIden, DbBmc};
+use crate::model::token::{Token, TokenBmc, TokenRef};
+use crate::model::ModelManager;
+use crate::model::{Error, ListPage, Result};
+use lib_utils::time::Rfc3339;
+use modql::field::{Fields, HasFields};
+use modql::filter::{FilterNodes, ListOptions, OpValsInt64, OpValsString};
+use modql::SIden;
+use sea_query::{Condition, Expr, Iden, OnConflict, Order, PostgresQueryBuilder, Query};
+use sea_query_binder::SqlxBinder;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+// region: -- Watchlist Types
+#[serde_as]
+#[derive(Debug, Clone, Fields, FromRow, Serialize)]
+pub struct Watchlist {
+    pub id: i64,
+    pub name: String,
+
+    // -- Ownership (filled from the Ctx in base::create)
+    pub owner_id: i64,
+
+    // -- Timestamps (stamped in base::create/update)
+    pub cid: i64,
+    #[serde_as(as = "Rfc3339")]
+    pub ctime: OffsetDateTime,
+    pub mid: i64,
+    #[serde_as(as = "Rfc3339")]
+    pub mtime: OffsetDateTime,
+}
+
+#[derive(Fields, Deserialize)]
+pub struct WatchlistForCreate {
+    pub name: String,
+}
+
+/// Sent to model layer to rename the watchlist
+#[derive(Fields, Deserialize)]
+pub struct WatchlistForUpdate {
+    pub name: String,
+}
+
+#[derive(FilterNodes, Deserialize, Default, Debug)]
+pub struct WatchlistFilter {
+    id: Option<OpValsInt64>,
+
+    name: Option<OpValsString>,
+}
+
+#[derive(Iden)]
+enum WatchlistItemIden {
+    #[iden = "watchlist_item"]
+    Table,
+    WatchlistId,
+    TokenId,
+}
+
+/// The watchlist_item memberships, only for their audit (see WatchlistBmc::add_token/remove_token)
+// NOTE: The items are written by the WatchlistBmc (scoped by the watchlist owner),
+// so this Bmc has no fns, just the audit flags of the table.
+struct WatchlistItemBmc;
+
+impl DbBmc for WatchlistItemBmc {
+    const TABLE: &'static str = "watchlist_item";
+    const AUDITED: bool = true;
+}
+
+/// The condition of the (watchlist_id, token_id) item
+fn item_cond(id: i64, token_id: i64) -> Condition {
+    Condition::all()
+        .add(Expr::col(WatchlistItemIden::WatchlistId).eq(id))
+        .add(Expr::col(WatchlistItemIden::TokenId).eq(token_id))
+}
+// endregion: -- Watchlist Types
+
+// region: -- WatchlistBmc
+pub struct WatchlistBmc;
+
+impl DbBmc for WatchlistBmc {
+    const TABLE: &'static str = "watchlist";
+    const OWNED: bool = true;
+    const TIMESTAMPED: bool = true;
+    const AUDITED: bool = true;
+}
+
+impl WatchlistBmc {
+    pub async fn create(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        watchlist_c: WatchlistForCreate,
+    ) -> Result<i64> {
+        base::create::<Self, _>(ctx, mm, watchlist_c).await
+    }
+
+    pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Watchlist> {
+        base::get::<Self, _>(ctx, mm, id).await
+    }
+
+    pub async fn list(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<WatchlistFilter>>,
+        list_options: Option<ListOptions>,
+    ) -> Result<Vec<Watchlist>> {
+        base::list::<Self, _, _>(ctx, mm, filters, list_options).await
+    }
+
+    /// Paged list ({items, total, next_cursor}), see base::list_paged
+    pub async fn list_paged(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<WatchlistFilter>>,
+        list_options: Option<ListOptions>,
+        cursor: Option<String>,
+    ) -> Result<ListPage<Watchlist>> {
+        base::list_paged::<Self, _, _>(ctx, mm, filters, list_options, cursor).await
+    }
+
+    pub async fn update(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        watchlist_u: WatchlistForUpdate,
+    ) -> Result<()> {
+        base::update::<Self, _>(ctx, mm, id, watchlist_u).await
+    }
+
+    /// Delete the watchlist (its items are deleted by cascade)
+    pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
+        base::delete::<Self>(ctx, mm, id).await
+    }
+
+    /// Add the token to the watchlist (no-op when already in it). Returns the token id.
+    pub async fn add_token(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        token_ref: TokenRef,
+    ) -> Result<i64> {
+        // NOTE: The watchlist get checks the watchlist is the Ctx user's.
+        Self::get(ctx, mm, id).await?;
+        let token_id = TokenBmc::resolve_id(ctx, mm, token_ref).await?;
+
+        let mm = &audit::begin::<WatchlistItemBmc>(mm).await?;
+        let dbx = mm.dbx();
+
+        // -- Build query
+        let mut query = Query::insert();
+        query
+            .into_table(WatchlistItemIden::Table)
+            .columns([WatchlistItemIden::WatchlistId, WatchlistItemIden::TokenId])
+            .values([id.into(), token_id.into()])?
+            .on_conflict(
+                OnConflict::columns([WatchlistItemIden::WatchlistId, WatchlistItemIden::TokenId])
+                    .do_nothing()
+                    .to_owned(),
+            );
+
+        // -- Audit before
+        let before = audit::snapshot::<WatchlistItemBmc, _>(mm, item_cond(id, token_id)).await?;
+
+        // -- Exec query
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        dbx.execute(sqlx::query_with(&sql, values)).await?;
+
+        // -- Audit (nothing recorded when the item was already there)
+        let after = audit::snapshot::<WatchlistItemBmc, _>(mm, item_cond(id, token_id)).await?;
+        audit::record::<WatchlistItemBmc>(ctx, mm, before, after).await?;
+        audit::commit::<WatchlistItemBmc>(mm).await?;
+
+        Ok(token_id)
+    }
+
+    /// Remove the token from the watchlist. Returns the token id.
+    pub async fn remove_token(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        token_ref: TokenRef,
+    ) -> Result<i64> {
+        Self::get(ctx, mm, id).await?;
+        let token_id = TokenBmc::resolve_id(ctx, mm, token_ref).await?;
+
+        let mm = &audit::begin::<WatchlistItemBmc>(mm).await?;
+        let dbx = mm.dbx();
+
+        // -- Build query
+        let mut query = Query::delete();
+        query
+            .from_table(WatchlistItemIden::Table)
+            .cond_where(item_cond(id, token_id));
+
+        // -- Audit before
+        let before = audit::snapshot::<WatchlistItemBmc, _>(mm, item_cond(id, token_id)).await?;
+
+        // -- Exec query
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        let count = dbx.execute(sqlx::query_with(&sql, values)).await?;
+
+        if count == 0 {
+            return Err(Error::EntityNotFound {
+                entity: "watchlist_item",
+                id: token_id,
+            });
+        }
+
+        // -- Audit
+        audit::record::<WatchlistItemBmc>(ctx, mm, before, Vec::new()).await?;
+        audit::commit::<WatchlistItemBmc>(mm).await?;
+
+        Ok(token_id)
+    }
+
+    /// The watchlist tokens, in the order they were added.
+    pub async fn list_tokens(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Vec<Token>> {
+        Self::get(ctx, mm, id).await?;
+
+        let dbx = mm.dbx();
+
+        // -- Build query
+        // NOTE: The token columns are table qualified (watchlist_item also has id, ctime).
+        let mut query = Query::select();
+        query
+            .from(TokenBmc::table_ref())
+            .columns(
+                Token::field_idens()
+                    .into_iter()
+                    .map(|col| (SIden(TokenBmc::TABLE), col)),
+            )
+            .inner_join(
+                WatchlistItemIden::Table,
+                Expr::col((SIden(TokenBmc::TABLE), CommonIden::Id))
+                    .equals((WatchlistItemIden::Table, WatchlistItemIden::TokenId)),
+            )
+            .and_where(Expr::col((WatchlistItemIden::Table, WatchlistItemIden::WatchlistId)).eq(id))
+            // NOTE: Same token read scope as TokenBmc::get/list (and resolve_id)
+            .and_where_option(base::read_owner_cond::<TokenBmc>(ctx))
+            .order_by((WatchlistItemIden::Table, CommonIden::Id), Order::Asc);
+
+        // -- Exec query
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        let tokens = dbx
+            .fetch_all(sqlx::query_as_with::<_, Token, _>(&sql, values))
+            .await?;
+
+        Ok(tokens)
+    }
+}
+
+// endregion: -- WatchlistBmc
+
+// region: -- Tests
+#[cfg(test)]
+mod tests {
+    #![allow(unused)]
+    pub type Result<T> = core::result::Result<T, Error>;
+    pub type Error = Box<dyn std::error::Error>; // For early dev & tests.
+
+    use super::*;
+    use crate::_dev_utils;
+    use crate::model::audit::{AuditBmc, AuditInfo, AuditLogFilter};
+    use crate::model::token::TokenForCreate;
+    use serde_json::json;
+    use serial_test::serial;
+    use uuid::Uuid;
+
+    #[serial]
+    #[tokio::test]
+    async fn test_watchlist_tokens_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::new(1020)?;
+        let ctx_other = Ctx::new(1021)?;
+        // NOTE: The tokens are created by root, to check any token can be watched
+        // (same token visibility as TokenBmc::get/list).
+        let token_ids = TokenBmc::create_many(
+            &Ctx::root_ctx(),
+            &mm,
+            ["WL1", "WL2"]
+                .into_iter()
+                .map(|symbol| TokenForCreate {
+                    address: format!("test_watchlist_tokens_ok-{symbol}"),
+                    symbol: symbol.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+        )
+        .await?;
+        let id = WatchlistBmc::create(
+            &ctx,
+            &mm,
+            WatchlistForCreate {
+                name: "test_watchlist_tokens_ok".to_string(),
+            },
+        )
+        .await?;
+
+        // -- Exec & Check: add by address & id (the second add is a no-op)
+        WatchlistBmc::add_token(
+            &ctx,
+            &mm,
+            id,
+            TokenRef::Address("test_watchlist_tokens_ok-WL2".to_string()),
+        )
+        .await?;
+        WatchlistBmc::add_token(&ctx, &mm, id, TokenRef::TokenId(token_ids[0])).await?;
+        WatchlistBmc::add_token(&ctx, &mm, id, TokenRef::TokenId(token_ids[0])).await?;
+        let tokens = WatchlistBmc::list_tokens(&ctx, &mm, id).await?;
+        let symbols: Vec<&str> = tokens.iter().map(|t| t.symbol.as_str()).collect();
+        assert_eq!(symbols, ["WL2", "WL1"]);
+        for token in tokens.iter() {
+            assert_eq!(
+                TokenBmc::get(&ctx, &mm, token.id).await?.symbol,
+                token.symbol
+            );
+        }
+
+        // -- Exec & Check: remove
+        WatchlistBmc::remove_token(&ctx, &mm, id, TokenRef::TokenId(token_ids[1])).await?;
+        let tokens = WatchlistBmc::list_tokens(&ctx, &mm, id).await?;
+        assert_eq!(tokens.len(), 1);
+        let res = WatchlistBmc::remove_token(&ctx, &mm, id, TokenRef::TokenId(token_ids[1])).await;
+        assert!(
+            matches!(
+                res,
+                Err(crate::model::Error::EntityNotFound {
+                    entity: "watchlist_item",
+                    ..
+                })
+            ),
+            "should be EntityNotFound, but was {res:?}"
+        );
+
+        // -- Exec & Check: unknown address
+        let res = WatchlistBmc::add_token(
+            &ctx,
+            &mm,
+            id,
+            TokenRef::Address("test_watchlist_tokens_ok-none".to_string()),
+        )
+        .await;
+        assert!(
+            matches!(res, Err(crate::model::Error::TokenAddressNotFound { .. })),
+            "should be TokenAddressNotFound, but was {res:?}"
+        );
+
+        // -- Exec & Check: the watchlist of another user
+        let res = WatchlistBmc::list_tokens(&ctx_other, &mm, id).await;
+        assert!(
+            matches!(
+                res,
+                Err(crate::model::Error::EntityNotFound {
+                    entity: "watchlist",
+                    ..
+                })
+            ),
+            "should be EntityNotFound, but was {res:?}"
+        );
+
+        // -- Clean
+        WatchlistBmc::delete(&ctx, &mm, id).await?;
+        for token_id in token_ids {
+            TokenBmc::delete(&Ctx::root_ctx(), &mm, token_id).await?;
+        }
+
+        Ok(())
+    }
+    #[serial]
+    #[tokio::test]
+    async fn test_watchlist_tokens_audited_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::new(1020)?;
+        let root_ctx = Ctx::root_ctx();
+        let token_id = TokenBmc::create(
+            &root_ctx,
+            &mm,
+            TokenForCreate {
+                address: "test_watchlist_tokens_audited_ok-WL1".to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+        let id = WatchlistBmc::create(
+            &ctx,
+            &mm,
+            WatchlistForCreate {
+                name: "test_watchlist_tokens_audited_ok".to_string(),
+            },
+        )
+        .await?;
+        let mm_audit = mm.with_audit(AuditInfo {
+            rpc_method: "test_watchlist_tokens_audited_ok".to_string(),
+            req_uuid: Uuid::new_v4(),
+        });
+
+        // -- Exec: add (twice, the second is a no-op), remove
+        for _ in 0..2 {
+            WatchlistBmc::add_token(&ctx, &mm_audit, id, TokenRef::TokenId(token_id)).await?;
+        }
+        WatchlistBmc::remove_token(&ctx, &mm_audit, id, TokenRef::TokenId(token_id)).await?;
+
+        // -- Check
+        let filters: Vec<AuditLogFilter> = vec![serde_json::from_value(json!({
+            "entity": "watchlist_item",
+            "rpc_method": "test_watchlist_tokens_audited_ok"
+        }))?];
+        let logs = AuditBmc::list(&root_ctx, &mm, Some(filters), None).await?;
+        let actions: Vec<&str> = logs.iter().map(|log| log.action.as_str()).collect();
+        assert_eq!(actions, ["create", "delete"]);
+        assert!(logs.iter().all(|log| log.user_id == 1020));
+        let created = logs[0].after.as_ref().ok_or("should have after")?;
+        assert_eq!(created["watchlist_id"], id);
+        assert_eq!(created["token_id"], token_id);
+        let deleted = logs[1].before.as_ref().ok_or("should have before")?;
+        assert_eq!(deleted["watchlist_id"], id);
+        assert_eq!(deleted["token_id"], token_id);
+
+        // -- Clean
+        WatchlistBmc::delete(&ctx, &mm, id).await?;
+        TokenBmc::delete(&root_ctx, &mm, token_id).await?;
+
+        Ok(())
+    }
+}
+// endregion: -- Tests
@@ -0,0 +1,110 @@
+use crate::params::{ListResult, ParamsForCreate, ParamsForUpdate, ParamsIdOnly, ParamsList};
+use crate::router::RpcRouter;
+use crate::Result;
+use lib_core::ctx::Ctx;
+use lib_core::model::alert::{
+    AlertEvent, AlertEventBmc, AlertEventFilter, AlertRule, AlertRuleBmc, AlertRuleFilter,
+    AlertRuleForCreate, AlertRuleForUpdate,
+};
+use lib_core::model::ModelManager;
+
+// NOTE: The alert rules and events are scoped to the Ctx user (OWNED).
+// The rules are evaluated on the token writes (see AlertRuleBmc::evaluate),
+// so there is no rpc to fire them.
+
+pub fn rpc_router() -> RpcRouter {
+    RpcRouter::new()
+        .add("create_alert_rule", create_alert_rule)
+        .add("get_alert_rule", get_alert_rule)
+        .add("list_alert_rules", list_alert_rules)
+        .add("update_alert_rule", update_alert_rule)
+        .add("delete_alert_rule", delete_alert_rule)
+        .add("list_alert_events", list_alert_events)
+}
+
+pub async fn create_alert_rule(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsForCreate<AlertRuleForCreate>,
+) -> Result<AlertRule> {
+    let ParamsForCreate { data } = params;
+
+    let id = AlertRuleBmc::create(&ctx, &mm, data).await?;
+    let rule = AlertRuleBmc::get(&ctx, &mm, id).await?;
+
+    Ok(rule)
+}
+
+pub async fn get_alert_rule(ctx: Ctx, mm: ModelManager, params: ParamsIdOnly) -> Result<AlertRule> {
+    let ParamsIdOnly { id } = params;
+
+    let rule = AlertRuleBmc::get(&ctx, &mm, id).await?;
+
+    Ok(rule)
+}
+
+pub async fn list_alert_rules(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsList<AlertRuleFilter>,
+) -> Result<Vec<AlertRule>> {
+    let rules = AlertRuleBmc::list(&ctx, &mm, params.filters, params.list_options).await?;
+
+    Ok(rules)
+}
+
+pub async fn update_alert_rule(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsForUpdate<AlertRuleForUpdate>,
+) -> Result<AlertRule> {
+    let ParamsForUpdate { id, data } = params;
+
+    AlertRuleBmc::update(&ctx, &mm, id, data).await?;
+
+    let rule = AlertRuleBmc::get(&ctx, &mm, id).await?;
+
+    Ok(rule)
+}
+
+pub async fn delete_alert_rule(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsIdOnly,
+) -> Result<AlertRule> {
+    let ParamsIdOnly { id } = params;
+
+    // NOTE: get + delete in one txn, so the returned rule is the deleted one
+    let mm = mm.new_with_txn()?;
+    mm.begin_txn().await?;
+
+    let rule = AlertRuleBmc::get(&ctx, &mm, id).await?;
+    AlertRuleBmc::delete(&ctx, &mm, id).await?;
+
+    mm.commit_txn().await?;
+
+    Ok(rule)
+}
+
+/// The fired alerts of the Ctx user (e.g., `{"filters": {"rule_id": 1000}, "paged": true}`)
+pub async fn list_alert_events(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsList<AlertEventFilter>,
+) -> Result<ListResult<AlertEvent>> {
+    if params.is_paged() {
+        let page = AlertEventBmc::list_paged(
+            &ctx,
+            &mm,
+            params.filters,
+            params.list_options,
+            params.cursor,
+        )
+        .await?;
+        return Ok(ListResult::Page(page));
+    }
+
+    let events = AlertEventBmc::list(&ctx, &mm, params.filters, params.list_options).await?;
+
+    Ok(ListResult::Items(events))
+}
@@ -0,0 +1,35 @@
+use crate::params::{ListResult, ParamsList};
+use crate::router::RpcRouter;
+use crate::Result;
+use lib_core::ctx::{Ctx, Role};
+use lib_core::model::audit::{AuditBmc, AuditLog, AuditLogFilter};
+use lib_core::model::ModelManager;
+
+// NOTE: The audit log is written by the model layer on each audited write
+// (see lib_core::model::audit), so it only has an admin read method.
+pub fn rpc_router() -> RpcRouter {
+    RpcRouter::new().add_with_role("list_audit_log", Role::Admin, list_audit_log)
+}
+
+/// E.g., `{"filters": {"user_id": 1000, "entity": "task", "ctime": {"$gte": "2024-03-14T00:00:00Z"}}}`
+pub async fn list_audit_log(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsList<AuditLogFilter>,
+) -> Result<ListResult<AuditLog>> {
+    if params.is_paged() {
+        let page = AuditBmc::list_paged(
+            &ctx,
+            &mm,
+            params.filters,
+            params.list_options,
+            params.cursor,
+        )
+        .await?;
+        return Ok(ListResult::Page(page));
+    }
+
+    let logs = AuditBmc::list(&ctx, &mm, params.filters, params.list_options).await?;
+
+    Ok(ListResult::Items(logs))
+}
@@ -1,4 +1,5 @@
 use derive_more::From;
+use lib_core::ctx::{self, Role};
 use lib_core::model;
 use serde::Serialize;
 use serde_with::{serde_as, DisplayFromStr};
@@ -26,6 +27,10 @@ pub type Result<T> = core::result::Result<T, Error>;
 #[serde(tag = "type", content = "data")]
 pub enum Error {
     // -- RPC
+    // NOTE: Request object not JSON-RPC 2.0 compliant (e.g., wrong "jsonrpc" version)
+    RpcRequestInvalid {
+        reason: String,
+    },
     RpcMethodUnknown(String),
     RpcMissingParams {
         rpc_method: String,
@@ -33,6 +38,11 @@ pub enum Error {
     RpcFailJsonParams {
         rpc_method: String,
     },
+    // NOTE: The Ctx role is below the role required by the rpc method
+    RpcAccessDenied {
+        rpc_method: String,
+        required_role: Role,
+    },
 
     // -- Login
     LoginFail,
@@ -50,6 +60,8 @@ pub enum Error {
 
     // -- Modules
     #[from]
+    Ctx(ctx::Error),
+    #[from]
     Model(model::Error),
 
     // -- External Modules
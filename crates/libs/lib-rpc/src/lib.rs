@@ -1,97 +1,109 @@
 // region:       -- Modules
 
+mod alert_rpc;
+mod audit_rpc;
 mod error;
 mod params;
+mod router;
 mod task_rpc;
 mod token_rpc;
+mod user_rpc;
+mod watchlist_rpc;
 
 pub use self::error::{Error, Result};
+pub use self::router::{RpcHandler, RpcRouter};
 
-use lib_core::ctx::Ctx;
-use lib_core::model::ModelManager;
-use serde::Deserialize;
-use serde_json::{from_value, to_value, Value};
-use task_rpc::{create_task, delete_task, list_tasks, update_task};
-use token_rpc::{create_token, delete_token, list_tokens, update_token};
+use serde_json::{json, Value};
 
 // endregion:    -- Modules
 
 // region:       -- RPC Types
 
+pub const JSONRPC_VERSION: &str = "2.0";
+
 /// The raw JSON-RPC Request Body object. Foundation for RPC routing.
 // NOTE: At this level we'll just use a generic JSON Value type,
 // but we'll do the actual parsing at the RPC routing level.
-#[derive(Deserialize)]
+// NOTE: U: JSON-RPC 2.0 - A request without an "id" member is a notification
+// (no response), but an explicit `"id": null` is NOT. Since serde would
+// deserialize both as None, we parse with RpcRequest::from_value() instead.
 pub struct RpcRequest {
+    /// None when the "id" member is absent (notification),
+    /// Some(Value::Null) when explicitly null.
     pub id: Option<Value>,
     pub method: String,
     pub params: Option<Value>,
 }
 
-// endregion:    -- RPC Types
+impl RpcRequest {
+    /// Parse and validate a JSON-RPC 2.0 request object.
+    // NOTE: The "jsonrpc" member is optional for backward compatibility,
+    // but when present it must be "2.0".
+    pub fn from_value(value: Value) -> Result<Self> {
+        let Value::Object(mut obj) = value else {
+            return Err(invalid_request("request must be a JSON object"));
+        };
+
+        match obj.remove("jsonrpc") {
+            None => (),
+            Some(Value::String(version)) if version == JSONRPC_VERSION => (),
+            Some(_) => return Err(invalid_request("jsonrpc version must be \"2.0\"")),
+        }
+
+        let id = obj.remove("id");
+        if !matches!(
+            id,
+            None | Some(Value::Null) | Some(Value::String(_)) | Some(Value::Number(_))
+        ) {
+            return Err(invalid_request("id must be a string, number or null"));
+        }
+
+        let Some(Value::String(method)) = obj.remove("method") else {
+            return Err(invalid_request("method must be a string"));
+        };
+
+        let params = match obj.remove("params") {
+            None | Some(Value::Null) => None,
+            Some(params @ (Value::Object(_) | Value::Array(_))) => Some(params),
+            Some(_) => return Err(invalid_request("params must be an object or array")),
+        };
 
-// NOTE: Using proc macro to refactor our _rpc_handler to be
-// more general and robust for additional entity types later on.
-// REF: https://youtu.be/3cA_mk4vdWY?t=13160
-macro_rules! exec_rpc_fn {
-    // -- With Params (eg. create_task(ctx, mm, params))
-    // NOTE: !! - Need to wrap with another layer of {} because the macro
-    // will need to generate the code block {} in order for the
-    // "match" statement in _rpc_handler to work. Specifically, the match will
-    // expect a code block with {} because this logic isn't a one-liner,
-    // hence the need to use/add {}s.
-    ($rpc_fn:expr, $ctx:expr, $mm:expr, $rpc_params:expr) => {{
-        // NOTE: TIP: Use stringify!($rpc_fn) to get a string
-        let rpc_fn_name = stringify!($rpc_fn);
-
-        // Convert our rpc_params Option<Value> into a Result. This ensures
-        // that we have params that are JSON Value type.
-        let params = $rpc_params.ok_or(Error::RpcMissingParams {
-            rpc_method: rpc_fn_name.to_string(),
-        })?;
-        // We want a TaskForCreate type so we use serde_json::from_value()
-        let params = from_value(params).map_err(|_| Error::RpcFailJsonParams {
-            rpc_method: rpc_fn_name.to_string(),
-        })?;
-
-        // We want this in the end, but we first need to get
-        // RPC params into ParamsForCreate<TaskForCreate> type
-
-        $rpc_fn($ctx, $mm, params).await.map(to_value)??
-    }};
-
-    // -- Without Params (eg. list_tasks(ctx, mm))
-    ($rpc_fn:expr, $ctx:expr, $mm:expr) => {
-        $rpc_fn($ctx, $mm).await.map(to_value)??
-    };
+        Ok(RpcRequest { id, method, params })
+    }
+
+    /// A request without an "id" member is a notification (no response expected).
+    pub fn is_notification(&self) -> bool {
+        self.id.is_none()
+    }
 }
 
-// NOTE: U: Multi-crate workspace moved rpc_handler and _rpc_handler fns
-// to the web-server. Adding a new helper fn here to compliment the
-// proc macro. This is basically the old _rpc_handler but renamed.
-pub async fn exec_rpc(ctx: Ctx, mm: ModelManager, rpc_req: RpcRequest) -> Result<Value> {
-    let rpc_method = rpc_req.method;
-    let rpc_params = rpc_req.params;
-
-    // -- Exec & store RpcInfo into response
-    let result_json: Value = match rpc_method.as_str() {
-        // -- Task RPC methods
-        "create_task" => exec_rpc_fn!(create_task, ctx, mm, rpc_params),
-        "list_tasks" => {
-            // NOTE: TIP: When first building a function, can add variables to debug,
-            // and then remove afterwards: let r = list_tasks() + todo!()
-            // NOTE: Using serde_json::to_value() returns a serde_json::Error,
-            // but we want a web::Error instead, so we need to add a new
-            // web::Error variant (SerdeJson(String)) and allow the conversion
-            // by impl From<serde_json::Error> for Error {}
-            exec_rpc_fn!(list_tasks, ctx, mm, rpc_params)
-        }
-        "update_task" => exec_rpc_fn!(update_task, ctx, mm, rpc_params),
-        "delete_task" => exec_rpc_fn!(delete_task, ctx, mm, rpc_params),
+fn invalid_request(reason: &str) -> Error {
+    Error::RpcRequestInvalid {
+        reason: reason.to_string(),
+    }
+}
 
-        // -- Fallback as Err.
-        _ => return Err(Error::RpcMethodUnknown(rpc_method)),
-    };
+/// Build a JSON-RPC 2.0 success response object
+pub fn rpc_response_ok(id: Option<Value>, result: Value) -> Value {
+    json!({
+        "jsonrpc": JSONRPC_VERSION,
+        "id": id,
+        "result": result
+    })
+}
+
+// endregion:    -- RPC Types
 
-    Ok(result_json)
+// NOTE: U: Replaced the exec_rpc() match + exec_rpc_fn! macro with a declarative
+// RpcRouter. Each rpc module now registers its own methods (see task_rpc::rpc_router()),
+// so adding a new entity no longer means editing this core dispatcher.
+/// All the application rpc methods merged into one RpcRouter.
+pub fn all_rpc_router() -> RpcRouter {
+    RpcRouter::new()
+        .extend(alert_rpc::rpc_router())
+        .extend(audit_rpc::rpc_router())
+        .extend(task_rpc::rpc_router())
+        .extend(token_rpc::rpc_router())
+        .extend(user_rpc::rpc_router())
+        .extend(watchlist_rpc::rpc_router())
 }
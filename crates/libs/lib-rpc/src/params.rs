@@ -5,8 +5,9 @@
 //! each rpc handler function to receive the exact desired type.
 //!
 
+use lib_core::model::ListPage;
 use modql::filter::ListOptions;
-use serde::{de::DeserializeOwned, Deserialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_with::{serde_as, OneOrMany};
 
 #[derive(Deserialize)]
@@ -26,6 +27,18 @@ pub struct ParamsIdOnly {
     pub id: i64,
 }
 
+// Only for the bulk (many) updates/deletes. The filters are required
+// (one or many), so an empty request can't target all the rows.
+#[serde_as]
+#[derive(Deserialize)]
+pub struct ParamsFilters<F>
+where
+    F: DeserializeOwned,
+{
+    #[serde_as(deserialize_as = "OneOrMany<_>")]
+    pub filters: Vec<F>,
+}
+
 // NOTE: We need Deserialize since this is going to come from our
 // JSON-RPC calls, which has to be deserialized from JSON. We'll
 // add a 'params: ParamsList' parameter to our task_rpc::list_tasks()
@@ -41,4 +54,26 @@ where
     #[serde_as(deserialize_as = "Option<OneOrMany<_>>")]
     pub filters: Option<Vec<F>>,
     pub list_options: Option<ListOptions>,
+    // NOTE: U: Opt-in paged response ({items, total, next_cursor}) instead of
+    // the plain array. A cursor (next_cursor of the previous page) implies paged.
+    #[serde(default)]
+    pub paged: bool,
+    pub cursor: Option<String>,
+}
+
+impl<F> ParamsList<F>
+where
+    F: DeserializeOwned,
+{
+    pub fn is_paged(&self) -> bool {
+        self.paged || self.cursor.is_some()
+    }
+}
+
+/// The list rpc result: the plain array, or the page when `ParamsList` is paged
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum ListResult<E> {
+    Items(Vec<E>),
+    Page(ListPage<E>),
 }
@@ -0,0 +1,287 @@
+//! Declarative RPC router.
+//!
+//! Each rpc module (e.g., `task_rpc`, `token_rpc`) exposes its own `RpcRouter`
+//! by registering its handler functions by method name:
+//!
+//! ```ignore
+//! RpcRouter::new()
+//!     .add("create_task", create_task)
+//!     .add("list_tasks", list_tasks)
+//! ```
+//!
+//! Methods that need a minimum `Role` (e.g., admin only) are registered with
+//! `.add_with_role("delete_token", Role::Admin, delete_token)`.
+//!
+//! Routers can then be merged with `RpcRouter::extend` (like Axum's `Router::merge`),
+//! and the web layer only has to call `rpc_router.call(ctx, mm, rpc_req)`.
+
+// region:       -- Modules
+
+mod rpc_handler;
+mod rpc_handler_wrapper;
+
+pub use self::rpc_handler::RpcHandler;
+pub use self::rpc_handler_wrapper::{PinFutureValue, RpcHandlerWrapper, RpcHandlerWrapperTrait};
+
+use crate::{Error, Result, RpcRequest};
+use lib_core::ctx::{Ctx, Role};
+use lib_core::model::ModelManager;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// endregion:    -- Modules
+
+/// Method name to type-erased rpc handler (and its required role) map.
+// NOTE: Handlers are behind an Arc so the RpcRouter is cheap to Clone
+// (e.g., when used as Axum State).
+#[derive(Clone, Default)]
+pub struct RpcRouter {
+    route_by_name: HashMap<&'static str, RpcRoute>,
+}
+
+#[derive(Clone)]
+struct RpcRoute {
+    handler: Arc<dyn RpcHandlerWrapperTrait>,
+    // NOTE: None means any authenticated Ctx can call the method
+    required_role: Option<Role>,
+}
+
+impl RpcRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a handler function for a given rpc method name.
+    /// e.g., `.add("create_task", create_task)`
+    pub fn add<F, P, R>(self, name: &'static str, handler: F) -> Self
+    where
+        F: RpcHandler<P, R> + Send + Sync + 'static,
+        P: 'static,
+        R: 'static,
+    {
+        self.add_dyn(name, None, handler.into_dyn())
+    }
+
+    /// Add a handler function that requires the Ctx to have at least `role`.
+    /// e.g., `.add_with_role("delete_token", Role::Admin, delete_token)`
+    pub fn add_with_role<F, P, R>(self, name: &'static str, role: Role, handler: F) -> Self
+    where
+        F: RpcHandler<P, R> + Send + Sync + 'static,
+        P: 'static,
+        R: 'static,
+    {
+        self.add_dyn(name, Some(role), handler.into_dyn())
+    }
+
+    /// Add an already type-erased handler (see `RpcHandler::into_dyn`).
+    pub fn add_dyn(
+        mut self,
+        name: &'static str,
+        required_role: Option<Role>,
+        dyn_handler: Arc<dyn RpcHandlerWrapperTrait>,
+    ) -> Self {
+        self.route_by_name.insert(
+            name,
+            RpcRoute {
+                handler: dyn_handler,
+                required_role,
+            },
+        );
+        self
+    }
+
+    /// Merge another RpcRouter into this one.
+    /// NOTE: Same method names in `other` will override the ones in `self`.
+    pub fn extend(mut self, other: RpcRouter) -> Self {
+        self.route_by_name.extend(other.route_by_name);
+        self
+    }
+
+    /// Route the RpcRequest to its handler and return the JSON result.
+    pub async fn call(&self, ctx: Ctx, mm: ModelManager, rpc_req: RpcRequest) -> Result<Value> {
+        let RpcRequest { method, params, .. } = rpc_req;
+
+        let Some(route) = self.route_by_name.get(method.as_str()) else {
+            return Err(Error::RpcMethodUnknown(method));
+        };
+
+        // -- Check the method permission
+        if let Some(required_role) = route.required_role {
+            if !ctx.has_role(required_role) {
+                return Err(Error::RpcAccessDenied {
+                    rpc_method: method,
+                    required_role,
+                });
+            }
+        }
+
+        route.handler.call(ctx, mm, method, params).await
+    }
+}
+
+// region:       -- Tests
+#[cfg(test)]
+mod tests {
+    #![allow(unused)]
+    pub type Result<T> = core::result::Result<T, Error>;
+    pub type Error = Box<dyn std::error::Error>; // For early dev & tests.
+
+    use super::*;
+    use crate::params::ParamsIdOnly;
+    use lib_core::_dev_utils;
+    use serde_json::json;
+
+    // -- Fixture handlers (no db access, so the ModelManager has no db, see init_test_no_db)
+    async fn fx_get_id(_ctx: Ctx, _mm: ModelManager, params: ParamsIdOnly) -> crate::Result<i64> {
+        Ok(params.id)
+    }
+
+    async fn fx_ping(_ctx: Ctx, _mm: ModelManager) -> crate::Result<&'static str> {
+        Ok("pong")
+    }
+
+    fn fx_rpc_req(method: &str, params: Option<Value>) -> RpcRequest {
+        RpcRequest {
+            id: Some(json!(1)),
+            method: method.to_string(),
+            params,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_router_call_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test_no_db();
+        let ctx = Ctx::root_ctx();
+        let rpc_router = RpcRouter::new()
+            .add("fx_get_id", fx_get_id)
+            .add("fx_ping", fx_ping);
+
+        // -- Exec
+        let with_params = rpc_router
+            .call(
+                ctx.clone(),
+                mm.clone(),
+                fx_rpc_req("fx_get_id", Some(json!({"id": 123}))),
+            )
+            .await?;
+        let without_params = rpc_router
+            .call(ctx, mm, fx_rpc_req("fx_ping", None))
+            .await?;
+
+        // -- Check
+        assert_eq!(with_params, json!(123));
+        assert_eq!(without_params, json!("pong"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_router_extend_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test_no_db();
+        let ctx = Ctx::root_ctx();
+        let rpc_router = RpcRouter::new()
+            .add("fx_get_id", fx_get_id)
+            .extend(RpcRouter::new().add("fx_ping", fx_ping));
+
+        // -- Exec
+        let res = rpc_router
+            .call(ctx, mm, fx_rpc_req("fx_ping", None))
+            .await?;
+
+        // -- Check
+        assert_eq!(res, json!("pong"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_router_err_method_unknown() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test_no_db();
+        let ctx = Ctx::root_ctx();
+        let rpc_router = RpcRouter::new().add("fx_ping", fx_ping);
+
+        // -- Exec
+        let res = rpc_router
+            .call(ctx, mm, fx_rpc_req("fx_unknown", None))
+            .await;
+
+        // -- Check
+        assert!(
+            matches!(&res, Err(crate::Error::RpcMethodUnknown(method)) if method == "fx_unknown"),
+            "RpcMethodUnknown not matching"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_router_err_params() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test_no_db();
+        let ctx = Ctx::root_ctx();
+        let rpc_router = RpcRouter::new().add("fx_get_id", fx_get_id);
+
+        // -- Exec
+        let res_missing = rpc_router
+            .call(ctx.clone(), mm.clone(), fx_rpc_req("fx_get_id", None))
+            .await;
+        let res_fail_json = rpc_router
+            .call(
+                ctx,
+                mm,
+                fx_rpc_req("fx_get_id", Some(json!({"id": "not-a-number"}))),
+            )
+            .await;
+
+        // -- Check
+        assert!(
+            matches!(res_missing, Err(crate::Error::RpcMissingParams { .. })),
+            "RpcMissingParams not matching"
+        );
+        assert!(
+            matches!(res_fail_json, Err(crate::Error::RpcFailJsonParams { .. })),
+            "RpcFailJsonParams not matching"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_router_err_access_denied() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test_no_db();
+        let rpc_router = RpcRouter::new().add_with_role("fx_ping", Role::Admin, fx_ping);
+
+        // -- Exec
+        let user_res = rpc_router
+            .call(Ctx::new(1000)?, mm.clone(), fx_rpc_req("fx_ping", None))
+            .await;
+        let admin_res = rpc_router
+            .call(
+                Ctx::new_with_role(1000, Role::Admin)?,
+                mm,
+                fx_rpc_req("fx_ping", None),
+            )
+            .await?;
+
+        // -- Check
+        assert!(
+            matches!(
+                user_res,
+                Err(crate::Error::RpcAccessDenied {
+                    required_role: Role::Admin,
+                    ..
+                })
+            ),
+            "RpcAccessDenied not matching"
+        );
+        assert_eq!(admin_res, json!("pong"));
+
+        Ok(())
+    }
+}
+// endregion:    -- Tests
@@ -0,0 +1,97 @@
+use crate::router::{PinFutureValue, RpcHandlerWrapper, RpcHandlerWrapperTrait};
+use crate::{Error, Result};
+use futures::Future;
+use lib_core::ctx::Ctx;
+use lib_core::model::ModelManager;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{from_value, to_value, Value};
+use std::sync::Arc;
+
+/// The `Handler` trait that will be implemented by rpc handler functions.
+///
+/// Key points:
+/// - Rpc handler functions are asynchronous, thus returning a Future of Result<Value>.
+/// - The call format is normalized to `ctx`, `mm`, and optional `params` (JSON Value),
+///   which is deserialized into the handler's params type `P` before the call.
+/// - The handler's `Result<R>` is serialized back into a JSON Value.
+// NOTE: The same "handler trait magic" as Axum's Handler, where the trait is
+// implemented for any function matching one of the supported signatures.
+// The generic `P` is a marker to differentiate the signatures (w/ or w/o params).
+pub trait RpcHandler<P, R>: Clone {
+    /// The type of future calling this handler returns.
+    type Future: Future<Output = Result<Value>> + Send + 'static;
+
+    /// Call the handler.
+    fn call(
+        self,
+        ctx: Ctx,
+        mm: ModelManager,
+        rpc_method: String,
+        params: Option<Value>,
+    ) -> Self::Future;
+
+    /// Convert this RpcHandler into a type-erased trait object (to be stored in the RpcRouter).
+    fn into_dyn(self) -> Arc<dyn RpcHandlerWrapperTrait>
+    where
+        Self: Sized + Send + Sync + 'static,
+        P: 'static,
+        R: 'static,
+    {
+        Arc::new(RpcHandlerWrapper::new(self))
+    }
+}
+
+// -- Without Params (e.g., `list_all(ctx, mm)`)
+impl<F, Fut, R> RpcHandler<(), R> for F
+where
+    F: FnOnce(Ctx, ModelManager) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = Result<R>> + Send,
+    R: Serialize,
+{
+    type Future = PinFutureValue;
+
+    fn call(
+        self,
+        ctx: Ctx,
+        mm: ModelManager,
+        _rpc_method: String,
+        _params: Option<Value>,
+    ) -> Self::Future {
+        Box::pin(async move {
+            let result = self(ctx, mm).await?;
+            Ok(to_value(result)?)
+        })
+    }
+}
+
+// -- With Params (e.g., `create_task(ctx, mm, params)`)
+impl<F, Fut, P, R> RpcHandler<(P,), R> for F
+where
+    F: FnOnce(Ctx, ModelManager, P) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = Result<R>> + Send,
+    P: DeserializeOwned + Send,
+    R: Serialize,
+{
+    type Future = PinFutureValue;
+
+    fn call(
+        self,
+        ctx: Ctx,
+        mm: ModelManager,
+        rpc_method: String,
+        params: Option<Value>,
+    ) -> Self::Future {
+        Box::pin(async move {
+            // NOTE: Same params handling as the former exec_rpc_fn! macro.
+            let params = params.ok_or_else(|| Error::RpcMissingParams {
+                rpc_method: rpc_method.clone(),
+            })?;
+            let params: P =
+                from_value(params).map_err(|_| Error::RpcFailJsonParams { rpc_method })?;
+
+            let result = self(ctx, mm, params).await?;
+            Ok(to_value(result)?)
+        })
+    }
+}
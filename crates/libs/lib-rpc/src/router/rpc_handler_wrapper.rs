@@ -0,0 +1,80 @@
+use crate::router::RpcHandler;
+use crate::Result;
+use futures::Future;
+use lib_core::ctx::Ctx;
+use lib_core::model::ModelManager;
+use serde_json::Value;
+use std::marker::PhantomData;
+use std::pin::Pin;
+
+/// The boxed future returned by any type-erased rpc handler
+pub type PinFutureValue = Pin<Box<dyn Future<Output = Result<Value>> + Send>>;
+
+/// `RpcHandlerWrapper` is a `RpcHandler` wrapper which implements
+/// `RpcHandlerWrapperTrait` for type erasure, enabling dynamic dispatch
+/// (i.e., storing handlers of different signatures in the same `RpcRouter` map).
+// NOTE: PhantomData<fn() -> (P, R)> so the wrapper does not require P and R
+// to be Send + Sync (we never hold a P or R, we only need them as markers).
+#[derive(Clone)]
+pub struct RpcHandlerWrapper<H, P, R> {
+    handler: H,
+    _marker: PhantomData<fn() -> (P, R)>,
+}
+
+impl<H, P, R> RpcHandlerWrapper<H, P, R> {
+    pub fn new(handler: H) -> Self {
+        Self {
+            handler,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<H, P, R> RpcHandlerWrapper<H, P, R>
+where
+    H: RpcHandler<P, R> + Send + Sync + 'static,
+    P: 'static,
+    R: 'static,
+{
+    pub fn call(
+        &self,
+        ctx: Ctx,
+        mm: ModelManager,
+        rpc_method: String,
+        params: Option<Value>,
+    ) -> H::Future {
+        // NOTE: Since handler is a FnOnce, we can use it only once, so we clone it.
+        // This is likely optimized by the compiler (handlers are plain fns).
+        let handler = self.handler.clone();
+        RpcHandler::call(handler, ctx, mm, rpc_method, params)
+    }
+}
+
+/// `RpcHandlerWrapperTrait` enables `RpcHandlerWrapper` to become a trait object,
+/// allowing for dynamic dispatch.
+pub trait RpcHandlerWrapperTrait: Send + Sync {
+    fn call(
+        &self,
+        ctx: Ctx,
+        mm: ModelManager,
+        rpc_method: String,
+        params: Option<Value>,
+    ) -> PinFutureValue;
+}
+
+impl<H, P, R> RpcHandlerWrapperTrait for RpcHandlerWrapper<H, P, R>
+where
+    H: RpcHandler<P, R> + Clone + Send + Sync + 'static,
+    P: 'static,
+    R: 'static,
+{
+    fn call(
+        &self,
+        ctx: Ctx,
+        mm: ModelManager,
+        rpc_method: String,
+        params: Option<Value>,
+    ) -> PinFutureValue {
+        Box::pin(self.call(ctx, mm, rpc_method, params))
+    }
+}
@@ -1,8 +1,11 @@
+use crate::params::{
+    ListResult, ParamsFilters, ParamsForCreate, ParamsForUpdate, ParamsIdOnly, ParamsList,
+};
+use crate::router::RpcRouter;
+use crate::Result;
 use lib_core::ctx::Ctx;
 use lib_core::model::task::{Task, TaskBmc, TaskFilter, TaskForCreate, TaskForUpdate};
 use lib_core::model::ModelManager;
-use crate::params::{ParamsForCreate, ParamsForUpdate, ParamsIdOnly, ParamsList};
-use crate::Result;
 
 // NOTE: !! - Our design is as follows: Our ModelController (TaskBmc)
 // will be very granular and will only return the id (TaskBmc::create -> Result<i64>).
@@ -10,6 +13,17 @@ use crate::Result;
 // It's these functions that directly correspond to the JSON-RPC methods.
 // Eg: /api/rpc => RpcRequest => RpcRequest.method => "list_tasks" => task_rpc::list_tasks();
 
+pub fn rpc_router() -> RpcRouter {
+    RpcRouter::new()
+        .add("create_task", create_task)
+        .add("get_task", get_task)
+        .add("list_tasks", list_tasks)
+        .add("update_task", update_task)
+        .add("delete_task", delete_task)
+        .add("create_tasks", create_tasks)
+        .add("delete_tasks", delete_tasks)
+}
+
 pub async fn create_task(
     // NOTE: This is end of line for Ctx and MM, so we're consuming
     // them both but we could pass references if we wanted.
@@ -25,14 +39,34 @@ pub async fn create_task(
     Ok(task)
 }
 
+pub async fn get_task(ctx: Ctx, mm: ModelManager, params: ParamsIdOnly) -> Result<Task> {
+    let ParamsIdOnly { id } = params;
+
+    let task = TaskBmc::get(&ctx, &mm, id).await?;
+
+    Ok(task)
+}
+
 pub async fn list_tasks(
     ctx: Ctx,
     mm: ModelManager,
     params: ParamsList<TaskFilter>,
-) -> Result<Vec<Task>> {
+) -> Result<ListResult<Task>> {
+    if params.is_paged() {
+        let page = TaskBmc::list_paged(
+            &ctx,
+            &mm,
+            params.filters,
+            params.list_options,
+            params.cursor,
+        )
+        .await?;
+        return Ok(ListResult::Page(page));
+    }
+
     let tasks = TaskBmc::list(&ctx, &mm, params.filters, params.list_options).await?;
 
-    Ok(tasks)
+    Ok(ListResult::Items(tasks))
 }
 
 pub async fn update_task(
@@ -52,9 +86,42 @@ pub async fn update_task(
 pub async fn delete_task(ctx: Ctx, mm: ModelManager, params: ParamsIdOnly) -> Result<Task> {
     let ParamsIdOnly { id } = params;
 
+    // NOTE: get + delete in one txn, so the returned task is the deleted one
+    let mm = mm.new_with_txn()?;
+    mm.begin_txn().await?;
+
     let task = TaskBmc::get(&ctx, &mm, id).await?;
     TaskBmc::delete(&ctx, &mm, id).await?;
 
+    mm.commit_txn().await?;
+
     Ok(task)
 }
 
+// region: -- Bulk (many)
+/// Create all the tasks in one statement. Returns the new ids.
+pub async fn create_tasks(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsForCreate<Vec<TaskForCreate>>,
+) -> Result<Vec<i64>> {
+    let ParamsForCreate { data } = params;
+
+    let ids = TaskBmc::create_many(&ctx, &mm, data).await?;
+
+    Ok(ids)
+}
+
+/// Delete all the tasks matching the filters. Returns the deleted ids.
+pub async fn delete_tasks(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsFilters<TaskFilter>,
+) -> Result<Vec<i64>> {
+    let ParamsFilters { filters } = params;
+
+    let ids = TaskBmc::delete_many(&ctx, &mm, filters).await?;
+
+    Ok(ids)
+}
+// endregion: -- Bulk (many)
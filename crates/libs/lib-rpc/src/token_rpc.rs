@@ -1,8 +1,17 @@
-use crate::params::{ParamsForCreate, ParamsForUpdate, ParamsIdOnly, ParamsList};
+use crate::params::{ListResult, ParamsForCreate, ParamsForUpdate, ParamsIdOnly, ParamsList};
+use crate::router::RpcRouter;
 use crate::Result;
-use lib_core::ctx::Ctx;
-use lib_core::model::token::{Token, TokenBmc, TokenFilter, TokenForCreate, TokenForUpdate};
+use lib_core::ctx::{Ctx, Role};
+use lib_core::model::token::{
+    MoverDirection, Token, TokenBmc, TokenFilter, TokenForCreate, TokenForUpdate, TokenRank,
+    TokenStats,
+};
+use lib_core::model::token_snapshot::{
+    HistoryBucket, TokenSnapshot, TokenSnapshotBmc, TokenSnapshotBucket,
+};
 use lib_core::model::ModelManager;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, OneOrMany};
 
 // NOTE: !! - Our design is as follows: Our ModelController (TokenBmc)
 // will be very granular and will only return the id (TokenBmc::create -> Result<i64>).
@@ -10,6 +19,20 @@ use lib_core::model::ModelManager;
 // It's these functions that directly correspond to the JSON-RPC methods.
 // Eg: /api/rpc => RpcRequest => RpcRequest.method => "list_tokens" => token_rpc::list_tokens();
 
+pub fn rpc_router() -> RpcRouter {
+    RpcRouter::new()
+        .add("get_token", get_token)
+        .add("list_tokens", list_tokens)
+        .add("get_token_history", get_token_history)
+        .add("token_top_movers", token_top_movers)
+        .add("token_rank", token_rank)
+        .add("token_stats", token_stats)
+        // NOTE: Tokens are shared market data, so only admins can write them
+        .add_with_role("create_token", Role::Admin, create_token)
+        .add_with_role("update_token", Role::Admin, update_token)
+        .add_with_role("delete_token", Role::Admin, delete_token)
+}
+
 pub async fn create_token(
     // NOTE: This is end of line for Ctx and MM, so we're consuming
     // them both but we could pass references if we wanted.
@@ -25,14 +48,34 @@ pub async fn create_token(
     Ok(token)
 }
 
+pub async fn get_token(ctx: Ctx, mm: ModelManager, params: ParamsIdOnly) -> Result<Token> {
+    let ParamsIdOnly { id } = params;
+
+    let token = TokenBmc::get(&ctx, &mm, id).await?;
+
+    Ok(token)
+}
+
 pub async fn list_tokens(
     ctx: Ctx,
     mm: ModelManager,
     params: ParamsList<TokenFilter>,
-) -> Result<Vec<Token>> {
+) -> Result<ListResult<Token>> {
+    if params.is_paged() {
+        let page = TokenBmc::list_paged(
+            &ctx,
+            &mm,
+            params.filters,
+            params.list_options,
+            params.cursor,
+        )
+        .await?;
+        return Ok(ListResult::Page(page));
+    }
+
     let tokens = TokenBmc::list(&ctx, &mm, params.filters, params.list_options).await?;
 
-    Ok(tokens)
+    Ok(ListResult::Items(tokens))
 }
 
 pub async fn update_token(
@@ -41,6 +84,8 @@ pub async fn update_token(
     params: ParamsForUpdate<TokenForUpdate>,
 ) -> Result<Token> {
     let ParamsForUpdate { id, data } = params;
+    // NOTE: Admin-only method, so it can update the tokens of any owner
+    let ctx = ctx.for_all_owners()?;
 
     TokenBmc::update(&ctx, &mm, id, data).await?;
 
@@ -51,9 +96,139 @@ pub async fn update_token(
 
 pub async fn delete_token(ctx: Ctx, mm: ModelManager, params: ParamsIdOnly) -> Result<Token> {
     let ParamsIdOnly { id } = params;
+    // NOTE: Admin-only method, so it can delete the tokens of any owner
+    let ctx = ctx.for_all_owners()?;
+
+    // NOTE: get + delete in one txn, so the returned token is the deleted one
+    let mm = mm.new_with_txn()?;
+    mm.begin_txn().await?;
 
     let token = TokenBmc::get(&ctx, &mm, id).await?;
     TokenBmc::delete(&ctx, &mm, id).await?;
 
+    mm.commit_txn().await?;
+
     Ok(token)
 }
+
+// region: -- History
+#[derive(Deserialize)]
+pub struct ParamsTokenHistory {
+    pub token_id: i64,
+    /// Range start/end (unix times, inclusive)
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// "hour" or "day" to downsample, the raw snapshots when absent
+    pub bucket: Option<HistoryBucket>,
+}
+
+/// The token history result: the raw snapshots, or the buckets when downsampled
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum TokenHistory {
+    Snapshots(Vec<TokenSnapshot>),
+    Buckets(Vec<TokenSnapshotBucket>),
+}
+
+pub async fn get_token_history(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsTokenHistory,
+) -> Result<TokenHistory> {
+    let ParamsTokenHistory {
+        token_id,
+        from,
+        to,
+        bucket,
+    } = params;
+
+    let history = match bucket {
+        Some(bucket) => TokenHistory::Buckets(
+            TokenSnapshotBmc::list_buckets_for_token(&ctx, &mm, token_id, from, to, bucket).await?,
+        ),
+        None => TokenHistory::Snapshots(
+            TokenSnapshotBmc::list_for_token(&ctx, &mm, token_id, from, to).await?,
+        ),
+    };
+
+    Ok(history)
+}
+// endregion: -- History
+
+// region: -- Analytics
+#[serde_as]
+#[derive(Deserialize)]
+pub struct ParamsTopMovers {
+    #[serde_as(deserialize_as = "Option<OneOrMany<_>>")]
+    pub filters: Option<Vec<TokenFilter>>,
+    /// "gainers" (default) or "losers"
+    #[serde(default)]
+    pub direction: MoverDirection,
+    pub min_liquidity: Option<f64>,
+    pub limit: Option<i64>,
+}
+
+// NOTE: Not a ParamsList, the rank has its own order and offset paging only
+// (so the unknown fields, e.g., paged/cursor/list_options, are rejected).
+#[serde_as]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParamsTokenRank {
+    #[serde_as(deserialize_as = "Option<OneOrMany<_>>")]
+    pub filters: Option<Vec<TokenFilter>>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[serde_as]
+#[derive(Deserialize)]
+pub struct ParamsTokenStats {
+    #[serde_as(deserialize_as = "Option<OneOrMany<_>>")]
+    pub filters: Option<Vec<TokenFilter>>,
+}
+
+pub async fn token_top_movers(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsTopMovers,
+) -> Result<Vec<Token>> {
+    let ParamsTopMovers {
+        filters,
+        direction,
+        min_liquidity,
+        limit,
+    } = params;
+
+    let tokens = TokenBmc::top_movers(&ctx, &mm, filters, direction, min_liquidity, limit).await?;
+
+    Ok(tokens)
+}
+
+pub async fn token_rank(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsTokenRank,
+) -> Result<Vec<TokenRank>> {
+    let ParamsTokenRank {
+        filters,
+        limit,
+        offset,
+    } = params;
+
+    let ranks = TokenBmc::rank_by_mc(&ctx, &mm, filters, limit, offset).await?;
+
+    Ok(ranks)
+}
+
+pub async fn token_stats(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsTokenStats,
+) -> Result<TokenStats> {
+    let ParamsTokenStats { filters } = params;
+
+    let stats = TokenBmc::stats(&ctx, &mm, filters).await?;
+
+    Ok(stats)
+}
+// endregion: -- Analytics
@@ -0,0 +1,83 @@
+use crate::params::{ListResult, ParamsIdOnly, ParamsList};
+use crate::router::RpcRouter;
+use crate::Result;
+use lib_core::ctx::{Ctx, Role};
+use lib_core::model::user::{User, UserBmc, UserFilter};
+use lib_core::model::ModelManager;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+// NOTE: User registration is NOT an rpc method since it has to be public
+// (see web-server routes_login /api/register). These are for logged-in users.
+pub fn rpc_router() -> RpcRouter {
+    RpcRouter::new()
+        .add("change_pwd", change_pwd)
+        .add_with_role("list_users", Role::Admin, list_users)
+        .add_with_role("delete_user", Role::Admin, delete_user)
+        .add_with_role("revoke_user_sessions", Role::Admin, revoke_user_sessions)
+}
+
+#[derive(Deserialize)]
+pub struct ParamsChangePwd {
+    pub pwd_old: String,
+    pub pwd_new: String,
+}
+
+/// Change the pwd of the Ctx user (requires the current/old pwd)
+pub async fn change_pwd(ctx: Ctx, mm: ModelManager, params: ParamsChangePwd) -> Result<Value> {
+    let ParamsChangePwd { pwd_old, pwd_new } = params;
+
+    UserBmc::change_pwd(&ctx, &mm, ctx.user_id(), &pwd_old, &pwd_new).await?;
+
+    Ok(json!({ "success": true }))
+}
+
+pub async fn list_users(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsList<UserFilter>,
+) -> Result<ListResult<User>> {
+    if params.is_paged() {
+        let page = UserBmc::list_paged(
+            &ctx,
+            &mm,
+            params.filters,
+            params.list_options,
+            params.cursor,
+        )
+        .await?;
+        return Ok(ListResult::Page(page));
+    }
+
+    let users = UserBmc::list(&ctx, &mm, params.filters, params.list_options).await?;
+
+    Ok(ListResult::Items(users))
+}
+
+pub async fn delete_user(ctx: Ctx, mm: ModelManager, params: ParamsIdOnly) -> Result<User> {
+    let ParamsIdOnly { id } = params;
+
+    // NOTE: get + delete in one txn, so the returned user is the deleted one
+    let mm = mm.new_with_txn()?;
+    mm.begin_txn().await?;
+
+    let user: User = UserBmc::get(&ctx, &mm, id).await?;
+    UserBmc::delete(&ctx, &mm, id).await?;
+
+    mm.commit_txn().await?;
+
+    Ok(user)
+}
+
+/// Revoke all the web tokens (sessions) of a user by rotating its token_salt
+pub async fn revoke_user_sessions(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsIdOnly,
+) -> Result<Value> {
+    let ParamsIdOnly { id } = params;
+
+    UserBmc::rotate_token_salt(&ctx, &mm, id).await?;
+
+    Ok(json!({ "success": true }))
+}
@@ -0,0 +1,160 @@
+use crate::params::{ListResult, ParamsForCreate, ParamsForUpdate, ParamsIdOnly, ParamsList};
+use crate::router::RpcRouter;
+use crate::Result;
+use lib_core::ctx::Ctx;
+use lib_core::model::token::{Token, TokenRef};
+use lib_core::model::watchlist::{
+    Watchlist, WatchlistBmc, WatchlistFilter, WatchlistForCreate, WatchlistForUpdate,
+};
+use lib_core::model::ModelManager;
+use serde::Deserialize;
+
+// NOTE: The watchlists are scoped to the Ctx user (WatchlistBmc is OWNED),
+// so each user only sees and changes their own watchlists.
+
+pub fn rpc_router() -> RpcRouter {
+    RpcRouter::new()
+        .add("create_watchlist", create_watchlist)
+        .add("get_watchlist", get_watchlist)
+        .add("list_watchlists", list_watchlists)
+        .add("update_watchlist", update_watchlist)
+        .add("delete_watchlist", delete_watchlist)
+        .add("add_watchlist_token", add_watchlist_token)
+        .add("remove_watchlist_token", remove_watchlist_token)
+        .add("list_watchlist_tokens", list_watchlist_tokens)
+}
+
+/// The watchlist token params, by token id or address
+/// (e.g., `{"watchlist_id": 1000, "token_id": 1001}` or `{"watchlist_id": 1000, "address": "So11..."}`)
+#[derive(Deserialize)]
+pub struct ParamsWatchlistToken {
+    pub watchlist_id: i64,
+    #[serde(flatten)]
+    pub token: TokenRef,
+}
+
+pub async fn create_watchlist(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsForCreate<WatchlistForCreate>,
+) -> Result<Watchlist> {
+    let ParamsForCreate { data } = params;
+
+    let id = WatchlistBmc::create(&ctx, &mm, data).await?;
+    let watchlist = WatchlistBmc::get(&ctx, &mm, id).await?;
+
+    Ok(watchlist)
+}
+
+pub async fn get_watchlist(ctx: Ctx, mm: ModelManager, params: ParamsIdOnly) -> Result<Watchlist> {
+    let ParamsIdOnly { id } = params;
+
+    let watchlist = WatchlistBmc::get(&ctx, &mm, id).await?;
+
+    Ok(watchlist)
+}
+
+pub async fn list_watchlists(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsList<WatchlistFilter>,
+) -> Result<ListResult<Watchlist>> {
+    if params.is_paged() {
+        let page = WatchlistBmc::list_paged(
+            &ctx,
+            &mm,
+            params.filters,
+            params.list_options,
+            params.cursor,
+        )
+        .await?;
+        return Ok(ListResult::Page(page));
+    }
+
+    let watchlists = WatchlistBmc::list(&ctx, &mm, params.filters, params.list_options).await?;
+
+    Ok(ListResult::Items(watchlists))
+}
+
+/// Rename the watchlist
+pub async fn update_watchlist(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsForUpdate<WatchlistForUpdate>,
+) -> Result<Watchlist> {
+    let ParamsForUpdate { id, data } = params;
+
+    WatchlistBmc::update(&ctx, &mm, id, data).await?;
+
+    let watchlist = WatchlistBmc::get(&ctx, &mm, id).await?;
+
+    Ok(watchlist)
+}
+
+pub async fn delete_watchlist(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsIdOnly,
+) -> Result<Watchlist> {
+    let ParamsIdOnly { id } = params;
+
+    // NOTE: get + delete in one txn, so the returned watchlist is the deleted one
+    let mm = mm.new_with_txn()?;
+    mm.begin_txn().await?;
+
+    let watchlist = WatchlistBmc::get(&ctx, &mm, id).await?;
+    WatchlistBmc::delete(&ctx, &mm, id).await?;
+
+    mm.commit_txn().await?;
+
+    Ok(watchlist)
+}
+
+/// Add the token to the watchlist. Returns the watchlist tokens.
+pub async fn add_watchlist_token(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsWatchlistToken,
+) -> Result<Vec<Token>> {
+    let ParamsWatchlistToken {
+        watchlist_id,
+        token,
+    } = params;
+
+    WatchlistBmc::add_token(&ctx, &mm, watchlist_id, token).await?;
+
+    let tokens = WatchlistBmc::list_tokens(&ctx, &mm, watchlist_id).await?;
+
+    Ok(tokens)
+}
+
+/// Remove the token from the watchlist. Returns the watchlist tokens.
+pub async fn remove_watchlist_token(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsWatchlistToken,
+) -> Result<Vec<Token>> {
+    let ParamsWatchlistToken {
+        watchlist_id,
+        token,
+    } = params;
+
+    WatchlistBmc::remove_token(&ctx, &mm, watchlist_id, token).await?;
+
+    let tokens = WatchlistBmc::list_tokens(&ctx, &mm, watchlist_id).await?;
+
+    Ok(tokens)
+}
+
+/// The watchlist tokens (full Token rows), in the order they were added
+pub async fn list_watchlist_tokens(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsIdOnly,
+) -> Result<Vec<Token>> {
+    let ParamsIdOnly { id } = params;
+
+    let tokens = WatchlistBmc::list_tokens(&ctx, &mm, id).await?;
+
+    Ok(tokens)
+}
@@ -19,6 +19,15 @@ pub fn get_env_parse<T: FromStr>(name: &'static str) -> Result<T> {
     val.parse::<T>().map_err(|_| Error::WrongFormat(name))
 }
 
+// NOTE: Same as get_env_parse, but falls back to the default when the
+// env is not set (a set but malformed value is still an error).
+pub fn get_env_parse_or<T: FromStr>(name: &'static str, default: T) -> Result<T> {
+    match get_env_parse(name) {
+        Err(Error::MissingEnv(_)) => Ok(default),
+        other => other,
+    }
+}
+
 // region:       -- Error
 // NOTE: As this grows, we can move into a separate 'errors' module
 // U: Adding Clone so we can return our Result<Ctx, AuthFailCtxNotInRequestExt>
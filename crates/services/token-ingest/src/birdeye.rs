@@ -0,0 +1,132 @@
+//! Birdeye API client (tokenlist endpoint)
+//!
+//! REF: https://docs.birdeye.so/reference/get_defi-tokenlist
+//!
+//! Rate limits: A 429 (or 5xx, or a connection error) is retried with an
+//! exponential backoff (or the `Retry-After` seconds when given by the server),
+//! up to `max_retries`. Both delays are capped at `BACKOFF_MAX_MS`, so a huge
+//! `Retry-After` cannot stall the worker.
+
+use crate::{Error, Result};
+use lib_core::model::token::{BirdeyeDataResponse, BirdeyeRootResponse};
+use reqwest::{header, Client, StatusCode};
+use std::time::Duration;
+use tracing::warn;
+
+const TOKENLIST_PATH: &str = "/defi/tokenlist";
+const BACKOFF_MAX_MS: u64 = 30_000;
+const HTTP_TIMEOUT_SEC: u64 = 30;
+
+pub struct BirdeyeClient {
+    http: Client,
+    base_url: String,
+    api_key: String,
+    max_retries: u32,
+    backoff_ms: u64,
+}
+
+/// One tokenlist page, with the number of retries it took
+pub struct TokenlistPage {
+    pub data: BirdeyeDataResponse,
+    pub retries: u32,
+}
+
+impl BirdeyeClient {
+    pub fn new(base_url: &str, api_key: &str, max_retries: u32, backoff_ms: u64) -> Result<Self> {
+        let http = Client::builder()
+            .timeout(Duration::from_secs(HTTP_TIMEOUT_SEC))
+            .build()?;
+
+        Ok(BirdeyeClient {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            max_retries,
+            backoff_ms,
+        })
+    }
+
+    /// Fetch one tokenlist page (sorted by v24hUSD desc, so the paging is stable-ish).
+    pub async fn fetch_tokenlist(&self, offset: i64, limit: i64) -> Result<TokenlistPage> {
+        let url = format!("{}{TOKENLIST_PATH}", self.base_url);
+        let mut retries = 0;
+
+        loop {
+            let res = self
+                .http
+                .get(&url)
+                .header("X-API-KEY", &self.api_key)
+                .header("x-chain", "solana")
+                .query(&[
+                    ("sort_by", "v24hUSD".to_string()),
+                    ("sort_type", "desc".to_string()),
+                    ("offset", offset.to_string()),
+                    ("limit", limit.to_string()),
+                ])
+                .send()
+                .await;
+
+            // -- Retryable or done
+            let (status, retry_after) = match res {
+                Ok(res) if res.status().is_success() => {
+                    let root: BirdeyeRootResponse = res.json().await?;
+                    if !root.success {
+                        return Err(Error::BirdeyeResponseNotSuccess);
+                    }
+                    return Ok(TokenlistPage {
+                        data: root.data,
+                        retries,
+                    });
+                }
+                Ok(res)
+                    if res.status() == StatusCode::TOO_MANY_REQUESTS
+                        || res.status().is_server_error() =>
+                {
+                    (Some(res.status().as_u16()), retry_after(&res))
+                }
+                Ok(res) => {
+                    return Err(Error::BirdeyeHttpStatus {
+                        status: res.status().as_u16(),
+                    })
+                }
+                Err(ex) if ex.is_connect() || ex.is_timeout() => (None, None),
+                Err(ex) => return Err(ex.into()),
+            };
+
+            // -- Backoff
+            if retries >= self.max_retries {
+                return Err(Error::BirdeyeRetriesExhausted { status, retries });
+            }
+            let delay = retry_after
+                .map(|d| d.min(Duration::from_millis(BACKOFF_MAX_MS)))
+                .unwrap_or_else(|| self.backoff(retries));
+            warn!(
+                "{:<12} - birdeye tokenlist - status: {status:?}, retry in {delay:?}",
+                "INGEST"
+            );
+            tokio::time::sleep(delay).await;
+            retries += 1;
+        }
+    }
+
+    /// Exponential backoff (backoff_ms * 2^retries), capped
+    fn backoff(&self, retries: u32) -> Duration {
+        let ms = self
+            .backoff_ms
+            .saturating_mul(2u64.saturating_pow(retries))
+            .min(BACKOFF_MAX_MS);
+
+        Duration::from_millis(ms)
+    }
+}
+
+/// The Retry-After header (seconds form only)
+fn retry_after(res: &reqwest::Response) -> Option<Duration> {
+    res.headers()
+        .get(header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
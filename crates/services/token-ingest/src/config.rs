@@ -0,0 +1,48 @@
+use lib_utils::envs::{get_env_parse_or, Result};
+use std::sync::OnceLock;
+
+// NOTE: Same pattern as the web-server WebConfig (loaded once, &'static).
+pub fn ingest_config() -> &'static IngestConfig {
+    static INSTANCE: OnceLock<IngestConfig> = OnceLock::new();
+
+    INSTANCE.get_or_init(|| {
+        IngestConfig::load_from_env()
+            .unwrap_or_else(|ex| panic!("FATAL - WHILE LOADING CONFIG - Cause: {ex:?}"))
+    })
+}
+
+#[allow(non_snake_case)]
+pub struct IngestConfig {
+    // -- Birdeye
+    // NOTE: Configurable so a local mock server can stand in (e.g., tests).
+    pub BIRDEYE_BASE_URL: String,
+    pub BIRDEYE_API_KEY: String,
+
+    // -- Ingest
+    pub INGEST_INTERVAL_SEC: u64,
+    pub INGEST_PAGE_LIMIT: i64,
+    pub INGEST_MAX_PAGES: i64,
+    pub INGEST_MAX_RETRIES: u32,
+    pub INGEST_BACKOFF_MS: u64,
+}
+
+impl IngestConfig {
+    fn load_from_env() -> Result<IngestConfig> {
+        Ok(IngestConfig {
+            // -- Birdeye
+            BIRDEYE_BASE_URL: get_env_parse_or(
+                "SERVICE_BIRDEYE_BASE_URL",
+                "https://public-api.birdeye.so".to_string(),
+            )?,
+            BIRDEYE_API_KEY: get_env_parse_or("SERVICE_BIRDEYE_API_KEY", String::new())?,
+
+            // -- Ingest
+            INGEST_INTERVAL_SEC: get_env_parse_or("SERVICE_INGEST_INTERVAL_SEC", 300)?,
+            // NOTE: 50 is the Birdeye tokenlist max limit per request.
+            INGEST_PAGE_LIMIT: get_env_parse_or("SERVICE_INGEST_PAGE_LIMIT", 50)?,
+            INGEST_MAX_PAGES: get_env_parse_or("SERVICE_INGEST_MAX_PAGES", 20)?,
+            INGEST_MAX_RETRIES: get_env_parse_or("SERVICE_INGEST_MAX_RETRIES", 5)?,
+            INGEST_BACKOFF_MS: get_env_parse_or("SERVICE_INGEST_BACKOFF_MS", 500)?,
+        })
+    }
+}
@@ -0,0 +1,35 @@
+use derive_more::From;
+use lib_core::model;
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug, From)]
+pub enum Error {
+    // -- Birdeye
+    BirdeyeHttpStatus {
+        status: u16,
+    },
+    BirdeyeRetriesExhausted {
+        status: Option<u16>,
+        retries: u32,
+    },
+    BirdeyeResponseNotSuccess,
+
+    // -- Modules
+    #[from]
+    Model(model::Error),
+
+    // -- Externals
+    #[from]
+    Reqwest(reqwest::Error),
+}
+
+// region:  -- Error boilerplate (Optional)
+impl std::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+        write!(fmt, "{self:?}")
+    }
+}
+
+impl std::error::Error for Error {}
+// end region:  -- Error boilerplate
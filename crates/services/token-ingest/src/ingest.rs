@@ -0,0 +1,277 @@
+//! Token ingestion runs
+//!
+//! A run pages through the Birdeye tokenlist and upserts each page
+//! (TokenBmc::upsert_many, which also records the token snapshots and
+//! evaluates the alert rules).
+//! Each run is recorded as an IngestRun ('running', then 'ok' or 'fail').
+
+use crate::birdeye::BirdeyeClient;
+use crate::{Error, Result};
+use lib_core::ctx::Ctx;
+use lib_core::model::ingest_run::{IngestRun, IngestRunBmc, IngestRunForFinish};
+use lib_core::model::token::TokenBmc;
+use lib_core::model::ModelManager;
+use std::collections::HashSet;
+use std::time::Duration;
+use tracing::{error, info};
+
+pub const SOURCE_BIRDEYE_TOKENLIST: &str = "birdeye_tokenlist";
+
+pub struct IngestOptions {
+    pub page_limit: i64,
+    pub max_pages: i64,
+}
+
+/// Run an ingestion every `interval`, forever (a failed run is logged, not fatal).
+pub async fn run_forever(
+    mm: ModelManager,
+    client: BirdeyeClient,
+    options: IngestOptions,
+    interval: Duration,
+) {
+    loop {
+        match ingest_once(&mm, &client, &options).await {
+            Ok(run) => info!(
+                "{:<12} - run {} {} - pages: {}, tokens: {}, retries: {}",
+                "INGEST", run.id, run.status, run.pages, run.tokens, run.retries
+            ),
+            Err(ex) => error!("{:<12} - run not recorded - {ex:?}", "INGEST"),
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// One ingestion run. Returns the finished (recorded) run.
+// NOTE: A failed ingestion is still Ok (run.status 'fail' with the run.error),
+// the Err is only when the run itself cannot be recorded.
+pub async fn ingest_once(
+    mm: &ModelManager,
+    client: &BirdeyeClient,
+    options: &IngestOptions,
+) -> Result<IngestRun> {
+    let ctx = Ctx::root_ctx();
+    let run_id = IngestRunBmc::start(&ctx, mm, SOURCE_BIRDEYE_TOKENLIST).await?;
+
+    let mut counters = IngestRunForFinish::default();
+    if let Err(ex) = ingest_pages(&ctx, mm, client, options, &mut counters).await {
+        if let Error::BirdeyeRetriesExhausted { retries, .. } = &ex {
+            counters.retries += *retries as i64;
+        }
+        counters.error = Some(ex.to_string());
+    }
+
+    IngestRunBmc::finish(&ctx, mm, run_id, counters).await?;
+    let run = IngestRunBmc::get(&ctx, mm, run_id).await?;
+
+    Ok(run)
+}
+
+async fn ingest_pages(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    client: &BirdeyeClient,
+    options: &IngestOptions,
+    counters: &mut IngestRunForFinish,
+) -> Result<()> {
+    let mut offset = 0;
+
+    while counters.pages < options.max_pages {
+        let page = client.fetch_tokenlist(offset, options.page_limit).await?;
+        counters.retries += page.retries as i64;
+        counters.pages += 1;
+
+        let total = page.data.total;
+        let mut token_cs = page.data.into_token_cs();
+        let page_count = token_cs.len() as i64;
+
+        // NOTE: The same address twice in one upsert_many is a pg error, so keep
+        // the last one (can happen when the ranking moves while paging).
+        let mut addresses = HashSet::new();
+        token_cs.reverse();
+        token_cs.retain(|token_c| addresses.insert(token_c.address.clone()));
+        token_cs.reverse();
+
+        counters.tokens += TokenBmc::upsert_many(ctx, mm, token_cs).await?.len() as i64;
+
+        offset += options.page_limit;
+        if page_count < options.page_limit || offset >= total {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+// region: -- Tests
+#[cfg(test)]
+mod tests {
+    pub type Result<T> = core::result::Result<T, Error>;
+    pub type Error = Box<dyn std::error::Error>; // For early dev & tests.
+
+    use super::*;
+    use axum::extract::{Query, State};
+    use axum::http::{header, StatusCode};
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use lib_core::_dev_utils;
+    use lib_core::model::token::TokenFilter;
+    use lib_core::model::user::{User, UserBmc};
+    use serde_json::{json, Value};
+    use serial_test::serial;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const FX_PREFIX: &str = "test_ingest";
+    const FX_TOTAL: usize = 3;
+
+    // NOTE: A new ModelManager per test (not _dev_utils::init_test()), since each
+    // #[tokio::test] has its own runtime (same as the web-server tests).
+    async fn fx_mm() -> Result<ModelManager> {
+        _dev_utils::init_dev().await;
+
+        Ok(ModelManager::new().await?)
+    }
+
+    /// Mock Birdeye tokenlist: the first request is rate limited (429),
+    /// then FX_TOTAL tokens, paged by offset/limit. Always 500 when `fail`.
+    async fn fx_mock_birdeye(fail: bool) -> Result<String> {
+        async fn tokenlist(
+            State((count, fail)): State<(Arc<AtomicUsize>, bool)>,
+            Query(params): Query<HashMap<String, String>>,
+        ) -> Response {
+            if fail {
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+            if count.fetch_add(1, Ordering::SeqCst) == 0 {
+                return (StatusCode::TOO_MANY_REQUESTS, [(header::RETRY_AFTER, "0")])
+                    .into_response();
+            }
+
+            let param = |name: &str| params.get(name).and_then(|v| v.parse::<usize>().ok());
+            let (offset, limit) = (param("offset").unwrap_or(0), param("limit").unwrap_or(50));
+            let tokens: Vec<Value> = (offset..FX_TOTAL.min(offset + limit))
+                .map(|i| {
+                    json!({
+                        "address": format!("{FX_PREFIX}-address-{i}"),
+                        "decimals": 6,
+                        "liquidity": 100.0,
+                        "logoURI": "https://example.com/logo.png",
+                        "mc": 1000.0 + i as f64,
+                        "symbol": format!("TI{i}"),
+                        "v24hChangePercent": null,
+                        "v24hUSD": 10.0,
+                        "name": format!("Test Ingest {i}"),
+                        "lastTradeUnixTime": 1710395665
+                    })
+                })
+                .collect();
+
+            Json(json!({
+                "success": true,
+                "data": {
+                    "updateUnixTime": 1710403689,
+                    "updateTime": "2024-03-14T08:08:09",
+                    "tokens": tokens,
+                    "total": FX_TOTAL
+                }
+            }))
+            .into_response()
+        }
+
+        let routes = Router::new()
+            .route("/defi/tokenlist", get(tokenlist))
+            .with_state((Arc::new(AtomicUsize::new(0)), fail));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, routes).await });
+
+        Ok(base_url)
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_ingest_once_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = fx_mm().await?;
+        let ctx = Ctx::root_ctx();
+        let base_url = fx_mock_birdeye(false).await?;
+        let client = BirdeyeClient::new(&base_url, "fx-key", 2, 1)?;
+        let options = IngestOptions {
+            page_limit: 2,
+            max_pages: 10,
+        };
+
+        // -- Exec
+        let run = ingest_once(&mm, &client, &options).await?;
+
+        // -- Check
+        assert_eq!(run.status, "ok", "run error: {:?}", run.error);
+        assert_eq!(run.pages, 2);
+        assert_eq!(run.tokens, FX_TOTAL as i64);
+        assert_eq!(run.retries, 1);
+        let filters: Vec<TokenFilter> = serde_json::from_value(json!([
+            {"address": {"$startsWith": FX_PREFIX}}
+        ]))?;
+        let tokens = TokenBmc::list(&ctx, &mm, Some(filters), None).await?;
+        assert_eq!(tokens.len(), FX_TOTAL);
+
+        // -- Check: a user can read the ingested (root owned) tokens
+        let demo1: User = UserBmc::first_by_username(&ctx, &mm, "demo1")
+            .await?
+            .ok_or("demo1 should exist")?;
+        let demo_ctx = Ctx::new(demo1.id)?;
+        let token = TokenBmc::get(&demo_ctx, &mm, tokens[0].id).await?;
+        assert_eq!(token.owner_id, 0);
+        let filters: Vec<TokenFilter> = serde_json::from_value(json!([
+            {"address": {"$startsWith": FX_PREFIX}}
+        ]))?;
+        let tokens = TokenBmc::list(&demo_ctx, &mm, Some(filters), None).await?;
+        assert_eq!(tokens.len(), FX_TOTAL);
+
+        // -- Exec & Check: a second run updates (no duplicates)
+        let run = ingest_once(&mm, &client, &options).await?;
+        assert_eq!(run.status, "ok");
+        let filters: Vec<TokenFilter> = serde_json::from_value(json!([
+            {"address": {"$startsWith": FX_PREFIX}}
+        ]))?;
+        let tokens = TokenBmc::list(&ctx, &mm, Some(filters), None).await?;
+        assert_eq!(tokens.len(), FX_TOTAL);
+
+        // -- Clean
+        for token in tokens {
+            TokenBmc::delete(&ctx, &mm, token.id).await?;
+        }
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_ingest_once_err_retries_exhausted() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = fx_mm().await?;
+        let base_url = fx_mock_birdeye(true).await?;
+        let client = BirdeyeClient::new(&base_url, "fx-key", 2, 1)?;
+        let options = IngestOptions {
+            page_limit: 2,
+            max_pages: 10,
+        };
+
+        // -- Exec
+        let run = ingest_once(&mm, &client, &options).await?;
+
+        // -- Check
+        assert_eq!(run.status, "fail");
+        assert_eq!(run.pages, 0);
+        assert_eq!(run.retries, 2);
+        assert!(run
+            .error
+            .is_some_and(|err| err.contains("BirdeyeRetriesExhausted")));
+
+        Ok(())
+    }
+}
+// endregion: -- Tests
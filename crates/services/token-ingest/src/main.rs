@@ -0,0 +1,60 @@
+// NOTE: Background worker ingesting the Birdeye tokenlist into the token table
+// (see ingest.rs). Configured with the SERVICE_BIRDEYE_* and SERVICE_INGEST_* envs.
+
+mod birdeye;
+mod config;
+mod error;
+mod ingest;
+
+pub use self::error::{Error, Result};
+pub use config::ingest_config;
+
+use birdeye::BirdeyeClient;
+use ingest::IngestOptions;
+use lib_core::model::ModelManager;
+use std::time::Duration;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // -- Tracing
+    tracing_subscriber::fmt()
+        .without_time()
+        .with_target(false)
+        .with_env_filter(EnvFilter::from_default_env())
+        .init();
+
+    // -- Initialize ModelManager
+    // NOTE: No _dev_utils::init_dev() here (it recreates the dev db, which is the
+    // web-server's job). The schema comes from SERVICE_DB_MIGRATE=true or the web-server.
+    // NOTE: The token changes are notified to the web-server (/api/ws, /api/sse),
+    // which has its own events bus (see lib_core::model::event).
+    let mm = ModelManager::new().await?.with_db_events_notify();
+
+    // -- Start Ingest
+    let config = ingest_config();
+    let client = BirdeyeClient::new(
+        &config.BIRDEYE_BASE_URL,
+        &config.BIRDEYE_API_KEY,
+        config.INGEST_MAX_RETRIES,
+        config.INGEST_BACKOFF_MS,
+    )?;
+    let options = IngestOptions {
+        page_limit: config.INGEST_PAGE_LIMIT,
+        max_pages: config.INGEST_MAX_PAGES,
+    };
+    info!(
+        "{:<12} - {} every {}s\n",
+        "INGEST", config.BIRDEYE_BASE_URL, config.INGEST_INTERVAL_SEC
+    );
+    ingest::run_forever(
+        mm,
+        client,
+        options,
+        Duration::from_secs(config.INGEST_INTERVAL_SEC),
+    )
+    .await;
+
+    Ok(())
+}
@@ -11,16 +11,9 @@ mod web;
 pub use self::error::{Error, Result};
 pub use config::web_config;
 
-use crate::web::{
-    mw_auth::{mw_ctx_require, mw_ctx_resolve},
-    mw_res_map::mw_response_map,
-    routes_login, routes_rpc, routes_static,
-};
-use axum::{middleware, Router};
 use lib_core::_dev_utils;
 use lib_core::model::ModelManager;
 use tokio::net::TcpListener;
-use tower_cookies::CookieManagerLayer;
 use tracing::info;
 use tracing_subscriber::EnvFilter;
 
@@ -44,29 +37,11 @@ async fn main() -> Result<()> {
 
     // -- Initialize ModelManager
     let mm = ModelManager::new().await?;
+    // NOTE: The changes of the other processes (e.g., token-ingest) for /api/ws, /api/sse
+    mm.listen_db_events().await?;
 
     // -- Define Routes
-    let routes_rpc =
-        routes_rpc::routes(mm.clone()).route_layer(middleware::from_fn(mw_ctx_require));
-
-    // NOTE: You could create a separate struct for mw, but the from_fn() is very
-    // powerful
-    // REF: https://youtu.be/XZtlD_m59sM?t=2619
-    // NOTE: Basically every Axum route handler gets turned into a
-    // Tower::Service trait, which is roughly equivalent to sth that
-    // implements:
-    // async fn (Request) -> Result<Response, E> for some <Request, E>
-    // // REF: https://youtu.be/Wnb_n5YktO8?t=1850
-    // // REF: https://tokio.rs/blog/2021-05-14-inventing-the-service-trait
-    let routes_all = Router::new()
-        .merge(routes_login::routes(mm.clone()))
-        // NOTE: By nesting (merging), we are basically attaching a subrouter
-        .nest("/api", routes_rpc)
-        .layer(middleware::map_response(mw_response_map))
-        // NOTE: Making our Ctx extractor accessible to all routes
-        .layer(middleware::from_fn_with_state(mm.clone(), mw_ctx_resolve))
-        .layer(CookieManagerLayer::new())
-        .fallback_service(routes_static::serve_dir());
+    let routes_all = web::routes_all(mm);
 
     // region:  --- Start Server
     let listener = TcpListener::bind("127.0.0.1:8080").await.unwrap();
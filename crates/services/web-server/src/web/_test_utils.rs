@@ -0,0 +1,103 @@
+// NOTE: Test-only helpers to drive the full web-server Router (routes + mw)
+// over real HTTP, the same way examples/quick_dev.rs does with httpc-test.
+use crate::web::routes_all;
+use lib_core::_dev_utils;
+use lib_core::model::ModelManager;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+pub type Result<T> = core::result::Result<T, Error>;
+pub type Error = Box<dyn std::error::Error>; // For tests.
+
+/// Spawn the web-server on a random local port and return a client
+/// (with cookie store) pointing to it.
+// NOTE: We create a new ModelManager for each test (rather than reusing
+// _dev_utils::init_test()) since each #[tokio::test] has its own runtime,
+// and the db pool connections must live on the runtime serving the requests.
+pub async fn new_client() -> Result<httpc_test::Client> {
+    let base_url = spawn_server().await?;
+
+    Ok(httpc_test::new_client(base_url)?)
+}
+
+/// Spawn the web-server on a random local port and return its base url
+pub async fn spawn_server() -> Result<String> {
+    let (base_url, _) = spawn_server_with_mm().await?;
+
+    Ok(base_url)
+}
+
+/// Spawn the web-server and return its base url and ModelManager
+// NOTE: For the tests writing outside of the rpc (e.g., with the root Ctx,
+// like token-ingest) on the same EventBus as the server.
+pub async fn spawn_server_with_mm() -> Result<(String, ModelManager)> {
+    _dev_utils::init_dev().await;
+    let mm = ModelManager::new().await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let server_mm = mm.clone();
+    tokio::spawn(async move {
+        axum::serve(listener, routes_all(server_mm).into_make_service())
+            .await
+            .unwrap();
+    });
+
+    Ok((format!("http://{addr}"), mm))
+}
+
+/// Spawn the web-server and log in as the seeded "demo1" user
+pub async fn new_client_demo1() -> Result<httpc_test::Client> {
+    new_client_login("demo1", "welcome").await
+}
+
+/// Spawn the web-server and log in as the seeded "admin1" user (admin role)
+pub async fn new_client_admin1() -> Result<httpc_test::Client> {
+    new_client_login("admin1", "welcome").await
+}
+
+/// Spawn the web-server and log in with the given credentials
+pub async fn new_client_login(username: &str, pwd: &str) -> Result<httpc_test::Client> {
+    let base_url = spawn_server().await?;
+
+    new_client_login_to(&base_url, username, pwd).await
+}
+
+/// Log in with the given credentials to an already spawned web-server
+// NOTE: For the tests with several users on the same server (e.g., the ws/sse
+// change notifications, which are per ModelManager).
+pub async fn new_client_login_to(
+    base_url: &str,
+    username: &str,
+    pwd: &str,
+) -> Result<httpc_test::Client> {
+    let client = httpc_test::new_client(base_url)?;
+    client
+        .do_post(
+            "/api/login",
+            json!({
+                "username": username,
+                "pwd": pwd
+            }),
+        )
+        .await?;
+
+    Ok(client)
+}
+
+/// Call a JSON-RPC method on /api/rpc and return the full response body
+pub async fn rpc_call(client: &httpc_test::Client, method: &str, params: Value) -> Result<Value> {
+    let res = client
+        .do_post(
+            "/api/rpc",
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": method,
+                "params": params
+            }),
+        )
+        .await?;
+
+    Ok(res.json_body()?)
+}
@@ -46,6 +46,33 @@ pub enum Error {
         user_id: i64,
     },
 
+    // -- Refresh
+    RefreshTokenNotFound,
+    RefreshTokenInvalid,
+    RefreshTokenReused {
+        family_id: String,
+    },
+
+    // -- RPC
+    // NOTE: The /api/rpc body is not valid JSON (JSON-RPC Parse error)
+    RpcReqJsonParseFail(String),
+    // NOTE: Atomic batch (/api/rpc?atomic=true) item not applied, since
+    // another item of the batch failed (and the txn was rolled back).
+    RpcBatchAborted,
+
+    // -- WebSocket (/api/ws messages)
+    WsMethodUnknown(String),
+    WsParamsInvalid {
+        method: String,
+        reason: String,
+    },
+    WsSubscriptionNotFound {
+        subscription_id: i64,
+    },
+
+    // -- SSE (/api/sse/tokens)
+    SseFiltersInvalid(String),
+
     // -- CtxExtError
     #[from]
     CtxExt(web::mw_auth::CtxExtError),
@@ -136,6 +163,84 @@ impl Error {
         // Bring our structs, enums, etc. into scope
         use web::Error::*;
 
+        // -- Model
+        // NOTE: Model errors from the RPC handlers come wrapped in lib_rpc::Error,
+        // so they are unwrapped once here.
+        if let Model(model_error) | Rpc(lib_rpc::Error::Model(model_error)) = self {
+            return match model_error {
+                model::Error::EntityNotFound { entity, id } => (
+                    StatusCode::BAD_REQUEST,
+                    ClientError::ENTITY_NOT_FOUND { entity, id: *id }, // Deref the &i64
+                ),
+                model::Error::ListLimitOverMax { max, .. } => (
+                    StatusCode::BAD_REQUEST,
+                    ClientError::LIST_OPTIONS_INVALID(format!("limit over max {max}")),
+                ),
+                model::Error::ListCursorInvalid => (
+                    StatusCode::BAD_REQUEST,
+                    ClientError::LIST_OPTIONS_INVALID("cursor invalid".to_string()),
+                ),
+                model::Error::ListOrderByInvalid { order_by } => (
+                    StatusCode::BAD_REQUEST,
+                    ClientError::LIST_OPTIONS_INVALID(format!("order_by '{order_by}' invalid")),
+                ),
+                model::Error::FiltersEmpty { entity } => (
+                    StatusCode::BAD_REQUEST,
+                    ClientError::FILTERS_EMPTY { entity },
+                ),
+                model::Error::UniqueViolation { table, .. } => (
+                    StatusCode::CONFLICT,
+                    ClientError::ENTITY_ALREADY_EXISTS {
+                        entity: table.to_string(),
+                    },
+                ),
+                model::Error::UpsertNotOwned {
+                    entity,
+                    unique_values,
+                } => (
+                    StatusCode::CONFLICT,
+                    ClientError::ENTITY_NOT_OWNED {
+                        entity,
+                        unique_values: unique_values.clone(),
+                    },
+                ),
+                model::Error::TokenAddressNotFound { address } => (
+                    StatusCode::BAD_REQUEST,
+                    ClientError::TOKEN_ADDRESS_NOT_FOUND {
+                        address: address.to_string(),
+                    },
+                ),
+                model::Error::UserUsernameInvalid { reason } => (
+                    StatusCode::BAD_REQUEST,
+                    ClientError::USERNAME_INVALID(reason.to_string()),
+                ),
+                model::Error::UserPwdInvalid { reason } => (
+                    StatusCode::BAD_REQUEST,
+                    ClientError::PWD_INVALID(reason.to_string()),
+                ),
+                model::Error::UserAlreadyExists { .. } => {
+                    (StatusCode::CONFLICT, ClientError::USERNAME_ALREADY_EXISTS)
+                }
+                model::Error::UserPwdNotMatching { .. } => {
+                    (StatusCode::FORBIDDEN, ClientError::PWD_NOT_MATCHING)
+                }
+                model::Error::UserDeleteSelf { .. } => (
+                    StatusCode::CONFLICT,
+                    ClientError::USER_DELETE_NOT_ALLOWED("self".to_string()),
+                ),
+                model::Error::UserDeleteLastAdmin { .. } => (
+                    StatusCode::CONFLICT,
+                    ClientError::USER_DELETE_NOT_ALLOWED("last admin".to_string()),
+                ),
+
+                // -- Fallback
+                _ => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ClientError::SERVICE_ERROR,
+                ),
+            };
+        }
+
         // NOTE: Optional #[allow(unreachable_patterns)] for when
         // fallback is unreachable? Not sure but it's optional. Could argue
         // you should be strict and exhaust all variants.
@@ -149,12 +254,57 @@ impl Error {
             // -- Auth
             CtxExt(_) => (StatusCode::FORBIDDEN, ClientError::NO_AUTH),
 
-            // -- Model
-            Model(model::Error::EntityNotFound { entity, id }) => (
+            // -- Refresh
+            RefreshTokenNotFound | RefreshTokenInvalid | RefreshTokenReused { .. } => {
+                (StatusCode::FORBIDDEN, ClientError::NO_AUTH)
+            }
+
+            // -- RPC
+            RpcReqJsonParseFail(_) => (StatusCode::BAD_REQUEST, ClientError::RPC_PARSE_FAIL),
+            RpcBatchAborted => (StatusCode::CONFLICT, ClientError::RPC_BATCH_ABORTED),
+
+            // -- SSE
+            SseFiltersInvalid(_) => (
                 StatusCode::BAD_REQUEST,
-                ClientError::ENTITY_NOT_FOUND { entity, id: *id }, // Deref the &i64
+                ClientError::RPC_PARAMS_INVALID("filters".to_string()),
             ),
 
+            // -- WebSocket
+            WsMethodUnknown(method) => (
+                StatusCode::NOT_FOUND,
+                ClientError::RPC_METHOD_UNKNOWN(method.to_string()),
+            ),
+            WsParamsInvalid { method, .. } => (
+                StatusCode::BAD_REQUEST,
+                ClientError::RPC_PARAMS_INVALID(method.to_string()),
+            ),
+            WsSubscriptionNotFound { subscription_id } => (
+                StatusCode::BAD_REQUEST,
+                ClientError::ENTITY_NOT_FOUND {
+                    entity: "subscription",
+                    id: *subscription_id,
+                },
+            ),
+            Rpc(lib_rpc::Error::RpcRequestInvalid { reason }) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::RPC_REQUEST_INVALID(reason.to_string()),
+            ),
+            Rpc(lib_rpc::Error::RpcMissingParams { rpc_method }) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::RPC_REQUEST_INVALID(format!("{rpc_method} missing params")),
+            ),
+            Rpc(lib_rpc::Error::RpcMethodUnknown(rpc_method)) => (
+                StatusCode::NOT_FOUND,
+                ClientError::RPC_METHOD_UNKNOWN(rpc_method.to_string()),
+            ),
+            Rpc(lib_rpc::Error::RpcFailJsonParams { rpc_method }) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::RPC_PARAMS_INVALID(rpc_method.to_string()),
+            ),
+            Rpc(lib_rpc::Error::RpcAccessDenied { .. }) => {
+                (StatusCode::FORBIDDEN, ClientError::ACCESS_DENIED)
+            }
+
             // -- Fallback
             _ => (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -187,7 +337,70 @@ impl Error {
 pub enum ClientError {
     LOGIN_FAIL,
     NO_AUTH,
-    ENTITY_NOT_FOUND { entity: &'static str, id: i64 },
+    ACCESS_DENIED,
+    ENTITY_NOT_FOUND {
+        entity: &'static str,
+        id: i64,
+    },
+    TOKEN_ADDRESS_NOT_FOUND {
+        address: String,
+    },
+    USERNAME_INVALID(String),
+    USERNAME_ALREADY_EXISTS,
+    PWD_NOT_MATCHING,
+    PWD_INVALID(String),
+    USER_DELETE_NOT_ALLOWED(String),
+    RPC_BATCH_ABORTED,
+    LIST_OPTIONS_INVALID(String),
+    FILTERS_EMPTY {
+        entity: &'static str,
+    },
+    ENTITY_ALREADY_EXISTS {
+        entity: String,
+    },
+    ENTITY_NOT_OWNED {
+        entity: &'static str,
+        unique_values: Vec<String>,
+    },
+
+    // -- JSON-RPC 2.0 standard errors
+    RPC_PARSE_FAIL,
+    RPC_REQUEST_INVALID(String),
+    RPC_METHOD_UNKNOWN(String),
+    RPC_PARAMS_INVALID(String),
+
     SERVICE_ERROR,
 }
+
+// NOTE: JSON-RPC 2.0 error codes. The -32768 to -32000 range is reserved,
+// with -32000 to -32099 for implementation-defined server (app) errors.
+// REF: https://www.jsonrpc.org/specification#error_object
+impl ClientError {
+    pub fn rpc_code(&self) -> i64 {
+        use ClientError::*;
+
+        match self {
+            // -- JSON-RPC 2.0 standard
+            RPC_PARSE_FAIL => -32700,
+            RPC_REQUEST_INVALID(_) => -32600,
+            RPC_METHOD_UNKNOWN(_) => -32601,
+            RPC_PARAMS_INVALID(_) | LIST_OPTIONS_INVALID(_) | FILTERS_EMPTY { .. } => -32602,
+
+            // -- App errors
+            SERVICE_ERROR => -32000,
+            LOGIN_FAIL => -32001,
+            NO_AUTH => -32002,
+            ENTITY_NOT_FOUND { .. } | TOKEN_ADDRESS_NOT_FOUND { .. } => -32003,
+            ACCESS_DENIED => -32004,
+            USERNAME_INVALID(_) => -32005,
+            USERNAME_ALREADY_EXISTS => -32006,
+            PWD_NOT_MATCHING => -32007,
+            RPC_BATCH_ABORTED => -32008,
+            ENTITY_ALREADY_EXISTS { .. } => -32009,
+            PWD_INVALID(_) => -32010,
+            ENTITY_NOT_OWNED { .. } => -32011,
+            USER_DELETE_NOT_ALLOWED(_) => -32012,
+        }
+    }
+}
 // endregion: -- Client Error
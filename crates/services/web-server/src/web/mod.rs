@@ -4,15 +4,57 @@ pub mod mw_auth;
 pub mod mw_res_map;
 pub mod routes_login;
 pub mod routes_rpc;
+pub mod routes_sse;
 pub mod routes_static;
+pub mod routes_ws;
+
+#[cfg(test)]
+pub mod _test_utils;
 
 pub use self::error::ClientError;
 pub use self::error::{Error, Result};
-use lib_auth::token::generate_web_token;
-use tower_cookies::{Cookie, Cookies};
+use crate::web::mw_auth::{mw_ctx_require, mw_ctx_resolve};
+use crate::web::mw_res_map::mw_response_map;
+use axum::{middleware, Router};
+use lib_auth::token::{generate_web_token, Token};
+use lib_core::model::ModelManager;
+use tower_cookies::{Cookie, CookieManagerLayer, Cookies};
 use uuid::Uuid;
 
 pub const AUTH_TOKEN: &str = "auth-token";
+pub const REFRESH_TOKEN: &str = "refresh-token";
+// NOTE: The refresh token cookie is only sent to the refresh route
+const REFRESH_TOKEN_PATH: &str = "/api/refresh";
+
+// NOTE: U: Moved out of main() so the tests can spin up the exact same
+// Router (routes + middleware stack) that we serve in production.
+pub fn routes_all(mm: ModelManager) -> Router {
+    // NOTE: U: The /api/ws websocket (checked on the upgrade) and the
+    // /api/sse streams require the auth as well.
+    let routes_api = routes_rpc::routes(mm.clone())
+        .merge(routes_ws::routes(mm.clone()))
+        .merge(routes_sse::routes(mm.clone()))
+        .route_layer(middleware::from_fn(mw_ctx_require));
+
+    // NOTE: You could create a separate struct for mw, but the from_fn() is very
+    // powerful
+    // REF: https://youtu.be/XZtlD_m59sM?t=2619
+    // NOTE: Basically every Axum route handler gets turned into a
+    // Tower::Service trait, which is roughly equivalent to sth that
+    // implements:
+    // async fn (Request) -> Result<Response, E> for some <Request, E>
+    // // REF: https://youtu.be/Wnb_n5YktO8?t=1850
+    // // REF: https://tokio.rs/blog/2021-05-14-inventing-the-service-trait
+    Router::new()
+        .merge(routes_login::routes(mm.clone()))
+        // NOTE: By nesting (merging), we are basically attaching a subrouter
+        .nest("/api", routes_api)
+        .layer(middleware::map_response(mw_response_map))
+        // NOTE: Making our Ctx extractor accessible to all routes
+        .layer(middleware::from_fn_with_state(mm, mw_ctx_resolve))
+        .layer(CookieManagerLayer::new())
+        .fallback_service(routes_static::serve_dir())
+}
 
 fn set_token_cookie(cookies: &Cookies, user: &str, salt: Uuid) -> Result<()> {
     // NOTE: generate_web_token returns a crypt::error::Error, but we
@@ -42,3 +84,18 @@ fn remove_token_cookie(cookies: &Cookies) -> Result<()> {
 
     Ok(())
 }
+
+fn set_refresh_token_cookie(cookies: &Cookies, refresh_token: &Token) {
+    let mut cookie = Cookie::new(REFRESH_TOKEN, refresh_token.to_string());
+    cookie.set_http_only(true);
+    cookie.set_path(REFRESH_TOKEN_PATH);
+
+    cookies.add(cookie);
+}
+
+fn remove_refresh_token_cookie(cookies: &Cookies) {
+    let mut cookie = Cookie::from(REFRESH_TOKEN);
+    cookie.set_path(REFRESH_TOKEN_PATH);
+
+    cookies.remove(cookie);
+}
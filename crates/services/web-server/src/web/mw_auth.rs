@@ -1,13 +1,17 @@
-use crate::web::{set_token_cookie, Error, Result, AUTH_TOKEN};
+use crate::web::{Error, Result, AUTH_TOKEN};
 use async_trait::async_trait;
 use axum::extract::{FromRequestParts, State};
+use axum::http::header::AUTHORIZATION;
 use axum::http::request::Parts;
+use axum::http::HeaderMap;
 use axum::{body::Body, http::Request, middleware::Next, response::Response};
 use lib_auth::token::{validate_web_token, Token};
-use lib_core::ctx::Ctx;
+use lib_core::ctx::{self, Ctx, Role};
 use lib_core::model::user::{UserBmc, UserForAuth};
 use lib_core::model::ModelManager;
+use lib_utils::time::{now_utc, parse_utc};
 use serde::Serialize;
+use std::time::Duration;
 use tower_cookies::{Cookie, Cookies};
 use tracing::debug;
 
@@ -59,15 +63,43 @@ pub async fn mw_ctx_resolve(
 ) -> Result<Response> {
     debug!("{:<12} - mw_ctx_resolve", "MIDDLEWARE");
 
+    // -- Get Token String
+    // NOTE: U: Only an "Authorization: Bearer <token>" header is taken, any other
+    // scheme (e.g., a proxy's "Basic ...") falls back to the auth-token cookie.
+    let header_token = bearer_token(req.headers());
+    let from_cookie = header_token.is_none();
+    let token = match header_token {
+        Some(token) => Ok(token),
+        None => cookies
+            .get(AUTH_TOKEN)
+            .map(|c| c.value().to_string())
+            .ok_or(CtxExtError::TokenNotInCookie),
+    };
+
     // Again, we don't want _ctx_resolve to fail here (using '?').
     // Instead, it will be handled later downstream.
-    let ctx_ext_result = _ctx_resolve(mm, &cookies).await;
+    let ctx_ext_result = match token {
+        Ok(token) => _ctx_resolve(mm, &token).await,
+        Err(ex) => Err(ex),
+    };
+
+    // NOTE: U: The token is also kept (see AuthTokenW), for the long-lived
+    // connections (ws, sse) to check it is still valid while they are open.
+    let ctx_ext_result = ctx_ext_result.map(|(ctx, token)| {
+        req.extensions_mut().insert(AuthTokenW(token));
+        ctx
+    });
 
     // Now that we have result_ctx, we don't want to fail on this function if there
     // is an error. Instead, we need to remove the cookie if something
     // went wrong other than AuthFailNoAuthTokenCookie. If the TokenNotInCookie error,
     // then there's nothing to remove from the cookie anyway.
-    if ctx_ext_result.is_err() && !matches!(ctx_ext_result, Err(CtxExtError::TokenNotInCookie)) {
+    // U: Only when the cookie was the token source (a bad Bearer token says
+    // nothing about the cookie).
+    if from_cookie
+        && ctx_ext_result.is_err()
+        && !matches!(ctx_ext_result, Err(CtxExtError::TokenNotInCookie))
+    {
         cookies.remove(Cookie::from(AUTH_TOKEN))
     }
 
@@ -84,13 +116,14 @@ pub async fn mw_ctx_resolve(
 
 // NOTE: We don't want to panic if errors. Instead, we capture the entire CtxExtResult
 // and then let the other MW handle specific Err cases.
-async fn _ctx_resolve(mm: State<ModelManager>, cookies: &Cookies) -> CtxExtResult {
-    // -- Get Token String
-    let token = cookies
-        .get(AUTH_TOKEN)
-        .map(|c| c.value().to_string())
-        .ok_or(CtxExtError::TokenNotInCookie)?;
-
+// NOTE: U: The token can come from the "Authorization: Bearer <token>" header
+// (e.g., CLI tools, service-to-service calls, see /api/token) or from the
+// auth-token cookie (browser, see /api/login). The header takes precedence
+// (see mw_ctx_resolve).
+async fn _ctx_resolve(
+    mm: State<ModelManager>,
+    token: &str,
+) -> core::result::Result<(CtxW, Token), CtxExtError> {
     // -- Parse Token
     // Shadow 'token'variable
     // NOTE: token.parse() returns a crypt::Error, but we want a CtxExtError type.
@@ -107,16 +140,32 @@ async fn _ctx_resolve(mm: State<ModelManager>, cookies: &Cookies) -> CtxExtResul
     // -- Validate Token
     validate_web_token(&token, user.token_salt).map_err(|_| CtxExtError::FailValidate)?;
 
-    // -- Update Token & Cookies
-    set_token_cookie(cookies, &user.username, user.token_salt)
-        .map_err(|_| CtxExtError::CannotSetTokenCookie)?;
+    // NOTE: U: The access token is no longer re-issued (sliding expiration) on
+    // each request. Once expired, the client gets a new one from /api/refresh
+    // with its (single-use) refresh token.
 
     // -- Create CtxExtResult to be added to Request extension
     // NOTE: Recall that CtxExtResult is independent of the web layer, so that's why
     // there is no cookie, token, etc.
-    Ctx::new(user.id)
-        .map(CtxW)
-        .map_err(|ex| CtxExtError::CtxCreateFail(ex.to_string()))
+    let role: Role = user
+        .role
+        .parse()
+        .map_err(|ex: ctx::Error| CtxExtError::CtxCreateFail(ex.to_string()))?;
+
+    let ctx = Ctx::new_with_role(user.id, role)
+        .map_err(|ex| CtxExtError::CtxCreateFail(ex.to_string()))?;
+
+    Ok((CtxW(ctx), token))
+}
+
+/// The token of an "Authorization: Bearer <token>" header, if any
+/// (the scheme is case-insensitive, RFC 7235).
+fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    let token = token.trim();
+
+    (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty()).then(|| token.to_string())
 }
 
 // region: -- Ctx Extractor
@@ -198,6 +247,50 @@ impl<S: Send + Sync> FromRequestParts<S> for CtxW {
 }
 // endregion: -- Ctx Extractor
 
+// region: -- Auth Token Extractor
+/// The validated auth token of the request Ctx (set by mw_ctx_resolve)
+// NOTE: For the long-lived connections (ws, sse), which outlive the request
+// auth check. They end when the token expires, or when it is not valid anymore
+// (logoff, pwd change, revoke_user_sessions rotate the token_salt, user deleted).
+#[derive(Debug, Clone)]
+pub struct AuthTokenW(pub Token);
+
+impl AuthTokenW {
+    /// The time left before the token expiration (zero when expired)
+    pub fn expires_in(&self) -> Duration {
+        parse_utc(&self.0.exp)
+            .map(|exp| (exp - now_utc()).try_into().unwrap_or_default())
+            .unwrap_or_default()
+    }
+
+    /// Check the token is still valid for the Ctx user (same check as mw_ctx_resolve,
+    /// with the current user token_salt).
+    pub async fn revalidate(&self, ctx: &Ctx, mm: &ModelManager) -> Result<()> {
+        let user = UserBmc::first_by_username(&Ctx::root_ctx(), mm, &self.0.ident)
+            .await?
+            .filter(|user: &UserForAuth| user.id == ctx.user_id())
+            .ok_or(Error::CtxExt(CtxExtError::UserNotFound))?;
+        validate_web_token(&self.0, user.token_salt)
+            .map_err(|_| Error::CtxExt(CtxExtError::FailValidate))?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthTokenW {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self> {
+        parts
+            .extensions
+            .get::<AuthTokenW>()
+            .cloned()
+            .ok_or(Error::CtxExt(CtxExtError::CtxNotInRequestExt))
+    }
+}
+// endregion: -- Auth Token Extractor
+
 // region: -- Ctx Extractor Result/Error
 // NOTE: This is so we don't have to make the web::Error implement
 // things like Clone, etc. - this keeps the Result/Error specific
@@ -215,7 +308,6 @@ pub enum CtxExtError {
     // NOTE: Could consider having the inner model::Error instead of String
     ModelAccessError(String),
     FailValidate,
-    CannotSetTokenCookie,
 
     CtxNotInRequestExt,
     // NOTE: Could consider having the inner ctx::Error instead of String
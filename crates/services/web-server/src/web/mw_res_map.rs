@@ -4,10 +4,12 @@ use crate::log::log_request;
 use crate::web;
 use crate::web::mw_auth::CtxW;
 use crate::web::routes_rpc::RpcInfo;
-use axum::http::{Method, Uri};
+use crate::web::ClientError;
+use axum::http::{Method, StatusCode, Uri};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use serde_json::json;
+use lib_rpc::JSONRPC_VERSION;
+use serde_json::{json, Value};
 use std::sync::Arc;
 use tracing::debug;
 use uuid::Uuid;
@@ -30,8 +32,6 @@ pub async fn mw_response_map(
     let ctx = ctx.map(|ctx| ctx.0);
 
     debug!("{:<12} - mw_response_map", "RES_MAPPER");
-    // Create a uuid to match our server errors to client errors
-    let uuid = Uuid::new_v4();
 
     // -- Get RpcInfo
     // NOTE: !! U: Axum 0.7 requires the data that's inserted needs to impl Clone,
@@ -41,6 +41,12 @@ pub async fn mw_response_map(
     // REF: https://youtu.be/MvWCX5ckuDE?list=PL7r-PXl6ZPcCIOFaL7nVHXZvBmHNhrh_Q&t=229
     let rpc_info = res.extensions().get::<Arc<RpcInfo>>().map(Arc::as_ref);
 
+    // Create a uuid to match our server errors to client errors
+    // U: The rpc call req_uuid when there is one (also in the audit log).
+    let uuid = rpc_info
+        .map(|rpc| rpc.req_uuid)
+        .unwrap_or_else(Uuid::new_v4);
+
     // -- Get the eventual response error
     let service_error = res.extensions().get::<Arc<web::Error>>().map(Arc::as_ref);
     let client_status_error = service_error.map(|se| se.client_status_and_error());
@@ -52,24 +58,14 @@ pub async fn mw_response_map(
     let error_response = client_status_error
         .as_ref()
         .map(|(status_code, client_error)| {
-            // U: After adding Serialize to ClientError to be more JSON RPC like.
-            // We'll be extracting the tag="message" and content="detail"
-            let client_error = serde_json::to_value(client_error).ok();
-            let message = client_error.as_ref().and_then(|v| v.get("message"));
-            let detail = client_error.as_ref().and_then(|v| v.get("detail"));
+            // NOTE: U: JSON-RPC 2.0 - Notifications (no "id") never get a response
+            // body, even on error. We still log the error below.
+            if rpc_info.is_some_and(|rpc| rpc.id.is_none()) {
+                return StatusCode::NO_CONTENT.into_response();
+            }
 
-            // U: Now we're making it more JSON RPC compliant with our structure
-            // (id, error.{message,data{}})
-            let client_error_body = json!({
-                "id": rpc_info.as_ref().map(|rpc| rpc.id.clone()),
-                "error": {
-                    "message": message, // VariantName
-                    "data": {
-                        "req_uuid": uuid.to_string(),
-                        "detail": detail // VariantData
-                    }
-                }
-            });
+            let client_error_body =
+                rpc_error_body(rpc_info.and_then(|rpc| rpc.id.clone()), client_error, uuid);
 
             debug!("CLIENT ERROR BODY: {client_error_body}");
 
@@ -105,3 +101,29 @@ pub async fn mw_response_map(
     // match in the logs for both client and server errors. Neat!
     error_response.unwrap_or(res)
 }
+
+/// Build the JSON-RPC 2.0 error response object sent to the client
+// NOTE: U: Shared with the batch rpc handler (routes_rpc), which has to build
+// the error objects of each batch item itself.
+pub fn rpc_error_body(id: Option<Value>, client_error: &ClientError, req_uuid: Uuid) -> Value {
+    // U: After adding Serialize to ClientError to be more JSON RPC like.
+    // We'll be extracting the tag="message" and content="detail"
+    let client_error_value = serde_json::to_value(client_error).ok();
+    let message = client_error_value.as_ref().and_then(|v| v.get("message"));
+    let detail = client_error_value.as_ref().and_then(|v| v.get("detail"));
+
+    // U: Now we're making it more JSON RPC compliant with our structure
+    // (jsonrpc, id, error.{code,message,data{}})
+    json!({
+        "jsonrpc": JSONRPC_VERSION,
+        "id": id,
+        "error": {
+            "code": client_error.rpc_code(),
+            "message": message, // VariantName
+            "data": {
+                "req_uuid": req_uuid.to_string(),
+                "detail": detail // VariantData
+            }
+        }
+    })
+}
@@ -1,13 +1,19 @@
-use crate::web::{self, remove_token_cookie, Error, Result};
+use crate::web::mw_auth::CtxW;
+use crate::web::{self, remove_token_cookie, Error, Result, REFRESH_TOKEN};
 use axum::{extract::State, routing::post, Json, Router};
 use lib_auth::pwd::{self, ContentToHash, SchemeStatus};
+use lib_auth::token::{generate_refresh_token, generate_web_token, validate_refresh_token, Token};
 use lib_core::ctx::Ctx;
-use lib_core::model::user::{UserBmc, UserForLogin};
+use lib_core::model::audit::AuditInfo;
+use lib_core::model::refresh_token::{RefreshTokenBmc, RefreshTokenForCreate};
+use lib_core::model::user::{UserBmc, UserForAuth, UserForCreate, UserForLogin};
 use lib_core::model::ModelManager;
+use lib_utils::time::parse_utc;
 use serde::Deserialize;
 use serde_json::{json, Value};
 use tower_cookies::Cookies;
 use tracing::debug;
+use uuid::Uuid;
 
 // NOTE: TIP: Common practice is to create a fn that returns the module Router
 // and then merge(web::routes_login::routes()) inside main
@@ -19,7 +25,10 @@ use tracing::debug;
 pub fn routes(mm: ModelManager) -> Router {
     Router::new()
         .route("/api/login", post(api_login_handler))
+        .route("/api/token", post(api_token_handler))
+        .route("/api/refresh", post(api_refresh_handler))
         .route("/api/logoff", post(api_logoff_handler))
+        .route("/api/register", post(api_register_handler))
         .with_state(mm)
 }
 
@@ -34,6 +43,63 @@ async fn api_login_handler(
 ) -> Result<Json<Value>> {
     debug!("{:<12} - api_login_handler", "HANDLER");
 
+    let user = validate_login(&mm, payload).await?;
+
+    // // -- Fake Login:
+    // // TODO: Implement real db/auth logic
+    // if payload.username != "demo1" || payload.pwd != "welcome" {
+    //     return Err(Error::LoginFail);
+    // }
+
+    // -- Set web token cookies using Tower's CookieManagerLayer extractor
+    // We'll use a format of: "user-{id}.{expire_date}.{signature}"
+    // - OLD:
+    // cookies.add(Cookie::new(web::AUTH_TOKEN, "user-1.exp.sign"));
+    // - U: With auth-token gen/sign:
+    // REF: https://youtu.be/3cA_mk4vdWY?t=10449
+    web::set_token_cookie(&cookies, &user.username, user.token_salt)?;
+    // U: Plus the long-lived refresh token (new family) for /api/refresh
+    let refresh_token = issue_refresh_token(&mm, user.id, user.token_salt, Uuid::new_v4()).await?;
+    web::set_refresh_token_cookie(&cookies, &refresh_token);
+
+    // Create the success body
+    let body = Json(json!({
+        "result": {
+        "success": true
+        }
+    }));
+
+    Ok(body)
+}
+
+// NOTE: U: Login variant for non-browser callers (CLI, services). Same
+// credentials check, but the token is returned in the body (no cookie),
+// and then sent back with the "Authorization: Bearer <token>" header.
+async fn api_token_handler(
+    State(mm): State<ModelManager>,
+    Json(payload): Json<LoginPayload>,
+) -> Result<Json<Value>> {
+    debug!("{:<12} - api_token_handler", "HANDLER");
+
+    let user = validate_login(&mm, payload).await?;
+
+    let token = generate_web_token(&user.username, user.token_salt)?;
+    let refresh_token = issue_refresh_token(&mm, user.id, user.token_salt, Uuid::new_v4()).await?;
+
+    // Create the success body
+    let body = Json(json!({
+        "result": {
+        "token": token.to_string(),
+        "token_type": "Bearer",
+        "refresh_token": refresh_token.to_string()
+        }
+    }));
+
+    Ok(body)
+}
+
+/// Validate the login credentials and return the logged in user
+async fn validate_login(mm: &ModelManager, payload: LoginPayload) -> Result<UserForLogin> {
     // -- Get payload & System user (Ctx::root_ctx())
     let LoginPayload {
         username,
@@ -51,14 +117,14 @@ async fn api_login_handler(
     // a web layer Error (.await?) if Err variant. We need to let it convert from a
     // web Error -> model Error. To do this, we need to update our web::error
     // sub module and impl From<model::Error> for Error (web).
-    let user: UserForLogin = UserBmc::first_by_username(&root_ctx, &mm, &username)
+    let user: UserForLogin = UserBmc::first_by_username(&root_ctx, mm, &username)
         .await?
         .ok_or(Error::LoginFailUsernameNotFound)?;
     let user_id = user.id;
 
     // -- Validate the password
     // NOTE: let-else pattern for adding a guard on password
-    let Some(pwd) = user.pwd else {
+    let Some(pwd) = user.pwd.clone() else {
         return Err(Error::LoginFailUserHasNoPwd { user_id });
     };
 
@@ -79,32 +145,38 @@ async fn api_login_handler(
     // SchemeStatus and auto-upgrade to Scheme02 if SchemeStatus::Outdated.
     if let SchemeStatus::Outdated = scheme_status {
         debug!("pwd encrypt scheme outdated, upgrading.");
-        UserBmc::update_pwd(&root_ctx, &mm, user_id, &pwd_clear).await?;
+        UserBmc::update_pwd(&root_ctx, mm, user_id, &pwd_clear).await?;
     }
 
-    // // -- Fake Login:
-    // // TODO: Implement real db/auth logic
-    // if payload.username != "demo1" || payload.pwd != "welcome" {
-    //     return Err(Error::LoginFail);
-    // }
+    Ok(user)
+}
 
-    // -- Set web token cookies using Tower's CookieManagerLayer extractor
-    // We'll use a format of: "user-{id}.{expire_date}.{signature}"
-    // - OLD:
-    // cookies.add(Cookie::new(web::AUTH_TOKEN, "user-1.exp.sign"));
-    // - U: With auth-token gen/sign:
-    // REF: https://youtu.be/3cA_mk4vdWY?t=10449
-    web::set_token_cookie(&cookies, &user.username, user.token_salt)?;
+/// Generate a new refresh token for the user (in the given family) and persist it
+async fn issue_refresh_token(
+    mm: &ModelManager,
+    user_id: i64,
+    token_salt: Uuid,
+    family_id: Uuid,
+) -> Result<Token> {
+    let jti = Uuid::new_v4();
+    let refresh_token = generate_refresh_token(&jti.to_string(), token_salt)?;
+    let exp = parse_utc(&refresh_token.exp).map_err(|_| Error::RefreshTokenInvalid)?;
 
-    // Create the success body
-    let body = Json(json!({
-        "result": {
-        "success": true
-        }
-    }));
+    RefreshTokenBmc::create(
+        &Ctx::root_ctx(),
+        mm,
+        RefreshTokenForCreate {
+            user_id,
+            jti,
+            family_id,
+            exp,
+        },
+    )
+    .await?;
 
-    Ok(body)
+    Ok(refresh_token)
 }
+
 // Login  payload sent from client
 // Deserialized from JSON to Rust
 #[derive(Debug, Deserialize)]
@@ -114,8 +186,100 @@ struct LoginPayload {
 }
 // endregion:    -- Login
 
+// region:       -- Refresh
+// NOTE: Exchange a (single-use) refresh token for a new access token and a new
+// refresh token of the same family. The refresh token comes from the body
+// ({"refresh_token": "..."}, e.g., /api/token callers) and the new pair is
+// returned in the body, or from the refresh-token cookie (e.g., /api/login
+// callers) and the new pair is set as cookies.
+// NOTE: The body is optional (Option<Json>), so a cookie-only POST (no body,
+// no json content-type) is accepted.
+// NOTE: If an already used refresh token is presented again, it was likely
+// stolen, so we revoke the whole family (the legit client has to log in again).
+async fn api_refresh_handler(
+    State(mm): State<ModelManager>,
+    cookies: Cookies,
+    payload: Option<Json<RefreshPayload>>,
+) -> Result<Json<Value>> {
+    debug!("{:<12} - api_refresh_handler", "HANDLER");
+
+    let root_ctx = Ctx::root_ctx();
+
+    // -- Get & Parse the refresh token
+    let body_refresh_token = payload.and_then(|Json(payload)| payload.refresh_token);
+    let from_cookie = body_refresh_token.is_none();
+    let refresh_token = body_refresh_token
+        .or_else(|| cookies.get(REFRESH_TOKEN).map(|c| c.value().to_string()))
+        .ok_or(Error::RefreshTokenNotFound)?;
+    let refresh_token: Token = refresh_token
+        .parse()
+        .map_err(|_| Error::RefreshTokenInvalid)?;
+    let jti: Uuid = refresh_token
+        .ident
+        .parse()
+        .map_err(|_| Error::RefreshTokenInvalid)?;
+
+    // -- Get the persisted refresh token & its user
+    let refresh_token_db = RefreshTokenBmc::first_by_jti(&root_ctx, &mm, jti)
+        .await?
+        .ok_or(Error::RefreshTokenInvalid)?;
+    let user: UserForAuth = UserBmc::get(&root_ctx, &mm, refresh_token_db.user_id).await?;
+
+    // -- Validate (signature with the user token_salt, expiration, not revoked)
+    validate_refresh_token(&refresh_token, user.token_salt)
+        .map_err(|_| Error::RefreshTokenInvalid)?;
+    if refresh_token_db.revoked {
+        return Err(Error::RefreshTokenInvalid);
+    }
+
+    // -- Single use & Reuse detection
+    if !RefreshTokenBmc::mark_used(&root_ctx, &mm, refresh_token_db.id).await? {
+        RefreshTokenBmc::revoke_family(&root_ctx, &mm, refresh_token_db.family_id).await?;
+        return Err(Error::RefreshTokenReused {
+            family_id: refresh_token_db.family_id.to_string(),
+        });
+    }
+
+    // -- Issue the new pair (same family)
+    let new_refresh_token =
+        issue_refresh_token(&mm, user.id, user.token_salt, refresh_token_db.family_id).await?;
+
+    let body = if from_cookie {
+        web::set_token_cookie(&cookies, &user.username, user.token_salt)?;
+        web::set_refresh_token_cookie(&cookies, &new_refresh_token);
+
+        json!({
+            "result": {
+            "success": true
+            }
+        })
+    } else {
+        let token = generate_web_token(&user.username, user.token_salt)?;
+
+        json!({
+            "result": {
+            "token": token.to_string(),
+            "token_type": "Bearer",
+            "refresh_token": new_refresh_token.to_string()
+            }
+        })
+    };
+
+    Ok(Json(body))
+}
+
+#[derive(Debug, Deserialize)]
+struct RefreshPayload {
+    refresh_token: Option<String>,
+}
+// endregion:    -- Refresh
+
 // region:       -- Logoff
+// NOTE: U: Logoff also rotates the user token_salt (when logged in), so the
+// token cannot be reused (e.g., if stolen) even before its expiration.
 async fn api_logoff_handler(
+    State(mm): State<ModelManager>,
+    ctx: Option<CtxW>,
     cookies: Cookies,
     Json(payload): Json<LogoffPayload>,
 ) -> Result<Json<Value>> {
@@ -123,7 +287,16 @@ async fn api_logoff_handler(
     let should_logoff = payload.logoff;
 
     if should_logoff {
+        if let Some(CtxW(ctx)) = ctx {
+            // NOTE: Not an rpc call, but audited like one (as the "logoff" method).
+            let mm = mm.with_audit(AuditInfo {
+                rpc_method: "logoff".to_string(),
+                req_uuid: Uuid::new_v4(),
+            });
+            UserBmc::rotate_token_salt(&ctx, &mm, ctx.user_id()).await?;
+        }
         remove_token_cookie(&cookies)?;
+        web::remove_refresh_token_cookie(&cookies);
     }
 
     // Create the success body
@@ -144,3 +317,384 @@ struct LogoffPayload {
     logoff: bool,
 }
 // endregion:    -- Logoff
+
+// region:       -- Register
+// NOTE: Public route (no Ctx required). Username validation and duplicate
+// detection are done by UserBmc::create (model::Error::UserUsernameInvalid
+// and model::Error::UserAlreadyExists).
+async fn api_register_handler(
+    State(mm): State<ModelManager>,
+    Json(payload): Json<RegisterPayload>,
+) -> Result<Json<Value>> {
+    debug!("{:<12} - api_register_handler", "HANDLER");
+
+    let RegisterPayload {
+        username,
+        pwd: pwd_clear,
+    } = payload;
+    // There is no user yet, so we use the root Ctx (system user)
+    let root_ctx = Ctx::root_ctx();
+
+    let user_id = UserBmc::create(
+        &root_ctx,
+        &mm,
+        UserForCreate {
+            username,
+            pwd_clear,
+        },
+    )
+    .await?;
+
+    // Create the success body
+    let body = Json(json!({
+        "result": {
+        "success": true,
+        "id": user_id
+        }
+    }));
+
+    Ok(body)
+}
+
+#[derive(Debug, Deserialize)]
+struct RegisterPayload {
+    username: String,
+    pwd: String,
+}
+// endregion:    -- Register
+
+// region:    -- Tests
+#[cfg(test)]
+mod tests {
+    #![allow(unused)]
+    use crate::web::_test_utils::{
+        new_client, new_client_admin1, new_client_demo1, new_client_login, new_client_login_to,
+        rpc_call, spawn_server, Result,
+    };
+    use serde_json::json;
+    use serial_test::serial;
+
+    #[tokio::test]
+    #[serial]
+    async fn test_register_login_change_pwd_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client().await?;
+        let fx_username = "test_register_ok-user";
+
+        // -- Exec: register
+        let res = client
+            .do_post(
+                "/api/register",
+                json!({"username": fx_username, "pwd": "pwd-01"}),
+            )
+            .await?;
+
+        // -- Check: register & login
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.json_body()?["result"]["success"], true);
+        let client = new_client_login(fx_username, "pwd-01").await?;
+        let res = rpc_call(&client, "list_tasks", json!({})).await?;
+        assert!(res["result"].is_array(), "should be logged in");
+
+        // -- Exec & Check: change_pwd
+        let res = rpc_call(
+            &client,
+            "change_pwd",
+            json!({"pwd_old": "wrong", "pwd_new": "pwd-02"}),
+        )
+        .await?;
+        assert_eq!(res["error"]["message"], "PWD_NOT_MATCHING");
+        let res = rpc_call(
+            &client,
+            "change_pwd",
+            json!({"pwd_old": "pwd-01", "pwd_new": "pwd-02"}),
+        )
+        .await?;
+        assert_eq!(res["result"]["success"], true);
+        let client = new_client_login(fx_username, "pwd-02").await?;
+        let res = rpc_call(&client, "list_tasks", json!({})).await?;
+        assert!(res["result"].is_array(), "should be logged in with new pwd");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_register_err() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client().await?;
+
+        // -- Exec & Check: duplicate
+        let res = client
+            .do_post(
+                "/api/register",
+                json!({"username": "demo1", "pwd": "pwd-01"}),
+            )
+            .await?;
+        assert_eq!(res.status(), 409);
+        assert_eq!(
+            res.json_body()?["error"]["message"],
+            "USERNAME_ALREADY_EXISTS"
+        );
+
+        // -- Exec & Check: invalid username
+        let res = client
+            .do_post("/api/register", json!({"username": "a b", "pwd": "pwd-01"}))
+            .await?;
+        assert_eq!(res.status(), 400);
+        assert_eq!(res.json_body()?["error"]["message"], "USERNAME_INVALID");
+
+        // -- Exec & Check: empty pwd
+        let res = client
+            .do_post(
+                "/api/register",
+                json!({"username": "test_register_err", "pwd": ""}),
+            )
+            .await?;
+        assert_eq!(res.status(), 400);
+        assert_eq!(res.json_body()?["error"]["message"], "PWD_INVALID");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_logoff_revokes_sessions_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        // Two sessions (tokens) for the same user
+        let client_01 = new_client_demo1().await?;
+        let client_02 = new_client_demo1().await?;
+
+        // -- Exec
+        client_01
+            .do_post("/api/logoff", json!({"logoff": true}))
+            .await?;
+
+        // -- Check: the other session token is no longer valid
+        let res = rpc_call(&client_02, "list_tasks", json!({})).await?;
+        assert_eq!(res["error"]["message"], "NO_AUTH");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_rpc_revoke_user_sessions_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client_demo1().await?;
+        let admin_client = new_client_admin1().await?;
+        let res = rpc_call(
+            &admin_client,
+            "list_users",
+            json!({"filters": {"username": "demo1"}}),
+        )
+        .await?;
+        let demo1_id = res["result"][0]["id"]
+            .as_i64()
+            .ok_or("should have demo1 id")?;
+
+        // -- Exec
+        let res = rpc_call(
+            &admin_client,
+            "revoke_user_sessions",
+            json!({ "id": demo1_id }),
+        )
+        .await?;
+
+        // -- Check
+        assert_eq!(res["result"]["success"], true);
+        let res = rpc_call(&client, "list_tasks", json!({})).await?;
+        assert_eq!(res["error"]["message"], "NO_AUTH");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_token_bearer_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let base_url = spawn_server().await?;
+        let client = httpc_test::new_client(base_url.as_str())?;
+        let fx_rpc_body = json!({"jsonrpc": "2.0", "id": 1, "method": "list_tasks", "params": {}});
+
+        // -- Exec
+        let res = client
+            .do_post("/api/token", json!({"username": "demo1", "pwd": "welcome"}))
+            .await?;
+
+        // -- Check: token in body, no cookie
+        let res = res.json_body()?;
+        let token = res["result"]["token"].as_str().ok_or("should have token")?;
+        assert_eq!(res["result"]["token_type"], "Bearer");
+        assert!(client.cookie_value("auth-token").is_none());
+
+        // -- Exec & Check: rpc call with the Bearer token
+        let res = client
+            .reqwest_client()
+            .post(format!("{base_url}/api/rpc"))
+            .header("Authorization", format!("Bearer {token}"))
+            .header("Content-Type", "application/json")
+            .body(fx_rpc_body.to_string())
+            .send()
+            .await?
+            .text()
+            .await?;
+        let res: serde_json::Value = serde_json::from_str(&res)?;
+        assert!(res["result"].is_array(), "should be authenticated");
+
+        // -- Exec & Check: the scheme is case-insensitive
+        let res = client
+            .reqwest_client()
+            .post(format!("{base_url}/api/rpc"))
+            .header("Authorization", format!("bearer {token}"))
+            .header("Content-Type", "application/json")
+            .body(fx_rpc_body.to_string())
+            .send()
+            .await?
+            .text()
+            .await?;
+        let res: serde_json::Value = serde_json::from_str(&res)?;
+        assert!(res["result"].is_array(), "should be authenticated");
+
+        // -- Exec & Check: rpc call with a non Bearer Authorization header
+        let res = client
+            .reqwest_client()
+            .post(format!("{base_url}/api/rpc"))
+            .header("Authorization", format!("Basic {token}"))
+            .header("Content-Type", "application/json")
+            .body(fx_rpc_body.to_string())
+            .send()
+            .await?;
+        assert_eq!(res.status().as_u16(), 403);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_token_header_cookie_fallback_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let base_url = spawn_server().await?;
+        let client = new_client_login_to(&base_url, "demo1", "welcome").await?;
+        let fx_rpc_body = json!({"jsonrpc": "2.0", "id": 1, "method": "list_tasks", "params": {}});
+
+        // -- Exec & Check: a non Bearer Authorization header falls back to the cookie
+        let res = client
+            .reqwest_client()
+            .post(format!("{base_url}/api/rpc"))
+            .header("Authorization", "Basic ZGVtbzE6cHJveHk=")
+            .header("Content-Type", "application/json")
+            .body(fx_rpc_body.to_string())
+            .send()
+            .await?
+            .text()
+            .await?;
+        let res: serde_json::Value = serde_json::from_str(&res)?;
+        assert!(
+            res["result"].is_array(),
+            "should be authenticated by the cookie"
+        );
+
+        // -- Exec & Check: an invalid Bearer token does not remove the cookie
+        let res = client
+            .reqwest_client()
+            .post(format!("{base_url}/api/rpc"))
+            .header("Authorization", "Bearer not-a-token")
+            .header("Content-Type", "application/json")
+            .body(fx_rpc_body.to_string())
+            .send()
+            .await?;
+        assert_eq!(res.status().as_u16(), 403);
+        assert!(client.cookie_value("auth-token").is_some());
+        let res = rpc_call(&client, "list_tasks", json!({})).await?;
+        assert!(res["result"].is_array(), "should still be logged in");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_refresh_body_and_reuse_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let base_url = spawn_server().await?;
+        let client = httpc_test::new_client(base_url.as_str())?;
+        let res = client
+            .do_post("/api/token", json!({"username": "demo1", "pwd": "welcome"}))
+            .await?
+            .json_body()?;
+        let fx_refresh_token = res["result"]["refresh_token"]
+            .as_str()
+            .ok_or("should have refresh_token")?
+            .to_string();
+
+        // -- Exec & Check: refresh
+        let res = client
+            .do_post("/api/refresh", json!({ "refresh_token": fx_refresh_token }))
+            .await?;
+        assert_eq!(res.status(), 200);
+        let res = res.json_body()?;
+        assert!(res["result"]["token"].is_string());
+        let new_refresh_token = res["result"]["refresh_token"]
+            .as_str()
+            .ok_or("should have new refresh_token")?
+            .to_string();
+        assert_ne!(new_refresh_token, fx_refresh_token);
+
+        // -- Exec & Check: reuse of the old refresh token
+        let res = client
+            .do_post("/api/refresh", json!({ "refresh_token": fx_refresh_token }))
+            .await?;
+        assert_eq!(res.status(), 403);
+        assert_eq!(res.json_body()?["error"]["message"], "NO_AUTH");
+
+        // -- Check: the whole family is revoked
+        let res = client
+            .do_post(
+                "/api/refresh",
+                json!({ "refresh_token": new_refresh_token }),
+            )
+            .await?;
+        assert_eq!(res.status(), 403);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_refresh_cookie_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client_demo1().await?;
+
+        // -- Exec
+        let res = client.do_post("/api/refresh", json!({})).await?;
+
+        // -- Check
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.json_body()?["result"]["success"], true);
+        let res = rpc_call(&client, "list_tasks", json!({})).await?;
+        assert!(res["result"].is_array(), "should still be authenticated");
+
+        // -- Exec & Check: cookie only (no body)
+        let res = client.do_post("/api/refresh", ("", "text/plain")).await?;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.json_body()?["result"]["success"], true);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_refresh_err_no_token() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client().await?;
+
+        // -- Exec
+        let res = client.do_post("/api/refresh", json!({})).await?;
+
+        // -- Check
+        assert_eq!(res.status(), 403);
+
+        Ok(())
+    }
+}
+// endregion: -- Tests
@@ -1,54 +1,141 @@
 // NOTE: U: This is the result of the multi-crate upgrade,
 // and splitting up the old/original web/rpc/mod.rs module
 // to this file AND lib-rpc/src/lib.rs
+use crate::log::log_request;
 use crate::web::mw_auth::CtxW;
-use crate::web::Result;
-use axum::extract::State;
+use crate::web::mw_res_map::rpc_error_body;
+use crate::web::{Error, Result};
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{FromRef, Query, State};
+use axum::http::{Method, StatusCode, Uri};
 use axum::response::{IntoResponse, Response};
 use axum::routing::post;
 use axum::{Json, Router};
+use futures::future::join_all;
 use lib_core::ctx::Ctx;
+use lib_core::model::audit::AuditInfo;
 use lib_core::model::ModelManager;
-use lib_rpc::{exec_rpc, RpcRequest};
-use serde_json::{json, Value};
+use lib_rpc::{all_rpc_router, rpc_response_ok, RpcRequest, RpcRouter};
+use serde::Deserialize;
+use serde_json::Value;
 use std::sync::Arc;
 use tracing::debug;
+use uuid::Uuid;
 
 // region:    -- RPC Router & Handler
+/// The RPC routes state: the ModelManager and the lib-rpc RpcRouter
+// NOTE: FromRef makes each property a sub-state we could extract
+// on its own (e.g., State<ModelManager>).
+#[derive(Clone, FromRef)]
+pub struct RpcState {
+    pub mm: ModelManager,
+    pub rpc_router: RpcRouter,
+}
+
 pub fn routes(mm: ModelManager) -> Router {
+    let rpc_state = RpcState {
+        mm,
+        rpc_router: all_rpc_router(),
+    };
+
     Router::new()
         .route("/rpc", post(rpc_handler))
-        .with_state(mm) // Turns this Router into a Tower Service. See Jon's decrust.
+        .with_state(rpc_state) // Turns this Router into a Tower Service. See Jon's decrust.
+}
+
+/// The /api/rpc query params
+// NOTE: atomic only applies to batches (a single request is already
+// handled by one rpc method, which uses its own txn when needed).
+#[derive(Debug, Default, Deserialize)]
+struct RpcQueryParams {
+    #[serde(default)]
+    atomic: bool,
 }
 
 /// RPC basic information holding the RPC request id and method for further logging
+// NOTE: U: The req_uuid identifies the rpc call in the request log line,
+// the client error and the audit log (see lib_core::model::audit).
 #[derive(Debug)]
 pub struct RpcInfo {
     pub id: Option<Value>,
     pub method: String,
+    pub req_uuid: Uuid,
 }
 
 // NOTE: U: Replacing Ctx with CtxW (wrapper) extractor since we need to implement
 // external Traits (Ctx from lib-core & FromRequestParts from Axum) on the
 // web layer's CtxW wrapper type. We can still access the real/inner Ctx using CtxW.0
+// NOTE: U: JSON-RPC 2.0 - The body is taken as a raw JSON Value (instead of
+// Json<RpcRequest>) since it can be a single request object OR a batch (array),
+// and we want to answer invalid JSON with a proper Parse error (-32700).
+// NOTE: Only syntax errors are Parse errors. A JSON body that does not fit
+// the Value is an Invalid Request (-32600), and the other rejections (e.g.,
+// missing json content-type) keep their Axum response (415).
+// REF: https://www.jsonrpc.org/specification
 async fn rpc_handler(
-    State(mm): State<ModelManager>,
+    State(RpcState { mm, rpc_router }): State<RpcState>,
     ctx: CtxW,
-    Json(rpc_req): Json<RpcRequest>,
+    uri: Uri,
+    http_method: Method,
+    Query(rpc_query): Query<RpcQueryParams>,
+    rpc_body: core::result::Result<Json<Value>, JsonRejection>,
 ) -> Response {
     // -- U: Extract the inner/real Ctx from our new CtxW wrapper
     let ctx = ctx.0;
 
+    let Json(rpc_body) = match rpc_body {
+        Ok(rpc_body) => rpc_body,
+        Err(JsonRejection::JsonSyntaxError(rej)) => {
+            return Error::RpcReqJsonParseFail(rej.body_text()).into_response()
+        }
+        Err(JsonRejection::JsonDataError(rej)) => {
+            let reason = rej.body_text();
+            return Error::Rpc(lib_rpc::Error::RpcRequestInvalid { reason }).into_response();
+        }
+        Err(rej) => return rej.into_response(),
+    };
+
+    match rpc_body {
+        Value::Array(rpc_reqs) => {
+            let atomic = rpc_query.atomic;
+            rpc_batch_handler(ctx, mm, &rpc_router, uri, http_method, rpc_reqs, atomic).await
+        }
+        rpc_req => rpc_single_handler(ctx, mm, &rpc_router, rpc_req).await,
+    }
+}
+
+/// Execute a single JSON-RPC request object
+// NOTE: Errors are returned as web::Error responses, and mw_response_map
+// builds the JSON-RPC error body (with the id from the RpcInfo).
+async fn rpc_single_handler(
+    ctx: Ctx,
+    mm: ModelManager,
+    rpc_router: &RpcRouter,
+    rpc_req: Value,
+) -> Response {
+    let rpc_req = match RpcRequest::from_value(rpc_req) {
+        Ok(rpc_req) => rpc_req,
+        Err(err) => return Error::Rpc(err).into_response(),
+    };
+
     // -- Create the RpcInfo to be set to the response.extensions
     // We'll later get/retrieve it for server login, request log line,
     // and errors we send back to the client.
     let rpc_info = RpcInfo {
         id: rpc_req.id.clone(),
         method: rpc_req.method.clone(),
+        req_uuid: Uuid::new_v4(),
     };
+    let is_notification = rpc_req.is_notification();
 
     // -- Execute & Store RpcInfo in response
-    let mut response = _rpc_handler(ctx, mm, rpc_req).await.into_response();
+    let res = _rpc_handler(ctx, mm, rpc_router, rpc_req, rpc_info.req_uuid).await;
+    let mut response = match res {
+        // NOTE: Notifications (no "id") are executed but get no response body.
+        Ok(_) if is_notification => StatusCode::NO_CONTENT.into_response(),
+        Ok(result) => Json(rpc_response_ok(rpc_info.id.clone(), result)).into_response(),
+        Err(err) => err.into_response(),
+    };
     // NOTE: !! U: With Tower update, we now are inserting an Arc type into
     // the response extensions, so when we try to retrieve/extract this RpcInfo,
     // we actually have to extract the Arc type, not RpcInfo.
@@ -57,21 +144,1063 @@ async fn rpc_handler(
     response
 }
 
-/// Route based on RPC method and return a JSON result
-async fn _rpc_handler(ctx: Ctx, mm: ModelManager, rpc_req: RpcRequest) -> Result<Json<Value>> {
+/// Execute a JSON-RPC batch (array of request objects)
+// NOTE: A batch is a single http request, so mw_response_map only sees the
+// final (200) response. Each item error is therefore turned into its JSON-RPC
+// error object and logged (one request log line per item) right here.
+// Responses are in the same order as the requests, and notifications are skipped.
+// NOTE: U: By default the items run concurrently, each on its own. With
+// /api/rpc?atomic=true they run in order in one transaction (all or nothing).
+async fn rpc_batch_handler(
+    ctx: Ctx,
+    mm: ModelManager,
+    rpc_router: &RpcRouter,
+    uri: Uri,
+    http_method: Method,
+    rpc_reqs: Vec<Value>,
+    atomic: bool,
+) -> Response {
+    // NOTE: An empty batch is a single Invalid Request error (not an empty array).
+    if rpc_reqs.is_empty() {
+        return Error::Rpc(lib_rpc::Error::RpcRequestInvalid {
+            reason: "empty batch".to_string(),
+        })
+        .into_response();
+    }
+
+    // -- Execute
+    let rpc_results = if atomic {
+        match rpc_batch_exec_atomic(&ctx, &mm, rpc_router, rpc_reqs).await {
+            Ok(rpc_results) => rpc_results,
+            Err(err) => return err.into_response(),
+        }
+    } else {
+        let rpc_res_futs = rpc_reqs
+            .into_iter()
+            .map(|rpc_req| rpc_batch_item_exec(ctx.clone(), mm.clone(), rpc_router, rpc_req));
+        join_all(rpc_res_futs).await
+    };
+
+    // -- Build the responses (& log the errors)
+    let mut rpc_responses: Vec<Value> = Vec::new();
+    for (rpc_info, res) in rpc_results {
+        // NOTE: Notifications get no entry in the batch response.
+        let is_notification = rpc_info.as_ref().is_some_and(|rpc| rpc.id.is_none());
+        let rpc_id = rpc_info.as_ref().and_then(|rpc| rpc.id.clone());
+
+        match res {
+            Ok(_) if is_notification => (),
+            Ok(result) => rpc_responses.push(rpc_response_ok(rpc_id, result)),
+            Err(err) => {
+                let uuid = rpc_info
+                    .as_ref()
+                    .map(|rpc| rpc.req_uuid)
+                    .unwrap_or_else(Uuid::new_v4);
+                let (_, client_error) = err.client_status_and_error();
+                let body = rpc_error_body(rpc_id, &client_error, uuid);
+                // TODO: Need to handle if log_request fails (same as mw_response_map)
+                let _ = log_request(
+                    uuid,
+                    http_method.clone(),
+                    uri.clone(),
+                    rpc_info.as_ref(),
+                    Some(ctx.clone()),
+                    Some(&err),
+                    Some(client_error),
+                )
+                .await;
+                if !is_notification {
+                    rpc_responses.push(body);
+                }
+            }
+        }
+    }
+
+    // NOTE: A batch of only notifications gets no response body at all.
+    if rpc_responses.is_empty() {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        Json(Value::Array(rpc_responses)).into_response()
+    }
+}
+
+/// Execute the batch items in order, in one transaction.
+// NOTE: On the first failing item, the txn is rolled back, and all the other
+// items (the ones executed before, rolled back, and the ones after, not executed)
+// get a RPC_BATCH_ABORTED error, so the client knows nothing was applied.
+async fn rpc_batch_exec_atomic(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    rpc_router: &RpcRouter,
+    rpc_reqs: Vec<Value>,
+) -> Result<Vec<(Option<RpcInfo>, Result<Value>)>> {
+    let mm = mm.new_with_txn()?;
+    mm.begin_txn().await?;
+
+    let mut rpc_results = Vec::with_capacity(rpc_reqs.len());
+    let mut failed = false;
+    for rpc_req in rpc_reqs {
+        if failed {
+            let rpc_info = RpcRequest::from_value(rpc_req).ok().map(|rpc_req| RpcInfo {
+                id: rpc_req.id,
+                method: rpc_req.method,
+                req_uuid: Uuid::new_v4(),
+            });
+            rpc_results.push((rpc_info, Err(Error::RpcBatchAborted)));
+            continue;
+        }
+
+        let (rpc_info, res) =
+            rpc_batch_item_exec(ctx.clone(), mm.clone(), rpc_router, rpc_req).await;
+        failed = res.is_err();
+        rpc_results.push((rpc_info, res));
+    }
+
+    if failed {
+        mm.rollback_txn().await?;
+        for (_, res) in rpc_results.iter_mut().filter(|(_, res)| res.is_ok()) {
+            *res = Err(Error::RpcBatchAborted);
+        }
+    } else {
+        mm.commit_txn().await?;
+    }
+
+    Ok(rpc_results)
+}
+
+/// Parse & Execute one batch item
+/// (the RpcInfo is None when the item is not a valid request object)
+async fn rpc_batch_item_exec(
+    ctx: Ctx,
+    mm: ModelManager,
+    rpc_router: &RpcRouter,
+    rpc_req: Value,
+) -> (Option<RpcInfo>, Result<Value>) {
+    match RpcRequest::from_value(rpc_req) {
+        Ok(rpc_req) => {
+            let rpc_info = RpcInfo {
+                id: rpc_req.id.clone(),
+                method: rpc_req.method.clone(),
+                req_uuid: Uuid::new_v4(),
+            };
+            let res = _rpc_handler(ctx, mm, rpc_router, rpc_req, rpc_info.req_uuid).await;
+            (Some(rpc_info), res)
+        }
+        Err(err) => (None, Err(Error::Rpc(err))),
+    }
+}
+
+/// Route based on RPC method and return the JSON result
+// NOTE: U: The rpc method writes are recorded in the audit log with the
+// rpc method and req_uuid (see lib_core::model::audit).
+async fn _rpc_handler(
+    ctx: Ctx,
+    mm: ModelManager,
+    rpc_router: &RpcRouter,
+    rpc_req: RpcRequest,
+    req_uuid: Uuid,
+) -> Result<Value> {
     let rpc_method = rpc_req.method.clone();
-    let rpc_id = rpc_req.id.clone();
 
     debug!("{:<12} - _rpc_handler - method: {rpc_method}", "HANDLER");
 
-    let result = exec_rpc(ctx, mm, rpc_req).await?;
-
-    // Now that we have our JSON result, time to send our JSON response
-    let body_response = json!({
-    "id": rpc_id,
-    "result": result
+    let mm = mm.with_audit(AuditInfo {
+        rpc_method,
+        req_uuid,
     });
+    let result = rpc_router.call(ctx, mm, rpc_req).await?;
 
-    Ok(Json(body_response))
+    Ok(result)
 }
 // endregion:    -- RPC Router & Handler
+
+// region:    -- Tests
+#[cfg(test)]
+mod tests {
+    #![allow(unused)]
+    use crate::web::_test_utils::{new_client_admin1, new_client_demo1, rpc_call, Result};
+    use serde_json::json;
+    use serial_test::serial;
+
+    // NOTE: #[serial] goes AFTER #[tokio::test] here, since the httpc-test
+    // client futures are not Send (required by serial's async wrapper).
+
+    #[tokio::test]
+    #[serial]
+    async fn test_rpc_task_crud_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client_demo1().await?;
+        let fx_title = "test_rpc_task_crud_ok - task 01";
+        let fx_title_new = "test_rpc_task_crud_ok - task 01 - new";
+
+        // -- Exec & Check: create_task
+        let res = rpc_call(&client, "create_task", json!({"data": {"title": fx_title}})).await?;
+        let id = res["result"]["id"]
+            .as_i64()
+            .ok_or("create_task should return id")?;
+        assert_eq!(res["result"]["title"], fx_title);
+
+        // -- Exec & Check: get_task
+        let res = rpc_call(&client, "get_task", json!({ "id": id })).await?;
+        assert_eq!(res["result"]["id"], id);
+        assert_eq!(res["result"]["title"], fx_title);
+
+        // -- Exec & Check: update_task
+        let res = rpc_call(
+            &client,
+            "update_task",
+            json!({"id": id, "data": {"title": fx_title_new, "done": true}}),
+        )
+        .await?;
+        assert_eq!(res["result"]["title"], fx_title_new);
+        assert_eq!(res["result"]["done"], true);
+
+        // -- Exec & Check: list_tasks
+        let res = rpc_call(
+            &client,
+            "list_tasks",
+            json!({"filters": {"title": {"$startsWith": "test_rpc_task_crud_ok"}}}),
+        )
+        .await?;
+        let tasks = res["result"]
+            .as_array()
+            .ok_or("list_tasks should return array")?;
+        assert_eq!(tasks.len(), 1);
+
+        // -- Exec & Check: delete_task
+        let res = rpc_call(&client, "delete_task", json!({ "id": id })).await?;
+        assert_eq!(res["result"]["id"], id);
+        let res = rpc_call(&client, "get_task", json!({ "id": id })).await?;
+        assert_eq!(res["error"]["message"], "ENTITY_NOT_FOUND");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_rpc_token_crud_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client_demo1().await?;
+        let admin_client = new_client_admin1().await?;
+        let fx_symbol = "TESTRPC";
+        let fx_mc = 1234.5;
+
+        // -- Exec & Check: create_token
+        // NOTE: TokenForCreate is camelCase (Birdeye format)
+        let res = rpc_call(
+            &admin_client,
+            "create_token",
+            json!({"data": {
+                "updateUnixTime": 1710403689,
+                "updateTime": "2024-03-14T08:08:09",
+                "address": "test_rpc_token_crud_ok-address",
+                "decimals": 6,
+                "liquidity": 100.0,
+                "logoURI": "https://example.com/logo.png",
+                "symbol": fx_symbol,
+                "name": "Test Rpc Token",
+                "mc": 1000.0,
+                "v24hChangePercent": 1.5,
+                "v24hUSD": 10.0,
+                "lastTradeUnixTime": 1710395665
+            }}),
+        )
+        .await?;
+        let id = res["result"]["id"]
+            .as_i64()
+            .ok_or("create_token should return id")?;
+        assert_eq!(res["result"]["symbol"], fx_symbol);
+
+        // -- Exec & Check: get_token
+        let res = rpc_call(&client, "get_token", json!({ "id": id })).await?;
+        assert_eq!(res["result"]["id"], id);
+        assert_eq!(res["result"]["logoURI"], "https://example.com/logo.png");
+
+        // -- Exec & Check: update_token
+        let res = rpc_call(
+            &admin_client,
+            "update_token",
+            json!({"id": id, "data": {"mc": fx_mc}}),
+        )
+        .await?;
+        assert_eq!(res["result"]["mc"], fx_mc);
+
+        // -- Exec & Check: create/update_token (admin only)
+        let res = rpc_call(&client, "create_token", json!({"data": {}})).await?;
+        assert_eq!(res["error"]["message"], "ACCESS_DENIED");
+        let res = rpc_call(
+            &client,
+            "update_token",
+            json!({"id": id, "data": {"mc": 1.0}}),
+        )
+        .await?;
+        assert_eq!(res["error"]["message"], "ACCESS_DENIED");
+
+        // -- Exec & Check: list_tokens
+        let res = rpc_call(
+            &client,
+            "list_tokens",
+            json!({"filters": {"symbol": fx_symbol}}),
+        )
+        .await?;
+        let tokens = res["result"]
+            .as_array()
+            .ok_or("list_tokens should return array")?;
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0]["id"], id);
+
+        // -- Exec & Check: delete_token (admin only)
+        let res = rpc_call(&client, "delete_token", json!({ "id": id })).await?;
+        assert_eq!(res["error"]["message"], "ACCESS_DENIED");
+        assert_eq!(res["error"]["code"], -32004);
+        let res = rpc_call(&admin_client, "delete_token", json!({ "id": id })).await?;
+        assert_eq!(res["result"]["id"], id);
+        let res = rpc_call(&client, "get_token", json!({ "id": id })).await?;
+        assert_eq!(res["error"]["message"], "ENTITY_NOT_FOUND");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_rpc_token_history_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client_demo1().await?;
+        let admin_client = new_client_admin1().await?;
+        let fx_t0 = 1710403200; // hour aligned
+        let res = rpc_call(
+            &admin_client,
+            "create_token",
+            json!({"data": {
+                "updateUnixTime": fx_t0,
+                "updateTime": "2024-03-14T08:00:00",
+                "address": "test_rpc_token_history_ok-address",
+                "decimals": 6,
+                "liquidity": 100.0,
+                "logoURI": "https://example.com/logo.png",
+                "symbol": "TESTHIST",
+                "name": "Test History Token",
+                "mc": 1.0,
+                "v24hChangePercent": 1.5,
+                "v24hUSD": 10.0,
+                "lastTradeUnixTime": fx_t0
+            }}),
+        )
+        .await?;
+        let id = res["result"]["id"].as_i64().ok_or("should have id")?;
+        rpc_call(
+            &admin_client,
+            "update_token",
+            json!({"id": id, "data": {"update_unix_time": fx_t0 + 60, "mc": 2.0}}),
+        )
+        .await?;
+
+        // -- Exec & Check: raw snapshots
+        let res = rpc_call(&client, "get_token_history", json!({ "token_id": id })).await?;
+        let snapshots = res["result"].as_array().ok_or("should have snapshots")?;
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[1]["mc"], 2.0);
+
+        // -- Exec & Check: hourly buckets
+        let res = rpc_call(
+            &client,
+            "get_token_history",
+            json!({"token_id": id, "from": fx_t0, "bucket": "hour"}),
+        )
+        .await?;
+        let buckets = res["result"].as_array().ok_or("should have buckets")?;
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0]["count"], 2);
+        assert_eq!(buckets[0]["mc_min"], 1.0);
+        assert_eq!(buckets[0]["mc_last"], 2.0);
+
+        // -- Clean
+        rpc_call(&admin_client, "delete_token", json!({ "id": id })).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_rpc_token_analytics_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client_demo1().await?;
+        let admin_client = new_client_admin1().await?;
+        let fx_prefix = "test_rpc_token_analytics_ok";
+        // (symbol, mc, liquidity, v24h_change_percent)
+        let fx_tokens = [("TA1", 10.0, 1e15, 7.0), ("TA2", 30.0, 1e15, -3.0)];
+        let mut ids = Vec::new();
+        for (symbol, mc, liquidity, v24h_change_percent) in fx_tokens {
+            let res = rpc_call(
+                &admin_client,
+                "create_token",
+                json!({"data": {
+                    "updateUnixTime": 1710403200,
+                    "updateTime": "2024-03-14T08:00:00",
+                    "address": format!("{fx_prefix}-{symbol}"),
+                    "decimals": 6,
+                    "liquidity": liquidity,
+                    "logoURI": "https://example.com/logo.png",
+                    "symbol": symbol,
+                    "name": "Test Analytics Token",
+                    "mc": mc,
+                    "v24hChangePercent": v24h_change_percent,
+                    "v24hUSD": 10.0,
+                    "lastTradeUnixTime": 1710403200
+                }}),
+            )
+            .await?;
+            ids.push(res["result"]["id"].as_i64().ok_or("should have id")?);
+        }
+        let fx_filters = json!({"address": {"$startsWith": fx_prefix}});
+
+        // -- Exec & Check: top movers
+        let res = rpc_call(
+            &client,
+            "token_top_movers",
+            json!({"filters": fx_filters, "direction": "losers"}),
+        )
+        .await?;
+        let tokens = res["result"].as_array().ok_or("should have tokens")?;
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0]["symbol"], "TA2");
+
+        // -- Exec & Check: rank
+        let res = rpc_call(&client, "token_rank", json!({ "filters": fx_filters })).await?;
+        let ranks = res["result"].as_array().ok_or("should have ranks")?;
+        assert_eq!(ranks.len(), 2);
+        assert_eq!(ranks[0]["rank"], 1);
+        assert_eq!(ranks[0]["symbol"], "TA2");
+        assert_eq!(ranks[1]["rank"], 2);
+        let res = rpc_call(
+            &client,
+            "token_rank",
+            json!({"filters": fx_filters, "limit": 1, "offset": 1}),
+        )
+        .await?;
+        let ranks = res["result"].as_array().ok_or("should have ranks")?;
+        assert_eq!(ranks.len(), 1);
+        assert_eq!(ranks[0]["rank"], 2);
+
+        // -- Exec & Check: rank does not take the list paging/order
+        let res = rpc_call(
+            &client,
+            "token_rank",
+            json!({"filters": fx_filters, "paged": true}),
+        )
+        .await?;
+        assert_eq!(res["error"]["message"], "RPC_PARAMS_INVALID");
+
+        // -- Exec & Check: stats
+        let res = rpc_call(&client, "token_stats", json!({ "filters": fx_filters })).await?;
+        let stats = &res["result"];
+        assert_eq!(stats["count"], 2);
+        assert_eq!(stats["mc"]["sum"], 40.0);
+        assert_eq!(stats["mc"]["avg"], 20.0);
+        assert_eq!(stats["mc"]["p50"], 20.0);
+
+        // -- Clean
+        for id in ids {
+            rpc_call(&admin_client, "delete_token", json!({ "id": id })).await?;
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_rpc_watchlist_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client_demo1().await?;
+        let admin_client = new_client_admin1().await?;
+        let fx_address = "test_rpc_watchlist_ok-address";
+        let res = rpc_call(
+            &admin_client,
+            "create_token",
+            json!({"data": {
+                "updateUnixTime": 1710403200,
+                "updateTime": "2024-03-14T08:00:00",
+                "address": fx_address,
+                "decimals": 6,
+                "liquidity": 100.0,
+                "logoURI": "https://example.com/logo.png",
+                "symbol": "TESTWL",
+                "name": "Test Watchlist Token",
+                "mc": 1.0,
+                "v24hChangePercent": 1.5,
+                "v24hUSD": 10.0,
+                "lastTradeUnixTime": 1710403200
+            }}),
+        )
+        .await?;
+        let token_id = res["result"]["id"].as_i64().ok_or("should have id")?;
+        let res = rpc_call(
+            &client,
+            "create_watchlist",
+            json!({"data": {"name": "test_rpc_watchlist_ok"}}),
+        )
+        .await?;
+        let id = res["result"]["id"].as_i64().ok_or("should have id")?;
+
+        // -- Exec & Check: add by address, list
+        let res = rpc_call(
+            &client,
+            "add_watchlist_token",
+            json!({"watchlist_id": id, "address": fx_address}),
+        )
+        .await?;
+        assert_eq!(res["result"][0]["id"], token_id);
+        let res = rpc_call(&client, "list_watchlist_tokens", json!({ "id": id })).await?;
+        let tokens = res["result"].as_array().ok_or("should have tokens")?;
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0]["symbol"], "TESTWL");
+
+        // -- Exec & Check: rename
+        let res = rpc_call(
+            &client,
+            "update_watchlist",
+            json!({"id": id, "data": {"name": "test_rpc_watchlist_ok-renamed"}}),
+        )
+        .await?;
+        assert_eq!(res["result"]["name"], "test_rpc_watchlist_ok-renamed");
+
+        // -- Exec & Check: unknown address
+        let res = rpc_call(
+            &client,
+            "add_watchlist_token",
+            json!({"watchlist_id": id, "address": "test_rpc_watchlist_ok-none"}),
+        )
+        .await?;
+        assert_eq!(res["error"]["code"], -32003);
+        assert_eq!(
+            res["error"]["data"]["detail"]["address"],
+            "test_rpc_watchlist_ok-none"
+        );
+
+        // -- Exec & Check: remove by id
+        let res = rpc_call(
+            &client,
+            "remove_watchlist_token",
+            json!({"watchlist_id": id, "token_id": token_id}),
+        )
+        .await?;
+        assert_eq!(res["result"], json!([]));
+
+        // -- Clean
+        rpc_call(&client, "delete_watchlist", json!({ "id": id })).await?;
+        rpc_call(&admin_client, "delete_token", json!({ "id": token_id })).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_rpc_alert_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client_demo1().await?;
+        let admin_client = new_client_admin1().await?;
+        let fx_address = "test_rpc_alert_ok-address";
+        let res = rpc_call(
+            &admin_client,
+            "create_token",
+            json!({"data": {
+                "updateUnixTime": 1710403200,
+                "updateTime": "2024-03-14T08:00:00",
+                "address": fx_address,
+                "decimals": 6,
+                "liquidity": 100.0,
+                "logoURI": "https://example.com/logo.png",
+                "symbol": "TESTAL",
+                "name": "Test Alert Token",
+                "mc": 1.0,
+                "v24hChangePercent": 1.5,
+                "v24hUSD": 10.0,
+                "lastTradeUnixTime": 1710403200
+            }}),
+        )
+        .await?;
+        let token_id = res["result"]["id"].as_i64().ok_or("should have id")?;
+        let res = rpc_call(
+            &client,
+            "create_alert_rule",
+            json!({"data": {
+                "address": fx_address,
+                "metric": "v24h_change_percent",
+                "comparator": "lte",
+                "threshold": -10.0
+            }}),
+        )
+        .await?;
+        let rule_id = res["result"]["id"].as_i64().ok_or("should have id")?;
+        assert_eq!(res["result"]["token_id"], token_id);
+        assert_eq!(res["result"]["cooldown_sec"], 3600);
+
+        // -- Exec: the token drops
+        rpc_call(
+            &admin_client,
+            "update_token",
+            json!({"id": token_id, "data": {"v24h_change_percent": -25.5}}),
+        )
+        .await?;
+
+        // -- Check
+        let res = rpc_call(
+            &client,
+            "list_alert_events",
+            json!({"filters": {"rule_id": rule_id}}),
+        )
+        .await?;
+        let events = res["result"].as_array().ok_or("should have events")?;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["value"], -25.5);
+        assert_eq!(events[0]["comparator"], "lte");
+
+        // -- Exec & Check: invalid metric
+        let res = rpc_call(
+            &client,
+            "create_alert_rule",
+            json!({"data": {
+                "token_id": token_id,
+                "metric": "price",
+                "comparator": "gt",
+                "threshold": 1.0
+            }}),
+        )
+        .await?;
+        assert_eq!(res["error"]["code"], -32602);
+
+        // -- Clean (rules & events deleted by cascade)
+        rpc_call(&admin_client, "delete_token", json!({ "id": token_id })).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_rpc_err_method_unknown() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client_demo1().await?;
+
+        // -- Exec
+        let res = rpc_call(&client, "no_such_method", json!({})).await?;
+
+        // -- Check
+        assert_eq!(res["jsonrpc"], "2.0");
+        assert_eq!(res["id"], 1);
+        assert_eq!(res["error"]["code"], -32601);
+        assert_eq!(res["error"]["message"], "RPC_METHOD_UNKNOWN");
+        assert_eq!(res["error"]["data"]["detail"], "no_such_method");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_rpc_err_params() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client_demo1().await?;
+
+        // -- Exec & Check: invalid params
+        let res = rpc_call(&client, "get_task", json!({"id": "not-a-number"})).await?;
+        assert_eq!(res["error"]["code"], -32602);
+        assert_eq!(res["error"]["message"], "RPC_PARAMS_INVALID");
+
+        // -- Exec & Check: missing params
+        let res = client
+            .do_post(
+                "/api/rpc",
+                json!({"jsonrpc": "2.0", "id": 2, "method": "get_task"}),
+            )
+            .await?
+            .json_body()?;
+        assert_eq!(res["id"], 2);
+        assert_eq!(res["error"]["code"], -32600);
+        assert_eq!(res["error"]["message"], "RPC_REQUEST_INVALID");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_rpc_err_request_invalid() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client_demo1().await?;
+
+        // -- Exec & Check: wrong jsonrpc version
+        let res = client
+            .do_post(
+                "/api/rpc",
+                json!({"jsonrpc": "1.0", "id": 1, "method": "list_tasks"}),
+            )
+            .await?;
+        assert_eq!(res.status(), 400);
+        let res = res.json_body()?;
+        assert_eq!(res["id"], json!(null));
+        assert_eq!(res["error"]["code"], -32600);
+
+        // -- Exec & Check: not json
+        let res = client
+            .do_post("/api/rpc", ("{not json", "application/json"))
+            .await?
+            .json_body()?;
+        assert_eq!(res["error"]["code"], -32700);
+        assert_eq!(res["error"]["message"], "RPC_PARSE_FAIL");
+
+        // -- Exec & Check: not a json content-type
+        let res = client.do_post("/api/rpc", ("{}", "text/plain")).await?;
+        assert_eq!(res.status(), 415);
+
+        // -- Exec & Check: empty batch
+        let res = client.do_post("/api/rpc", json!([])).await?.json_body()?;
+        assert_eq!(res["error"]["code"], -32600);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_rpc_notification_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client_demo1().await?;
+        let fx_title = "test_rpc_notification_ok - task 01";
+
+        // -- Exec
+        let res = client
+            .do_post(
+                "/api/rpc",
+                json!({"jsonrpc": "2.0", "method": "create_task", "params": {"data": {"title": fx_title}}}),
+            )
+            .await?;
+
+        // -- Check: no body, but the task was created
+        assert_eq!(res.status(), 204);
+        let res = rpc_call(
+            &client,
+            "list_tasks",
+            json!({"filters": {"title": fx_title}}),
+        )
+        .await?;
+        assert_eq!(res["result"].as_array().map(|v| v.len()), Some(1));
+
+        // -- Exec & Check: notification errors have no body either
+        let res = client
+            .do_post(
+                "/api/rpc",
+                json!({"jsonrpc": "2.0", "method": "no_such_method"}),
+            )
+            .await?;
+        assert_eq!(res.status(), 204);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_rpc_batch_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client_demo1().await?;
+        let fx_title = "test_rpc_batch_ok - task 01";
+
+        // -- Exec
+        let res = client
+            .do_post(
+                "/api/rpc",
+                json!([
+                    {"jsonrpc": "2.0", "id": "a", "method": "create_task", "params": {"data": {"title": fx_title}}},
+                    {"jsonrpc": "2.0", "method": "list_tasks", "params": {}},
+                    {"jsonrpc": "2.0", "id": "b", "method": "no_such_method"},
+                    {"jsonrpc": "2.0", "id": "c", "method": "get_task", "params": {"id": 999999}},
+                    {"foo": "bar"},
+                    {"jsonrpc": "2.0", "id": 4, "method": "list_tasks", "params": {}}
+                ]),
+            )
+            .await?;
+
+        // -- Check
+        assert_eq!(res.status(), 200);
+        let res = res.json_body()?;
+        let res = res.as_array().ok_or("batch should return array")?;
+        // The notification has no entry; the rest keep the request order.
+        assert_eq!(res.len(), 5);
+        assert_eq!(res[0]["id"], "a");
+        assert_eq!(res[0]["result"]["title"], fx_title);
+        assert_eq!(res[1]["id"], "b");
+        assert_eq!(res[1]["error"]["code"], -32601);
+        assert_eq!(res[2]["id"], "c");
+        assert_eq!(res[2]["error"]["message"], "ENTITY_NOT_FOUND");
+        assert_eq!(res[2]["error"]["code"], -32003);
+        assert_eq!(res[3]["id"], json!(null));
+        assert_eq!(res[3]["error"]["code"], -32600);
+        assert_eq!(res[4]["id"], 4);
+        assert_eq!(res[4]["jsonrpc"], "2.0");
+        assert!(res[4]["result"].is_array());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_rpc_batch_atomic_ok_and_err_aborted() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client_demo1().await?;
+        let fx_title_ok = "test_rpc_batch_atomic - task ok";
+        let fx_title_aborted = "test_rpc_batch_atomic - task aborted";
+
+        // -- Exec & Check: all ok, committed
+        let res = client
+            .do_post(
+                "/api/rpc?atomic=true",
+                json!([
+                    {"jsonrpc": "2.0", "id": 1, "method": "create_task", "params": {"data": {"title": fx_title_ok}}},
+                    {"jsonrpc": "2.0", "id": 2, "method": "list_tasks", "params": {"filters": {"title": fx_title_ok}}}
+                ]),
+            )
+            .await?;
+        let res = res.json_body()?;
+        assert_eq!(res[0]["result"]["title"], fx_title_ok);
+        // The second item sees the first one (same txn)
+        assert_eq!(res[1]["result"][0]["title"], fx_title_ok);
+        let task_id = res[0]["result"]["id"].clone();
+
+        // -- Exec & Check: one failing item aborts the whole batch
+        let res = client
+            .do_post(
+                "/api/rpc?atomic=true",
+                json!([
+                    {"jsonrpc": "2.0", "id": 1, "method": "create_task", "params": {"data": {"title": fx_title_aborted}}},
+                    {"jsonrpc": "2.0", "id": 2, "method": "get_task", "params": {"id": 999999}},
+                    {"jsonrpc": "2.0", "id": 3, "method": "delete_task", "params": {"id": task_id}}
+                ]),
+            )
+            .await?;
+        assert_eq!(res.status(), 200);
+        let res = res.json_body()?;
+        assert_eq!(res[0]["error"]["message"], "RPC_BATCH_ABORTED");
+        assert_eq!(res[0]["error"]["code"], -32008);
+        assert_eq!(res[1]["error"]["message"], "ENTITY_NOT_FOUND");
+        assert_eq!(res[2]["error"]["message"], "RPC_BATCH_ABORTED");
+
+        // -- Check: nothing applied
+        let res = rpc_call(
+            &client,
+            "list_tasks",
+            json!({"filters": {"title": {"$startsWith": "test_rpc_batch_atomic"}}}),
+        )
+        .await?;
+        let titles: Vec<_> = res["result"]
+            .as_array()
+            .ok_or("list_tasks should return array")?
+            .iter()
+            .map(|task| task["title"].clone())
+            .collect();
+        assert_eq!(titles, vec![json!(fx_title_ok)]);
+
+        // -- Clean
+        rpc_call(&client, "delete_task", json!({ "id": task_id })).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_rpc_list_tasks_paged_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client_demo1().await?;
+        let fx_prefix = "test_rpc_list_tasks_paged_ok";
+        let mut fx_ids = Vec::new();
+        for i in 1..=3 {
+            let res = rpc_call(
+                &client,
+                "create_task",
+                json!({"data": {"title": format!("{fx_prefix} - task {i}")}}),
+            )
+            .await?;
+            fx_ids.push(res["result"]["id"].clone());
+        }
+        let fx_filters = json!({"title": {"$startsWith": fx_prefix}});
+
+        // -- Exec & Check: first page
+        let res = rpc_call(
+            &client,
+            "list_tasks",
+            json!({"filters": fx_filters, "list_options": {"limit": 2}, "paged": true}),
+        )
+        .await?;
+        let page = &res["result"];
+        assert_eq!(page["total"], 3);
+        assert_eq!(page["items"].as_array().map(|a| a.len()), Some(2));
+        let cursor = page["next_cursor"]
+            .as_str()
+            .ok_or("should have next_cursor")?;
+
+        // -- Exec & Check: next (last) page
+        let res = rpc_call(
+            &client,
+            "list_tasks",
+            json!({"filters": fx_filters, "list_options": {"limit": 2}, "cursor": cursor}),
+        )
+        .await?;
+        let page = &res["result"];
+        assert_eq!(page["items"][0]["id"], fx_ids[2]);
+        assert_eq!(page["next_cursor"], json!(null));
+
+        // -- Exec & Check: not paged, still the plain array
+        let res = rpc_call(&client, "list_tasks", json!({ "filters": fx_filters })).await?;
+        assert_eq!(res["result"].as_array().map(|a| a.len()), Some(3));
+
+        // -- Exec & Check: invalid cursor
+        let res = rpc_call(&client, "list_tasks", json!({ "cursor": "nope" })).await?;
+        assert_eq!(res["error"]["message"], "LIST_OPTIONS_INVALID");
+        assert_eq!(res["error"]["code"], -32602);
+
+        // -- Clean
+        for id in fx_ids {
+            rpc_call(&client, "delete_task", json!({ "id": id })).await?;
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_rpc_create_delete_tasks_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client_demo1().await?;
+        let fx_prefix = "test_rpc_create_delete_tasks_ok";
+        let fx_data: Vec<_> = (1..=3)
+            .map(|i| json!({"title": format!("{fx_prefix} - task {i}")}))
+            .collect();
+
+        // -- Exec & Check: create_tasks
+        let res = rpc_call(&client, "create_tasks", json!({ "data": fx_data })).await?;
+        let ids = res["result"].as_array().ok_or("should have ids")?.clone();
+        assert_eq!(ids.len(), 3);
+        let res = rpc_call(&client, "get_task", json!({ "id": ids[0] })).await?;
+        assert_eq!(res["result"]["title"], format!("{fx_prefix} - task 1"));
+
+        // -- Exec & Check: delete_tasks requires filters
+        let res = rpc_call(&client, "delete_tasks", json!({ "filters": [] })).await?;
+        assert_eq!(res["error"]["message"], "FILTERS_EMPTY");
+        assert_eq!(res["error"]["code"], -32602);
+
+        // -- Exec & Check: delete_tasks
+        let res = rpc_call(
+            &client,
+            "delete_tasks",
+            json!({"filters": {"title": {"$startsWith": fx_prefix}}}),
+        )
+        .await?;
+        assert_eq!(res["result"].as_array().map(|a| a.len()), Some(3));
+        let res = rpc_call(&client, "get_task", json!({ "id": ids[0] })).await?;
+        assert_eq!(res["error"]["message"], "ENTITY_NOT_FOUND");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_rpc_user_admin_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client_demo1().await?;
+        let admin_client = new_client_admin1().await?;
+        let fx_username = "test_rpc_user_admin_ok-user";
+        admin_client
+            .do_post(
+                "/api/register",
+                json!({"username": fx_username, "pwd": "pwd-01"}),
+            )
+            .await?;
+
+        // -- Exec & Check: not admin
+        let res = rpc_call(&client, "list_users", json!({})).await?;
+        assert_eq!(res["error"]["message"], "ACCESS_DENIED");
+
+        // -- Exec & Check: list_users
+        let res = rpc_call(
+            &admin_client,
+            "list_users",
+            json!({"filters": {"username": fx_username}}),
+        )
+        .await?;
+        let users = res["result"]
+            .as_array()
+            .ok_or("list_users should return array")?;
+        assert_eq!(users.len(), 1);
+        let id = users[0]["id"].as_i64().ok_or("user should have id")?;
+
+        // -- Exec & Check: delete_user
+        let res = rpc_call(&client, "delete_user", json!({ "id": id })).await?;
+        assert_eq!(res["error"]["message"], "ACCESS_DENIED");
+        let res = rpc_call(
+            &admin_client,
+            "list_users",
+            json!({"filters": {"username": "admin1"}}),
+        )
+        .await?;
+        let admin_id = res["result"][0]["id"]
+            .as_i64()
+            .ok_or("admin1 should have id")?;
+        let res = rpc_call(&admin_client, "delete_user", json!({ "id": admin_id })).await?;
+        assert_eq!(res["error"]["message"], "USER_DELETE_NOT_ALLOWED");
+        assert_eq!(res["error"]["code"], -32012);
+        let res = rpc_call(&admin_client, "delete_user", json!({ "id": id })).await?;
+        assert_eq!(res["result"]["username"], fx_username);
+        let res = rpc_call(
+            &admin_client,
+            "list_users",
+            json!({"filters": {"username": fx_username}}),
+        )
+        .await?;
+        assert_eq!(res["result"].as_array().map(|v| v.len()), Some(0));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_rpc_audit_log_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let client = new_client_demo1().await?;
+        let admin_client = new_client_admin1().await?;
+        let fx_title = "test_rpc_audit_log_ok title";
+
+        // -- Exec: the audited writes
+        let res = rpc_call(&client, "create_task", json!({"data": {"title": fx_title}})).await?;
+        let id = res["result"]["id"].as_i64().ok_or("should have id")?;
+        rpc_call(
+            &client,
+            "update_task",
+            json!({"id": id, "data": {"done": true}}),
+        )
+        .await?;
+
+        // -- Exec & Check: not admin
+        let res = rpc_call(&client, "list_audit_log", json!({})).await?;
+        assert_eq!(res["error"]["code"], -32004);
+
+        // -- Exec & Check: list_audit_log
+        let res = rpc_call(
+            &admin_client,
+            "list_audit_log",
+            json!({"filters": {"entity": "task", "entity_id": id}}),
+        )
+        .await?;
+        let logs = res["result"].as_array().ok_or("should have logs")?;
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0]["rpc_method"], "create_task");
+        assert_eq!(logs[0]["action"], "create");
+        assert_eq!(logs[0]["after"]["title"], fx_title);
+        assert_eq!(logs[1]["rpc_method"], "update_task");
+        assert_eq!(logs[1]["action"], "update");
+        assert_eq!(logs[1]["before"], json!({"done": false}));
+        assert_eq!(logs[1]["after"], json!({"done": true}));
+        assert_eq!(logs[0]["user_id"], logs[1]["user_id"]);
+        assert_ne!(logs[0]["req_uuid"], logs[1]["req_uuid"]);
+
+        // -- Clean
+        rpc_call(&client, "delete_task", json!({ "id": id })).await?;
+
+        Ok(())
+    }
+}
+// endregion: -- Tests
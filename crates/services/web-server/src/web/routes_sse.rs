@@ -0,0 +1,684 @@
+//! /api/sse/tokens - Server-Sent Events stream of the token changes
+//!
+//! For the clients that can't use the /api/ws websocket (e.g., dashboards).
+//!
+//! - `GET /api/sse/tokens?filters={"mc": {"$gt": 1e9}}` (optional TokenFilter json,
+//!   one or many), authenticated like the rpc routes (mw_ctx_require).
+//! - Each event has `id:` the `{epoch}:{seq}` of the change (epoch: a uuid per server
+//!   process, seq: the token change sequence, monotonically increasing),
+//!   `event:` the change type (e.g., EntityUpdated), and `data:` the change json
+//!   (`{"type", "entity", "id", "user_id", "data"}`, `data` is the token, null when deleted).
+//! - Resume: a reconnect with `Last-Event-ID` first replays the missed changes from
+//!   a bounded in-memory buffer (the last SSE_BUFFER_CAPACITY changes). When they
+//!   are not all in the buffer anymore (or the id is of another epoch, e.g., the
+//!   server restarted), a `reset` event is sent, and the client should reload its
+//!   tokens (e.g., list_tokens).
+//!
+//! Design:
+//! - One TokenEventLog per server, fed from the ModelManager EventBus, gives the
+//!   sequence ids and holds the buffer. Each stream reads from that buffer
+//!   (replay and live), so a slow client just reads further behind.
+//! - The changes of the other processes (e.g., the token-ingest worker) reach the
+//!   EventBus through Postgres NOTIFY (see `ModelManager::listen_db_events`, started
+//!   in main). Limitation: the ones made while the web-server is down are not
+//!   notified (a reconnect after a restart gets a `reset`).
+//! - A change is sent when its token matches the filters, with the read scope of
+//!   the stream Ctx (all the tokens, whoever wrote them). Same rule as /api/ws:
+//!   the created/updated token is read from the db, the deleted one comes with the event.
+//! - The stream ends when its auth token expires, or is not valid anymore
+//!   (checked before each event, see AuthTokenW).
+
+use crate::web::mw_auth::{AuthTokenW, CtxW};
+use crate::web::{Error, Result};
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::get;
+use axum::Router;
+use futures::stream::{self, Stream};
+use lib_core::ctx::Ctx;
+use lib_core::model::event::ModelEvent;
+use lib_core::model::token::{TokenBmc, TokenFilter};
+use lib_core::model::ModelManager;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
+use tokio::time::{sleep_until, Instant};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+const SSE_BUFFER_CAPACITY: usize = 1024;
+const LAST_EVENT_ID: &str = "last-event-id";
+
+#[derive(Clone)]
+struct SseState {
+    mm: ModelManager,
+    log: Arc<TokenEventLog>,
+}
+
+pub fn routes(mm: ModelManager) -> Router {
+    let sse_state = SseState {
+        log: TokenEventLog::start(&mm),
+        mm,
+    };
+
+    Router::new()
+        .route("/sse/tokens", get(sse_tokens_handler))
+        .with_state(sse_state)
+}
+
+// region: -- Token Event Log
+/// A buffered change (event None when some bus events were lost)
+struct LogEntry {
+    seq: u64,
+    event: Option<ModelEvent>,
+}
+
+struct TokenEventLog {
+    // NOTE: The seqs restart at 0 with the process, so the event ids carry the epoch.
+    epoch: Uuid,
+    buffer: Mutex<VecDeque<Arc<LogEntry>>>,
+    // NOTE: The last seq, so the streams can wait for the next changes.
+    seq_tx: watch::Sender<u64>,
+}
+
+/// The entries after a seq, or Gap when some are not in the buffer anymore
+enum LogRead {
+    Entries(Vec<Arc<LogEntry>>),
+    Gap { last_seq: u64 },
+}
+
+impl TokenEventLog {
+    fn new() -> Self {
+        TokenEventLog {
+            epoch: Uuid::new_v4(),
+            buffer: Mutex::new(VecDeque::with_capacity(SSE_BUFFER_CAPACITY)),
+            seq_tx: watch::channel(0).0,
+        }
+    }
+
+    /// Create the log, fed by a background task from the ModelManager EventBus
+    fn start(mm: &ModelManager) -> Arc<Self> {
+        let log = Arc::new(TokenEventLog::new());
+
+        let mut events_rx = mm.events().subscribe();
+        let log_task = log.clone();
+        tokio::spawn(async move {
+            loop {
+                match events_rx.recv().await {
+                    Ok(event) if event.entity() == "token" => log_task.push(Some(event)),
+                    Ok(_) => (),
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!(
+                            "{:<12} - token event log lagged - skipped: {skipped}",
+                            "SSE"
+                        );
+                        log_task.push(None);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+
+        log
+    }
+
+    fn push(&self, event: Option<ModelEvent>) {
+        let mut buffer = self.lock_buffer();
+        let seq = *self.seq_tx.borrow() + 1;
+        if buffer.len() == SSE_BUFFER_CAPACITY {
+            buffer.pop_front();
+        }
+        buffer.push_back(Arc::new(LogEntry { seq, event }));
+        // NOTE: Sent while holding the buffer lock, so the seq order is the buffer order.
+        self.seq_tx.send_replace(seq);
+    }
+
+    fn last_seq(&self) -> u64 {
+        *self.seq_tx.borrow()
+    }
+
+    /// The SSE event id of the seq (`{epoch}:{seq}`)
+    fn event_id(&self, seq: u64) -> String {
+        format!("{}:{seq}", self.epoch)
+    }
+
+    /// The seq of an event id of this log, None when it is of another epoch (or invalid)
+    fn parse_event_id(&self, event_id: &str) -> Option<u64> {
+        let (epoch, seq) = event_id.trim().split_once(':')?;
+        if Uuid::parse_str(epoch).ok()? != self.epoch {
+            return None;
+        }
+        seq.parse().ok()
+    }
+
+    fn read_after(&self, after_seq: u64) -> LogRead {
+        let buffer = self.lock_buffer();
+        let last_seq = *self.seq_tx.borrow();
+        let first_seq = buffer
+            .front()
+            .map(|entry| entry.seq)
+            .unwrap_or(last_seq + 1);
+
+        // NOTE: An after_seq ahead of the log is from before a server restart.
+        if after_seq > last_seq || after_seq + 1 < first_seq {
+            return LogRead::Gap { last_seq };
+        }
+
+        let entries = buffer
+            .iter()
+            .filter(|entry| entry.seq > after_seq)
+            .cloned()
+            .collect();
+        LogRead::Entries(entries)
+    }
+
+    fn lock_buffer(&self) -> std::sync::MutexGuard<'_, VecDeque<Arc<LogEntry>>> {
+        self.buffer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+// endregion: -- Token Event Log
+
+// region: -- SSE Handler
+#[derive(Deserialize)]
+struct ParamsSseTokens {
+    /// The TokenFilter json (one filter object or an array of them)
+    filters: Option<String>,
+}
+
+async fn sse_tokens_handler(
+    State(SseState { mm, log }): State<SseState>,
+    ctx: CtxW,
+    auth_token: AuthTokenW,
+    Query(params): Query<ParamsSseTokens>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = core::result::Result<Event, Infallible>>>> {
+    let ctx = ctx.0;
+
+    // -- Check the filters (bad filters fail the request, not each event)
+    let filters = params
+        .filters
+        .map(|filters| serde_json::from_str::<Value>(&filters))
+        .transpose()
+        .map_err(|ex| Error::SseFiltersInvalid(ex.to_string()))?;
+    TokenBmc::get_matching(&ctx, &mm, 0, parse_filters(&filters)?).await?;
+
+    // -- Resume from the Last-Event-ID (or the current seq)
+    // NOTE: An id that is not of this log (e.g., from before a server restart)
+    // starts with a reset, as a gap.
+    let last_event_id = headers
+        .get(LAST_EVENT_ID)
+        .map(|value| value.to_str().unwrap_or_default().to_string());
+    let mut pending = VecDeque::new();
+    let last_seq = match last_event_id.as_deref().map(|id| log.parse_event_id(id)) {
+        None => log.last_seq(),
+        Some(Some(seq)) => seq,
+        Some(None) => {
+            let last_seq = log.last_seq();
+            pending.push_back(Arc::new(LogEntry {
+                seq: last_seq,
+                event: None,
+            }));
+            last_seq
+        }
+    };
+    debug!(
+        "{:<12} - sse tokens - user_id: {}, last_event_id: {last_event_id:?}",
+        "SSE",
+        ctx.user_id()
+    );
+
+    let stream_state = TokenStream {
+        ctx,
+        auth_exp: Instant::now() + auth_token.expires_in(),
+        auth_token,
+        mm,
+        seq_rx: log.seq_tx.subscribe(),
+        log,
+        filters,
+        last_seq,
+        pending,
+    };
+
+    let stream = stream::unfold(stream_state, |mut state| async move {
+        let event = state.next_event().await?;
+        Some((Ok(event), state))
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// The filters are one filter object or an array of them (or-ed)
+fn parse_filters(filters: &Option<Value>) -> Result<Option<Vec<TokenFilter>>> {
+    let filters = match filters {
+        None => return Ok(None),
+        Some(filters @ Value::Array(_)) => serde_json::from_value(filters.clone()),
+        Some(filter) => serde_json::from_value(filter.clone()).map(|f| vec![f]),
+    };
+
+    filters
+        .map(Some)
+        .map_err(|ex| Error::SseFiltersInvalid(ex.to_string()))
+}
+
+/// The state of one SSE stream
+// NOTE: The filters are kept as json, since the modql filters are consumed by the queries.
+struct TokenStream {
+    ctx: Ctx,
+    auth_token: AuthTokenW,
+    auth_exp: Instant,
+    mm: ModelManager,
+    log: Arc<TokenEventLog>,
+    seq_rx: watch::Receiver<u64>,
+    filters: Option<Value>,
+    last_seq: u64,
+    pending: VecDeque<Arc<LogEntry>>,
+}
+
+impl TokenStream {
+    /// The next SSE event (None when the log is closed or the auth token is not valid
+    /// anymore, which ends the stream)
+    async fn next_event(&mut self) -> Option<Event> {
+        loop {
+            while let Some(entry) = self.pending.pop_front() {
+                self.last_seq = entry.seq;
+                if let Some(event) = self.to_sse_event(&entry).await {
+                    return self.auth_checked(event).await;
+                }
+            }
+
+            match self.log.read_after(self.last_seq) {
+                LogRead::Entries(entries) if !entries.is_empty() => self.pending.extend(entries),
+                LogRead::Entries(_) => {
+                    // NOTE: Mark the current seq as seen, then wait for a newer one.
+                    self.seq_rx.borrow_and_update();
+                    if self.log.last_seq() == self.last_seq {
+                        tokio::select! {
+                            changed = self.seq_rx.changed() => changed.ok()?,
+                            _ = sleep_until(self.auth_exp) => return None,
+                        }
+                    }
+                }
+                LogRead::Gap { last_seq } => {
+                    self.last_seq = last_seq;
+                    return self.auth_checked(self.reset_event(last_seq)).await;
+                }
+            }
+        }
+    }
+
+    /// The event when the auth token is still valid, None otherwise
+    async fn auth_checked(&self, event: Event) -> Option<Event> {
+        match self.auth_token.revalidate(&self.ctx, &self.mm).await {
+            Ok(()) => Some(event),
+            Err(ex) => {
+                debug!(
+                    "{:<12} - sse tokens - auth not valid anymore - {ex:?}",
+                    "SSE"
+                );
+                None
+            }
+        }
+    }
+
+    /// The SSE event of the entry, None when the token does not match the filters
+    /// (or is not readable by the stream Ctx)
+    async fn to_sse_event(&self, entry: &LogEntry) -> Option<Event> {
+        let Some(event) = &entry.event else {
+            return Some(self.reset_event(entry.seq));
+        };
+
+        let (ctx, mm) = (&self.ctx, &self.mm);
+        let filters = parse_filters(&self.filters).ok()?;
+        let data = match event {
+            ModelEvent::EntityDeleted { row, .. } => TokenBmc::row_matches(ctx, mm, row, filters)
+                .await
+                .map(|matches| matches.then_some(Value::Null)),
+            _ => TokenBmc::get_matching(ctx, mm, event.id(), filters)
+                .await
+                .map(|token| token.and_then(|token| serde_json::to_value(token).ok())),
+        };
+        let data = match data {
+            Ok(data) => data?,
+            Err(ex) => {
+                debug!("{:<12} - sse tokens - {ex:?}", "SSE");
+                return None;
+            }
+        };
+
+        let mut change = serde_json::to_value(event).ok()?;
+        change["data"] = data;
+        let event_type = change["type"].as_str().unwrap_or_default().to_string();
+
+        Event::default()
+            .id(self.log.event_id(entry.seq))
+            .event(event_type)
+            .json_data(change)
+            .ok()
+    }
+
+    /// The client should reload its tokens (some changes could not be sent)
+    fn reset_event(&self, seq: u64) -> Event {
+        Event::default()
+            .id(self.log.event_id(seq))
+            .event("reset")
+            .data(json!({ "seq": seq }).to_string())
+    }
+}
+// endregion: -- SSE Handler
+
+// region: -- Tests
+#[cfg(test)]
+mod tests {
+    #![allow(unused)]
+    use super::*;
+    use crate::web::_test_utils::{
+        new_client_login_to, rpc_call, spawn_server, spawn_server_with_mm, Result,
+    };
+    use lib_core::ctx::Ctx;
+    use lib_core::model::token::{TokenBmc, TokenForCreate};
+    use serde_json::{json, Value};
+    use serial_test::serial;
+    use std::time::Duration;
+
+    /// One received SSE event (the keep-alive comments are skipped)
+    #[derive(Debug)]
+    struct SseEvent {
+        id: String,
+        event: String,
+        data: Value,
+    }
+
+    impl SseEvent {
+        /// The (epoch, seq) of the id
+        fn epoch_seq(&self) -> Result<(&str, u64)> {
+            let (epoch, seq) = self.id.split_once(':').ok_or("should be epoch:seq")?;
+            Ok((epoch, seq.parse()?))
+        }
+    }
+
+    /// Read the next event of the stream (buf holds the received, not yet parsed text)
+    async fn sse_next(res: &mut reqwest::Response, buf: &mut String) -> Result<SseEvent> {
+        loop {
+            if let Some(end) = buf.find("\n\n") {
+                let block: String = buf.drain(..end + 2).collect();
+                let field = |name: &str| {
+                    block
+                        .lines()
+                        .find_map(|line| line.strip_prefix(name))
+                        .map(|value| value.trim().to_string())
+                };
+                let Some(event) = field("event:") else {
+                    continue;
+                };
+                return Ok(SseEvent {
+                    id: field("id:").ok_or("should have id")?,
+                    event,
+                    data: serde_json::from_str(&field("data:").ok_or("should have data")?)?,
+                });
+            }
+
+            let chunk = tokio::time::timeout(Duration::from_secs(5), res.chunk())
+                .await??
+                .ok_or("sse stream closed")?;
+            buf.push_str(std::str::from_utf8(&chunk)?);
+        }
+    }
+
+    #[test]
+    fn test_token_event_log_resume_across_epochs() -> Result<()> {
+        // -- Setup & Fixtures
+        // NOTE: Two logs, as before/after a server restart (both seqs start at 0).
+        let log_before = TokenEventLog::new();
+        let log_after = TokenEventLog::new();
+        let fx_event = |id: i64| ModelEvent::EntityCreated {
+            entity: "token",
+            id,
+            user_id: 0,
+        };
+        for id in 1..=2 {
+            log_before.push(Some(fx_event(id)));
+        }
+        for id in 1..=3 {
+            log_after.push(Some(fx_event(id)));
+        }
+        let last_event_id = log_before.event_id(2);
+
+        // -- Check: same log, resumed after the seq
+        let seq = log_before
+            .parse_event_id(&last_event_id)
+            .ok_or("should parse")?;
+        assert_eq!(seq, 2);
+        assert!(
+            matches!(log_before.read_after(seq), LogRead::Entries(entries) if entries.is_empty())
+        );
+
+        // -- Check: other log, not resumed (even if its seq is known there)
+        assert_eq!(log_after.parse_event_id(&last_event_id), None);
+        assert_eq!(log_after.parse_event_id("2"), None);
+        assert_eq!(log_after.parse_event_id(&log_after.event_id(1)), Some(1));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_sse_tokens_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let base_url = spawn_server().await?;
+        let client = new_client_login_to(&base_url, "demo1", "welcome").await?;
+        // NOTE: Tokens are written by admins only (same server, for the events)
+        let admin_client = new_client_login_to(&base_url, "admin1", "welcome").await?;
+        let fx_url = format!(
+            "{base_url}/api/sse/tokens?filters={}",
+            r#"{"address":{"$startsWith":"test_sse_tokens_ok"}}"#
+        );
+        let fx_token_data = |address: &str| {
+            json!({"data": {
+                "updateUnixTime": 1710403200,
+                "updateTime": "2024-03-14T08:00:00",
+                "address": address,
+                "decimals": 6,
+                "liquidity": 100.0,
+                "logoURI": "https://example.com/logo.png",
+                "symbol": "TESTSSE",
+                "name": "Test Sse Token",
+                "mc": 1.0,
+                "v24hChangePercent": 1.5,
+                "v24hUSD": 10.0,
+                "lastTradeUnixTime": 1710403200
+            }})
+        };
+        let mut res = client.reqwest_client().get(&fx_url).send().await?;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers()["content-type"], "text/event-stream");
+        let mut buf = String::new();
+
+        // -- Exec & Check: only the token matching the filters is sent
+        let res_other = rpc_call(
+            &admin_client,
+            "create_token",
+            fx_token_data("other-test_sse_tokens_ok"),
+        )
+        .await?;
+        let other_token_id = res_other["result"]["id"].as_i64().ok_or("should have id")?;
+        let res_token = rpc_call(
+            &admin_client,
+            "create_token",
+            fx_token_data("test_sse_tokens_ok-a"),
+        )
+        .await?;
+        let token_id = res_token["result"]["id"].as_i64().ok_or("should have id")?;
+        let created = sse_next(&mut res, &mut buf).await?;
+        assert_eq!(created.event, "EntityCreated");
+        assert_eq!(created.data["id"], token_id);
+        assert_eq!(created.data["data"]["address"], "test_sse_tokens_ok-a");
+
+        // -- Exec & Check: update, with a greater id
+        rpc_call(
+            &admin_client,
+            "update_token",
+            json!({"id": token_id, "data": {"mc": 2.0}}),
+        )
+        .await?;
+        let updated = sse_next(&mut res, &mut buf).await?;
+        assert_eq!(updated.event, "EntityUpdated");
+        let (epoch, created_seq) = created.epoch_seq()?;
+        assert_eq!(updated.epoch_seq()?, (epoch, created_seq + 1));
+        assert_eq!(updated.data["data"]["mc"], 2.0);
+
+        // -- Exec & Check: resume from the Last-Event-ID (replayed from the buffer)
+        let mut res = client
+            .reqwest_client()
+            .get(&fx_url)
+            .header("Last-Event-ID", &created.id)
+            .send()
+            .await?;
+        let mut buf = String::new();
+        let replayed = sse_next(&mut res, &mut buf).await?;
+        assert_eq!(replayed.id, updated.id);
+        assert_eq!(replayed.event, "EntityUpdated");
+
+        // -- Exec & Check: resume from an unknown Last-Event-ID (ahead, other epoch)
+        let (_, updated_seq) = updated.epoch_seq()?;
+        let fx_other_epoch = Uuid::new_v4();
+        for last_event_id in [
+            format!("{epoch}:{}", updated_seq + 1000),
+            format!("{fx_other_epoch}:{created_seq}"),
+        ] {
+            let mut res = client
+                .reqwest_client()
+                .get(&fx_url)
+                .header("Last-Event-ID", last_event_id)
+                .send()
+                .await?;
+            let mut buf = String::new();
+            let reset = sse_next(&mut res, &mut buf).await?;
+            assert_eq!(reset.event, "reset");
+            assert_eq!(reset.epoch_seq()?.0, epoch);
+        }
+
+        // -- Exec & Check: bad filters, no auth
+        let res = client
+            .reqwest_client()
+            .get(format!("{base_url}/api/sse/tokens?filters=not-json"))
+            .send()
+            .await?;
+        assert_eq!(res.status(), 400);
+        let res = reqwest::get(&fx_url).await?;
+        assert_eq!(res.status(), 403);
+
+        // -- Clean
+        for id in [other_token_id, token_id] {
+            rpc_call(&admin_client, "delete_token", json!({ "id": id })).await?;
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_sse_tokens_root_write_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        // NOTE: The tokens are written with the root Ctx (like token-ingest),
+        // and streamed to a user.
+        let (base_url, mm) = spawn_server_with_mm().await?;
+        let root_ctx = Ctx::root_ctx();
+        let client = new_client_login_to(&base_url, "demo1", "welcome").await?;
+        let fx_url = format!(
+            "{base_url}/api/sse/tokens?filters={}",
+            r#"{"address":{"$startsWith":"test_sse_tokens_root_write_ok"}}"#
+        );
+        let fx_token_c = |address: &str| TokenForCreate {
+            address: address.to_string(),
+            ..Default::default()
+        };
+        let mut res = client.reqwest_client().get(&fx_url).send().await?;
+        assert_eq!(res.status(), 200);
+        let mut buf = String::new();
+
+        // -- Exec & Check: created by root, sent to demo1
+        let other_token_id = TokenBmc::create(
+            &root_ctx,
+            &mm,
+            fx_token_c("other-test_sse_tokens_root_write_ok"),
+        )
+        .await?;
+        let token_id = TokenBmc::create(
+            &root_ctx,
+            &mm,
+            fx_token_c("test_sse_tokens_root_write_ok-a"),
+        )
+        .await?;
+        let created = sse_next(&mut res, &mut buf).await?;
+        assert_eq!(created.event, "EntityCreated");
+        assert_eq!(created.data["id"], token_id);
+        assert_eq!(created.data["user_id"], 0);
+        assert_eq!(
+            created.data["data"]["address"],
+            "test_sse_tokens_root_write_ok-a"
+        );
+
+        // -- Exec & Check: deleted by root, only the token matching the filters is sent
+        TokenBmc::delete(&root_ctx, &mm, other_token_id).await?;
+        TokenBmc::delete(&root_ctx, &mm, token_id).await?;
+        let deleted = sse_next(&mut res, &mut buf).await?;
+        assert_eq!(deleted.event, "EntityDeleted");
+        assert_eq!(
+            deleted.data["id"], token_id,
+            "should skip the other token delete"
+        );
+        assert_eq!(deleted.data["data"], Value::Null);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_sse_tokens_auth_revoked_end() -> Result<()> {
+        // -- Setup & Fixtures
+        let (base_url, mm) = spawn_server_with_mm().await?;
+        let root_ctx = Ctx::root_ctx();
+        let client = new_client_login_to(&base_url, "demo1", "welcome").await?;
+        let fx_url = format!(
+            "{base_url}/api/sse/tokens?filters={}",
+            r#"{"address":{"$startsWith":"test_sse_tokens_auth_revoked_end"}}"#
+        );
+        let mut res = client.reqwest_client().get(&fx_url).send().await?;
+        assert_eq!(res.status(), 200);
+        let mut buf = String::new();
+
+        // -- Exec: logoff (rotates the token_salt), then a matching change
+        client
+            .do_post("/api/logoff", json!({"logoff": true}))
+            .await?;
+        let token_id = TokenBmc::create(
+            &root_ctx,
+            &mm,
+            TokenForCreate {
+                address: "test_sse_tokens_auth_revoked_end-a".to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        // -- Check: the stream ended rather than sent the change
+        let res_next = sse_next(&mut res, &mut buf).await;
+        assert!(
+            matches!(&res_next, Err(ex) if ex.to_string() == "sse stream closed"),
+            "should be closed, but was {res_next:?}"
+        );
+
+        // -- Clean
+        TokenBmc::delete(&root_ctx, &mm, token_id).await?;
+
+        Ok(())
+    }
+}
+// endregion: -- Tests
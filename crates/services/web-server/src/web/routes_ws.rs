@@ -0,0 +1,645 @@
+//! /api/ws - WebSocket subscriptions to the task and token changes
+//!
+//! Protocol (JSON-RPC 2.0 style, one JSON message per text frame):
+//! - Subscribe, with optional TaskFilter/TokenFilter (one or many):
+//!   `{"jsonrpc": "2.0", "id": 1, "method": "subscribe",
+//!     "params": {"entity": "token", "filters": {"mc": {"$gt": 1e9}}}}`
+//!   -> `{"jsonrpc": "2.0", "id": 1, "result": {"subscription_id": 1}}`
+//! - Unsubscribe:
+//!   `{"jsonrpc": "2.0", "id": 2, "method": "unsubscribe", "params": {"subscription_id": 1}}`
+//!   -> `{"jsonrpc": "2.0", "id": 2, "result": {"subscription_id": 1}}`
+//! - Pushed change notifications (`data` is the row, null when deleted):
+//!   `{"jsonrpc": "2.0", "method": "notification", "params": {"subscription_id": 1,
+//!     "type": "EntityUpdated", "entity": "token", "id": 1000, "user_id": 0, "data": {..}}}`
+//!
+//! Design:
+//! - The changes come from the ModelManager EventBus (see lib_core::model::event),
+//!   so they are only the committed ones.
+//! - The changes of the other processes (e.g., the token-ingest worker) reach the
+//!   EventBus through Postgres NOTIFY (see `ModelManager::listen_db_events`, started
+//!   in main). Limitation: the ones made while the web-server is down are not
+//!   notified (the clients should reload their data on reconnect).
+//! - The connection is closed (close code 1008) when its auth token expires, or
+//!   is not valid anymore (checked before each push, see AuthTokenW).
+//! - A change is notified when its row matches the subscription filters and the
+//!   read scope of the connection Ctx (so a user only gets the tasks it can read,
+//!   and all the tokens). Same rule for all the changes: the created/updated row
+//!   is read from the db, the deleted row comes with the event.
+
+use crate::web::mw_auth::{AuthTokenW, CtxW};
+use crate::web::mw_res_map::rpc_error_body;
+use crate::web::{Error, Result};
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+use futures::{SinkExt, StreamExt};
+use lib_core::ctx::Ctx;
+use lib_core::model::event::ModelEvent;
+use lib_core::model::task::{TaskBmc, TaskFilter};
+use lib_core::model::token::{TokenBmc, TokenFilter};
+use lib_core::model::ModelManager;
+use lib_rpc::JSONRPC_VERSION;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{sleep_until, Instant};
+use tracing::debug;
+use uuid::Uuid;
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new().route("/ws", get(ws_handler)).with_state(mm)
+}
+
+// NOTE: The auth (CtxW) is checked on the upgrade request (auth-token cookie
+// or Authorization header, see mw_ctx_resolve), and kept for the connection
+// with its token (AuthTokenW), until the token is not valid anymore.
+async fn ws_handler(
+    State(mm): State<ModelManager>,
+    ctx: CtxW,
+    auth_token: AuthTokenW,
+    ws: WebSocketUpgrade,
+) -> Response {
+    ws.on_upgrade(move |socket| handle_socket(socket, ctx.0, auth_token, mm))
+}
+
+// region: -- Ws Types
+#[derive(Deserialize)]
+struct WsRequest {
+    id: Option<Value>,
+    method: String,
+    params: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum WsEntity {
+    Task,
+    Token,
+}
+
+impl WsEntity {
+    fn table(&self) -> &'static str {
+        match self {
+            WsEntity::Task => "task",
+            WsEntity::Token => "token",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ParamsSubscribe {
+    entity: WsEntity,
+    filters: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct ParamsUnsubscribe {
+    subscription_id: i64,
+}
+
+/// A subscription of the connection
+// NOTE: The filters are kept as json, and deserialized for each check,
+// since the modql filters are consumed by the queries.
+struct Subscription {
+    entity: WsEntity,
+    filters: Option<Value>,
+}
+
+impl Subscription {
+    /// The row (as json) when it matches the filters, None otherwise.
+    async fn get_matching(&self, ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Option<Value>> {
+        let row = match self.entity {
+            WsEntity::Task => {
+                let filters = parse_filters::<TaskFilter>(&self.filters)?;
+                let task = TaskBmc::get_matching(ctx, mm, id, filters).await?;
+                task.map(serde_json::to_value).transpose()?
+            }
+            WsEntity::Token => {
+                let filters = parse_filters::<TokenFilter>(&self.filters)?;
+                let token = TokenBmc::get_matching(ctx, mm, id, filters).await?;
+                token.map(serde_json::to_value).transpose()?
+            }
+        };
+
+        Ok(row)
+    }
+
+    /// True when the deleted row (from the EntityDeleted event) matches the filters.
+    async fn deleted_matches(&self, ctx: &Ctx, mm: &ModelManager, row: &Value) -> Result<bool> {
+        let matches = match self.entity {
+            WsEntity::Task => {
+                let filters = parse_filters::<TaskFilter>(&self.filters)?;
+                TaskBmc::row_matches(ctx, mm, row, filters).await?
+            }
+            WsEntity::Token => {
+                let filters = parse_filters::<TokenFilter>(&self.filters)?;
+                TokenBmc::row_matches(ctx, mm, row, filters).await?
+            }
+        };
+
+        Ok(matches)
+    }
+}
+
+/// The filters are one filter object or an array of them (or-ed)
+fn parse_filters<F: DeserializeOwned>(filters: &Option<Value>) -> Result<Option<Vec<F>>> {
+    let filters = match filters {
+        None => return Ok(None),
+        Some(filters @ Value::Array(_)) => serde_json::from_value::<Vec<F>>(filters.clone()),
+        Some(filter) => serde_json::from_value::<F>(filter.clone()).map(|f| vec![f]),
+    };
+
+    filters.map(Some).map_err(|ex| Error::WsParamsInvalid {
+        method: "subscribe".to_string(),
+        reason: ex.to_string(),
+    })
+}
+// endregion: -- Ws Types
+
+// region: -- Ws Connection
+async fn handle_socket(socket: WebSocket, ctx: Ctx, auth_token: AuthTokenW, mm: ModelManager) {
+    debug!("{:<12} - ws connected - user_id: {}", "WS", ctx.user_id());
+
+    let (mut ws_tx, mut ws_rx) = socket.split();
+    let mut events_rx = mm.events().subscribe();
+    let mut subs: HashMap<i64, Subscription> = HashMap::new();
+    let mut next_sub_id = 1;
+    let auth_exp = Instant::now() + auth_token.expires_in();
+    let mut auth_ended = false;
+
+    loop {
+        let out_msgs: Vec<Value> = tokio::select! {
+            msg = ws_rx.next() => match msg {
+                Some(Ok(Message::Text(text))) => {
+                    handle_request(&ctx, &mm, &mut subs, &mut next_sub_id, &text)
+                        .await
+                        .into_iter()
+                        .collect()
+                }
+                // NOTE: The pings are answered by axum, binary frames are ignored.
+                Some(Ok(Message::Binary(_) | Message::Ping(_) | Message::Pong(_))) => continue,
+                Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+            },
+            event = events_rx.recv() => match event {
+                Ok(event) => {
+                    let msgs = notifications(&ctx, &mm, &subs, &event).await;
+                    if !msgs.is_empty() && auth_token.revalidate(&ctx, &mm).await.is_err() {
+                        auth_ended = true;
+                        break;
+                    }
+                    msgs
+                }
+                // NOTE: The client fell behind the bus, so it should reload its data.
+                Err(RecvError::Lagged(skipped)) => vec![json!({
+                    "jsonrpc": JSONRPC_VERSION,
+                    "method": "lagged",
+                    "params": {"skipped": skipped}
+                })],
+                Err(RecvError::Closed) => break,
+            },
+            _ = sleep_until(auth_exp) => {
+                auth_ended = true;
+                break;
+            }
+        };
+
+        for msg in out_msgs {
+            if ws_tx.send(Message::Text(msg.to_string())).await.is_err() {
+                debug!("{:<12} - ws send failed, closing", "WS");
+                return;
+            }
+        }
+    }
+
+    if auth_ended {
+        debug!("{:<12} - ws auth token not valid anymore, closing", "WS");
+        let close_frame = CloseFrame {
+            code: close_code::POLICY,
+            reason: "auth token expired or revoked".into(),
+        };
+        let _ = ws_tx.send(Message::Close(Some(close_frame))).await;
+    }
+
+    debug!("{:<12} - ws closed - user_id: {}", "WS", ctx.user_id());
+}
+
+/// The response of a client request (None for a JSON-RPC notification, no id)
+async fn handle_request(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    subs: &mut HashMap<i64, Subscription>,
+    next_sub_id: &mut i64,
+    text: &str,
+) -> Option<Value> {
+    let (id, res) = match serde_json::from_str::<WsRequest>(text) {
+        Ok(req) => (
+            req.id.clone(),
+            exec_request(ctx, mm, subs, next_sub_id, req).await,
+        ),
+        Err(ex) => (
+            Some(Value::Null),
+            Err(Error::RpcReqJsonParseFail(ex.to_string())),
+        ),
+    };
+    let id = id?;
+
+    match res {
+        Ok(result) => Some(json!({
+            "jsonrpc": JSONRPC_VERSION,
+            "id": id,
+            "result": result
+        })),
+        Err(err) => {
+            let req_uuid = Uuid::new_v4();
+            debug!("{:<12} - ws request {req_uuid} - {err:?}", "WS");
+            let (_, client_error) = err.client_status_and_error();
+            Some(rpc_error_body(Some(id), &client_error, req_uuid))
+        }
+    }
+}
+
+async fn exec_request(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    subs: &mut HashMap<i64, Subscription>,
+    next_sub_id: &mut i64,
+    req: WsRequest,
+) -> Result<Value> {
+    let WsRequest { method, params, .. } = req;
+
+    match method.as_str() {
+        "subscribe" => {
+            let ParamsSubscribe { entity, filters } = from_params(&method, params)?;
+            let sub = Subscription { entity, filters };
+
+            // NOTE: Run the filters once, so bad filters fail the subscribe
+            // (rather than each notification check).
+            sub.get_matching(ctx, mm, 0).await?;
+
+            let subscription_id = *next_sub_id;
+            *next_sub_id += 1;
+            subs.insert(subscription_id, sub);
+
+            Ok(json!({ "subscription_id": subscription_id }))
+        }
+        "unsubscribe" => {
+            let ParamsUnsubscribe { subscription_id } = from_params(&method, params)?;
+            subs.remove(&subscription_id)
+                .ok_or(Error::WsSubscriptionNotFound { subscription_id })?;
+
+            Ok(json!({ "subscription_id": subscription_id }))
+        }
+        _ => Err(Error::WsMethodUnknown(method)),
+    }
+}
+
+fn from_params<P: DeserializeOwned>(method: &str, params: Option<Value>) -> Result<P> {
+    serde_json::from_value(params.unwrap_or_default()).map_err(|ex| Error::WsParamsInvalid {
+        method: method.to_string(),
+        reason: ex.to_string(),
+    })
+}
+
+/// The notifications of the event, one per matching subscription
+// NOTE: The row is matched once per subscription (each subscription has its own filters).
+async fn notifications(
+    ctx: &Ctx,
+    mm: &ModelManager,
+    subs: &HashMap<i64, Subscription>,
+    event: &ModelEvent,
+) -> Vec<Value> {
+    let mut sub_ids: Vec<&i64> = subs
+        .iter()
+        .filter(|(_, sub)| sub.entity.table() == event.entity())
+        .map(|(sub_id, _)| sub_id)
+        .collect();
+    sub_ids.sort();
+
+    let mut msgs = Vec::new();
+    for sub_id in sub_ids {
+        let sub = &subs[sub_id];
+        let data = match event {
+            ModelEvent::EntityDeleted { row, .. } => sub
+                .deleted_matches(ctx, mm, row)
+                .await
+                .map(|matches| matches.then_some(Value::Null)),
+            _ => sub.get_matching(ctx, mm, event.id()).await,
+        };
+        let data = match data {
+            Ok(data) => data,
+            Err(ex) => {
+                debug!("{:<12} - ws subscription {sub_id} - {ex:?}", "WS");
+                None
+            }
+        };
+
+        if let Some(data) = data {
+            let mut params = serde_json::to_value(event).unwrap_or_default();
+            params["subscription_id"] = json!(sub_id);
+            params["data"] = data;
+            msgs.push(json!({
+                "jsonrpc": JSONRPC_VERSION,
+                "method": "notification",
+                "params": params
+            }));
+        }
+    }
+
+    msgs
+}
+// endregion: -- Ws Connection
+
+// region: -- Tests
+#[cfg(test)]
+mod tests {
+    #![allow(unused)]
+    use crate::web::_test_utils::{
+        new_client_login_to, rpc_call, spawn_server, spawn_server_with_mm, Result,
+    };
+    use futures::{SinkExt, StreamExt};
+    use lib_core::ctx::Ctx;
+    use lib_core::model::task::TaskBmc;
+    use lib_core::model::token::{TokenBmc, TokenForCreate};
+    use serde_json::{json, Value};
+    use serial_test::serial;
+    use std::time::Duration;
+    use tokio::net::TcpStream;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+    use tokio_tungstenite::tungstenite::http::HeaderValue;
+    use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+    use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+    type WsClient = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    /// Connect to /api/ws (with the auth-token cookie when given)
+    async fn fx_ws_connect(
+        base_url: &str,
+        auth_token: Option<&str>,
+    ) -> core::result::Result<WsClient, WsError> {
+        let mut req =
+            format!("{}/api/ws", base_url.replacen("http", "ws", 1)).into_client_request()?;
+        if let Some(auth_token) = auth_token {
+            let cookie = HeaderValue::from_str(&format!("auth-token={auth_token}"))?;
+            req.headers_mut().insert("Cookie", cookie);
+        }
+
+        let (ws, _) = connect_async(req).await?;
+        Ok(ws)
+    }
+
+    async fn ws_call(ws: &mut WsClient, id: i64, method: &str, params: Value) -> Result<Value> {
+        let req = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        ws.send(Message::Text(req.to_string())).await?;
+
+        // NOTE: Skip the notifications (no id) pushed in between.
+        loop {
+            let msg = ws_recv(ws).await?;
+            if msg["id"] == id {
+                return Ok(msg);
+            }
+        }
+    }
+
+    async fn ws_recv(ws: &mut WsClient) -> Result<Value> {
+        let msg = tokio::time::timeout(Duration::from_secs(5), ws.next())
+            .await?
+            .ok_or("ws closed")??;
+
+        Ok(serde_json::from_str(msg.to_text()?)?)
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_ws_subscribe_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let base_url = spawn_server().await?;
+        let client = new_client_login_to(&base_url, "demo1", "welcome").await?;
+        // NOTE: Tokens are written by admins only (same server, for the events)
+        let admin_client = new_client_login_to(&base_url, "admin1", "welcome").await?;
+        let auth_token = client
+            .cookie_value("auth-token")
+            .ok_or("should have cookie")?;
+        let mut ws = fx_ws_connect(&base_url, Some(&auth_token)).await?;
+        let fx_token_data = |address: &str| {
+            json!({"data": {
+                "updateUnixTime": 1710403200,
+                "updateTime": "2024-03-14T08:00:00",
+                "address": address,
+                "decimals": 6,
+                "liquidity": 100.0,
+                "logoURI": "https://example.com/logo.png",
+                "symbol": "TESTWS",
+                "name": "Test Ws Token",
+                "mc": 1.0,
+                "v24hChangePercent": 1.5,
+                "v24hUSD": 10.0,
+                "lastTradeUnixTime": 1710403200
+            }})
+        };
+
+        // -- Exec: subscribe
+        let res = ws_call(
+            &mut ws,
+            1,
+            "subscribe",
+            json!({"entity": "token", "filters": {"address": {"$startsWith": "test_ws_subscribe_ok"}}}),
+        )
+        .await?;
+        assert_eq!(res["result"]["subscription_id"], 1);
+        let res = ws_call(&mut ws, 2, "subscribe", json!({"entity": "task"})).await?;
+        assert_eq!(res["result"]["subscription_id"], 2);
+
+        // -- Exec & Check: only the token matching the filters is notified
+        let res = rpc_call(
+            &admin_client,
+            "create_token",
+            fx_token_data("other-test_ws_subscribe_ok"),
+        )
+        .await?;
+        let other_token_id = res["result"]["id"].as_i64().ok_or("should have id")?;
+        let res = rpc_call(
+            &admin_client,
+            "create_token",
+            fx_token_data("test_ws_subscribe_ok-a"),
+        )
+        .await?;
+        let token_id = res["result"]["id"].as_i64().ok_or("should have id")?;
+        let msg = ws_recv(&mut ws).await?;
+        assert_eq!(msg["method"], "notification");
+        assert_eq!(msg["params"]["subscription_id"], 1);
+        assert_eq!(msg["params"]["type"], "EntityCreated");
+        assert_eq!(msg["params"]["id"], token_id);
+        assert_eq!(msg["params"]["data"]["address"], "test_ws_subscribe_ok-a");
+
+        // -- Exec & Check: task created
+        let res = rpc_call(
+            &client,
+            "create_task",
+            json!({"data": {"title": "test_ws_subscribe_ok"}}),
+        )
+        .await?;
+        let task_id = res["result"]["id"].as_i64().ok_or("should have id")?;
+        let msg = ws_recv(&mut ws).await?;
+        assert_eq!(msg["params"]["subscription_id"], 2);
+        assert_eq!(msg["params"]["id"], task_id);
+        assert_eq!(msg["params"]["data"]["title"], "test_ws_subscribe_ok");
+
+        // -- Exec & Check: unsubscribe, then the task delete is not notified
+        let res = ws_call(&mut ws, 3, "unsubscribe", json!({"subscription_id": 2})).await?;
+        assert_eq!(res["result"]["subscription_id"], 2);
+        rpc_call(&client, "delete_task", json!({"id": task_id})).await?;
+        let req = json!({"jsonrpc": "2.0", "id": 4, "method": "unsubscribe", "params": {"subscription_id": 2}});
+        ws.send(Message::Text(req.to_string())).await?;
+        let res = ws_recv(&mut ws).await?;
+        assert_eq!(res["id"], 4, "should not be a notification");
+        assert_eq!(res["error"]["code"], -32003);
+
+        // -- Exec & Check: bad requests
+        let res = ws_call(&mut ws, 5, "subscribe", json!({"entity": "user"})).await?;
+        assert_eq!(res["error"]["code"], -32602);
+        let res = ws_call(&mut ws, 6, "publish", json!({})).await?;
+        assert_eq!(res["error"]["code"], -32601);
+
+        // -- Check: no auth
+        let res = fx_ws_connect(&base_url, None).await;
+        assert!(
+            matches!(&res, Err(WsError::Http(res)) if res.status() == 403),
+            "should be 403"
+        );
+
+        // -- Clean
+        for id in [other_token_id, token_id] {
+            rpc_call(&admin_client, "delete_token", json!({ "id": id })).await?;
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_ws_delete_scoping_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let (base_url, mm) = spawn_server_with_mm().await?;
+        let root_ctx = Ctx::root_ctx();
+        let client = new_client_login_to(&base_url, "demo1", "welcome").await?;
+        let admin_client = new_client_login_to(&base_url, "admin1", "welcome").await?;
+        let auth_token = client
+            .cookie_value("auth-token")
+            .ok_or("should have cookie")?;
+        let mut ws = fx_ws_connect(&base_url, Some(&auth_token)).await?;
+        ws_call(&mut ws, 1, "subscribe", json!({"entity": "task"})).await?;
+        ws_call(
+            &mut ws,
+            2,
+            "subscribe",
+            json!({"entity": "token", "filters": {"address": {"$startsWith": "test_ws_delete_scoping_ok"}}}),
+        )
+        .await?;
+        let fx_token_c = |address: &str| TokenForCreate {
+            address: address.to_string(),
+            ..Default::default()
+        };
+
+        // -- Exec & Check: own task deleted by root (not the owner), notified
+        let res = rpc_call(
+            &client,
+            "create_task",
+            json!({"data": {"title": "test_ws_delete_scoping_ok"}}),
+        )
+        .await?;
+        let task_id = res["result"]["id"].as_i64().ok_or("should have id")?;
+        let msg = ws_recv(&mut ws).await?;
+        assert_eq!(msg["params"]["type"], "EntityCreated");
+        assert_eq!(msg["params"]["id"], task_id);
+        // NOTE: The admin1 task changes are not for demo1 (checked by the next message).
+        let res = rpc_call(
+            &admin_client,
+            "create_task",
+            json!({"data": {"title": "test_ws_delete_scoping_ok - admin1"}}),
+        )
+        .await?;
+        let admin_task_id = res["result"]["id"].as_i64().ok_or("should have id")?;
+        rpc_call(&admin_client, "delete_task", json!({ "id": admin_task_id })).await?;
+        TaskBmc::delete(&root_ctx, &mm, task_id).await?;
+        let msg = ws_recv(&mut ws).await?;
+        assert_eq!(msg["params"]["subscription_id"], 1);
+        assert_eq!(msg["params"]["type"], "EntityDeleted");
+        assert_eq!(msg["params"]["id"], task_id);
+        assert_eq!(msg["params"]["user_id"], 0);
+        assert_eq!(msg["params"]["data"], Value::Null);
+
+        // -- Exec & Check: only the token delete matching the filters is notified
+        let other_token_id = TokenBmc::create(
+            &root_ctx,
+            &mm,
+            fx_token_c("other-test_ws_delete_scoping_ok"),
+        )
+        .await?;
+        let token_id =
+            TokenBmc::create(&root_ctx, &mm, fx_token_c("test_ws_delete_scoping_ok-a")).await?;
+        let msg = ws_recv(&mut ws).await?;
+        assert_eq!(msg["params"]["type"], "EntityCreated");
+        assert_eq!(msg["params"]["id"], token_id);
+        TokenBmc::delete(&root_ctx, &mm, other_token_id).await?;
+        TokenBmc::delete(&root_ctx, &mm, token_id).await?;
+        let msg = ws_recv(&mut ws).await?;
+        assert_eq!(msg["params"]["subscription_id"], 2);
+        assert_eq!(msg["params"]["type"], "EntityDeleted");
+        assert_eq!(msg["params"]["id"], token_id);
+
+        // -- Check: nothing else was notified
+        let req = json!({"jsonrpc": "2.0", "id": 3, "method": "unsubscribe", "params": {"subscription_id": 1}});
+        ws.send(Message::Text(req.to_string())).await?;
+        let res = ws_recv(&mut ws).await?;
+        assert_eq!(res["id"], 3, "should not be a notification");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_ws_auth_revoked_close() -> Result<()> {
+        // -- Setup & Fixtures
+        let (base_url, mm) = spawn_server_with_mm().await?;
+        let root_ctx = Ctx::root_ctx();
+        let client = new_client_login_to(&base_url, "demo1", "welcome").await?;
+        let auth_token = client
+            .cookie_value("auth-token")
+            .ok_or("should have cookie")?;
+        let mut ws = fx_ws_connect(&base_url, Some(&auth_token)).await?;
+        ws_call(&mut ws, 1, "subscribe", json!({"entity": "token", "filters": {"address": {"$startsWith": "test_ws_auth_revoked_close"}}})).await?;
+
+        // -- Exec: logoff (rotates the token_salt), then a matching change
+        client
+            .do_post("/api/logoff", json!({"logoff": true}))
+            .await?;
+        let token_id = TokenBmc::create(
+            &root_ctx,
+            &mm,
+            TokenForCreate {
+                address: "test_ws_auth_revoked_close-a".to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        // -- Check: closed rather than notified
+        let msg = tokio::time::timeout(Duration::from_secs(5), ws.next())
+            .await?
+            .ok_or("ws closed")??;
+        match msg {
+            Message::Close(Some(frame)) => assert_eq!(u16::from(frame.code), 1008),
+            msg => return Err(format!("should be a close frame, but was {msg:?}").into()),
+        }
+
+        // -- Clean
+        TokenBmc::delete(&root_ctx, &mm, token_id).await?;
+
+        Ok(())
+    }
+}
+// endregion: -- Tests
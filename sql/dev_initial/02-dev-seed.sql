@@ -1,2 +1,5 @@
 -- User demo1
 INSERT INTO "user" (username) VALUES ('demo1');
+
+-- User admin1 (admin role)
+INSERT INTO "user" (username, role) VALUES ('admin1', 'admin');
@@ -0,0 +1,93 @@
+-- Base app schema
+
+
+-- User
+-- NOTE: Jeremy likes to start at ID=1000 to reserve the first batch
+-- for test users, root users, etc. Just a practice.
+CREATE TABLE "user"(
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+  username varchar(128) NOT NULL UNIQUE,
+
+  -- Access control (see lib-core ctx::Role, e.g., 'user', 'admin')
+  role varchar(32) NOT NULL DEFAULT 'user',
+
+  -- Auth
+  pwd varchar(256),
+  pwd_salt uuid NOT NULL DEFAULT gen_random_uuid(),
+  token_salt uuid NOT NULL DEFAULT gen_random_uuid()
+);
+
+
+
+-- Refresh Token
+-- NOTE: Refresh tokens are single-use. Each /api/refresh marks the token as
+-- used and issues a new one in the same family (family_id). Reusing a used
+-- token revokes the whole family (stolen token detection).
+CREATE TABLE refresh_token (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+  user_id BIGINT NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+  -- The refresh token ident (jti)
+  jti uuid NOT NULL UNIQUE,
+  family_id uuid NOT NULL,
+
+  exp timestamp with time zone NOT NULL,
+  used bool NOT NULL DEFAULT false,
+  revoked bool NOT NULL DEFAULT false,
+
+  ctime timestamp with time zone NOT NULL DEFAULT now()
+);
+CREATE INDEX refresh_token_family_id_idx ON refresh_token (family_id);
+
+
+
+-- Task
+CREATE TABLE task (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+  title varchar(256) NOT NULL,
+  -- NOTE: TIP! - Usually it's good to avoid null values in a db,
+  -- so booleans help or even DB enums for three-state properties.
+  done bool NOT NULL DEFAULT false,
+
+  -- Ownership
+  -- NOTE: No FK on the user table since the root Ctx (user_id 0) can also
+  -- create rows.
+  owner_id BIGINT NOT NULL,
+
+  -- Timestamps (cid/mid are the creator/modifier user ids)
+  cid BIGINT NOT NULL,
+  ctime timestamp with time zone NOT NULL,
+  mid BIGINT NOT NULL,
+  mtime timestamp with time zone NOT NULL
+);
+
+
+-- Token
+CREATE TABLE token (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+  update_unix_time BIGINT NOT NULL,
+  update_time varchar(128) NOT NULL,
+  address varchar(128) NOT NULL,
+  decimals BIGINT NOT NULL,
+  liquidity DOUBLE PRECISION NOT NULL,
+  logo_uri TEXT NOT NULL,
+  symbol varchar(128) NOT NULL,
+  name varchar(128) NOT NULL,
+  mc DOUBLE PRECISION NOT NULL,
+  v24h_change_percent DOUBLE PRECISION NOT NULL,
+  v24h_usd DOUBLE PRECISION NOT NULL,
+  last_trade_unix_time BIGINT NOT NULL,
+
+  -- Ownership
+  -- NOTE: No FK on the user table since the root Ctx (user_id 0) can also
+  -- create rows.
+  owner_id BIGINT NOT NULL,
+
+  -- Timestamps (cid/mid are the creator/modifier user ids)
+  cid BIGINT NOT NULL,
+  ctime timestamp with time zone NOT NULL,
+  mid BIGINT NOT NULL,
+  mtime timestamp with time zone NOT NULL
+);
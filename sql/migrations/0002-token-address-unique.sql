@@ -0,0 +1,7 @@
+-- Token address unique (upsert conflict target, see TokenBmc::upsert)
+-- NOTE: Re-imports used to duplicate the tokens, so keep the latest row per address.
+DELETE FROM token t
+  USING token newer
+  WHERE t.address = newer.address AND t.id < newer.id;
+
+ALTER TABLE token ADD CONSTRAINT token_address_key UNIQUE (address);
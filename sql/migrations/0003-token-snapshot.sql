@@ -0,0 +1,18 @@
+-- Token Snapshot
+-- NOTE: The token metrics history (the token row only has the latest values).
+-- One row per token and Birdeye update time, recorded by TokenBmc on each write.
+CREATE TABLE token_snapshot (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+  token_id BIGINT NOT NULL REFERENCES token(id) ON DELETE CASCADE,
+  update_unix_time BIGINT NOT NULL,
+
+  liquidity DOUBLE PRECISION NOT NULL,
+  mc DOUBLE PRECISION NOT NULL,
+  v24h_change_percent DOUBLE PRECISION NOT NULL,
+  v24h_usd DOUBLE PRECISION NOT NULL,
+
+  ctime timestamp with time zone NOT NULL DEFAULT now(),
+
+  CONSTRAINT token_snapshot_token_time_key UNIQUE (token_id, update_unix_time)
+);
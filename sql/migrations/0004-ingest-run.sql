@@ -0,0 +1,18 @@
+-- Ingest Run
+-- NOTE: One row per ingestion run (e.g., token-ingest service paging the
+-- Birdeye tokenlist), for observability (see lib-core model::ingest_run).
+CREATE TABLE ingest_run (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+  source varchar(64) NOT NULL,
+  -- 'running', 'ok' or 'fail' (see lib-core IngestRunStatus)
+  status varchar(16) NOT NULL,
+
+  pages BIGINT NOT NULL DEFAULT 0,
+  tokens BIGINT NOT NULL DEFAULT 0,
+  retries BIGINT NOT NULL DEFAULT 0,
+  error TEXT,
+
+  started_at timestamp with time zone NOT NULL DEFAULT now(),
+  finished_at timestamp with time zone
+);
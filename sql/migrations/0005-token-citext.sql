@@ -0,0 +1,12 @@
+-- Token symbol/name case-insensitive (citext), so the TokenFilter string ops
+-- ($eq, $in, $contains, $startsWith, ...) match regardless of the case.
+-- NOTE: The address stays case-sensitive (base58).
+-- NOTE: citext is a trusted extension (PG 13+), so the db owner can create it.
+CREATE EXTENSION IF NOT EXISTS citext;
+
+-- NOTE: citext has no length modifier, so the varchar(128) limit becomes a check.
+ALTER TABLE token
+  ALTER COLUMN symbol TYPE citext,
+  ALTER COLUMN name TYPE citext,
+  ADD CONSTRAINT token_symbol_len_check CHECK (char_length(symbol) <= 128),
+  ADD CONSTRAINT token_name_len_check CHECK (char_length(name) <= 128);
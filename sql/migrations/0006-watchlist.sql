@@ -0,0 +1,35 @@
+-- Watchlist
+-- NOTE: A user's named set of tracked tokens (see lib-core model::watchlist).
+CREATE TABLE watchlist (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+  name varchar(128) NOT NULL,
+
+  -- Ownership (the Ctx user, see base::create)
+  owner_id BIGINT NOT NULL,
+
+  -- Timestamps (cid/mid are the creator/modifier user ids)
+  cid BIGINT NOT NULL,
+  ctime timestamp with time zone NOT NULL,
+  mid BIGINT NOT NULL,
+  mtime timestamp with time zone NOT NULL,
+
+  CONSTRAINT watchlist_owner_name_key UNIQUE (owner_id, name)
+);
+
+
+
+-- Watchlist Item
+-- NOTE: The watchlist/token membership. Deleting the watchlist or the
+-- token deletes the membership.
+CREATE TABLE watchlist_item (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+  watchlist_id BIGINT NOT NULL REFERENCES watchlist(id) ON DELETE CASCADE,
+  token_id BIGINT NOT NULL REFERENCES token(id) ON DELETE CASCADE,
+
+  ctime timestamp with time zone NOT NULL DEFAULT now(),
+
+  CONSTRAINT watchlist_item_watchlist_token_key UNIQUE (watchlist_id, token_id)
+);
+CREATE INDEX watchlist_item_token_id_idx ON watchlist_item (token_id);
@@ -0,0 +1,52 @@
+-- Alert Rule
+-- NOTE: A user's threshold on a token metric, evaluated after each token
+-- write (see lib-core model::alert, AlertRuleBmc::evaluate). A rule fires
+-- at most once per cooldown_sec.
+CREATE TABLE alert_rule (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+  token_id BIGINT NOT NULL REFERENCES token(id) ON DELETE CASCADE,
+  -- 'mc', 'liquidity', 'v24h_change_percent' or 'v24h_usd' (see lib-core AlertMetric)
+  metric varchar(32) NOT NULL,
+  -- 'gt', 'gte', 'lt' or 'lte' (see lib-core AlertComparator)
+  comparator varchar(8) NOT NULL,
+  threshold DOUBLE PRECISION NOT NULL,
+  cooldown_sec BIGINT NOT NULL,
+  last_triggered_at timestamp with time zone,
+
+  -- Ownership (the Ctx user, see base::create)
+  owner_id BIGINT NOT NULL,
+
+  -- Timestamps (cid/mid are the creator/modifier user ids)
+  cid BIGINT NOT NULL,
+  ctime timestamp with time zone NOT NULL,
+  mid BIGINT NOT NULL,
+  mtime timestamp with time zone NOT NULL,
+
+  CONSTRAINT alert_rule_metric_check
+    CHECK (metric IN ('mc', 'liquidity', 'v24h_change_percent', 'v24h_usd')),
+  CONSTRAINT alert_rule_comparator_check CHECK (comparator IN ('gt', 'gte', 'lt', 'lte')),
+  CONSTRAINT alert_rule_cooldown_sec_check CHECK (cooldown_sec >= 0)
+);
+CREATE INDEX alert_rule_token_id_idx ON alert_rule (token_id);
+
+
+
+-- Alert Event
+-- NOTE: One row per fired rule, with the rule and the metric value at the
+-- time (the rule can change later). owner_id is the rule owner.
+CREATE TABLE alert_event (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+  rule_id BIGINT NOT NULL REFERENCES alert_rule(id) ON DELETE CASCADE,
+  token_id BIGINT NOT NULL REFERENCES token(id) ON DELETE CASCADE,
+  owner_id BIGINT NOT NULL,
+
+  metric varchar(32) NOT NULL,
+  comparator varchar(8) NOT NULL,
+  threshold DOUBLE PRECISION NOT NULL,
+  value DOUBLE PRECISION NOT NULL,
+
+  ctime timestamp with time zone NOT NULL DEFAULT now()
+);
+CREATE INDEX alert_event_owner_id_idx ON alert_event (owner_id);
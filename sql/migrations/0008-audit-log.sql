@@ -0,0 +1,25 @@
+-- Audit Log
+-- NOTE: One row per audited entity write (create/update/delete) of an rpc call
+-- (see lib-core model::audit). For an update, before/after only hold the changed
+-- fields, for a create only after, and for a delete only before.
+CREATE TABLE audit_log (
+  id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
+
+  -- The Ctx user of the write
+  user_id BIGINT NOT NULL,
+  rpc_method varchar(128),
+  req_uuid UUID,
+
+  entity varchar(64) NOT NULL,
+  entity_id BIGINT NOT NULL,
+  action varchar(16) NOT NULL,
+  before JSONB,
+  after JSONB,
+
+  ctime timestamp with time zone NOT NULL DEFAULT now(),
+
+  CONSTRAINT audit_log_action_check CHECK (action IN ('create', 'update', 'delete'))
+);
+CREATE INDEX audit_log_entity_idx ON audit_log (entity, entity_id);
+CREATE INDEX audit_log_user_id_idx ON audit_log (user_id);
+CREATE INDEX audit_log_ctime_idx ON audit_log (ctime);